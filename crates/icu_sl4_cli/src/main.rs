@@ -1,5 +1,5 @@
 use anyhow::Result;
-use clap::{Parser, Subcommand};
+use clap::{Parser, Subcommand, ValueEnum};
 use icu_sl4_engine::*;
 use serde_json::json;
 use std::fs;
@@ -22,11 +22,27 @@ enum Cmd {
         #[arg(long)]
         input: PathBuf,
         /// Policy YAML path
+        #[arg(long, required_unless_present = "policy_dir")]
+        policy: Option<PathBuf>,
+        /// Directory of policy YAML files evaluated together as a PolicySet
+        #[arg(long, conflicts_with = "policy")]
+        policy_dir: Option<PathBuf>,
+        /// Overlay YAML applied to the policy it extends (repeatable; merged by level)
+        #[arg(long = "overlay")]
+        overlays: Vec<PathBuf>,
+        /// Detached policy signature (defaults to `<policy>.sig`, or
+        /// `<policy>.<overlay chain>.sig` with `--overlay`, when present)
+        #[arg(long, conflicts_with = "policy_dir")]
+        policy_sig: Option<PathBuf>,
+        /// Trust store of policy signing keys; unsigned or untrusted policies are refused
         #[arg(long)]
-        policy: PathBuf,
+        trust_store: Option<PathBuf>,
         /// Keypair JSON path { "secret_hex": "<64 hex>" }
         #[arg(long)]
         keypair: PathBuf,
+        /// Signal catalog YAML (defaults to the builtin catalog)
+        #[arg(long)]
+        signals: Option<PathBuf>,
         /// Binary hash (string to pin build)
         #[arg(long, default_value = "blake3:demo-binary")]
         binary_hash: String,
@@ -51,6 +67,92 @@ enum Cmd {
         #[arg(long)]
         out: PathBuf,
     },
+    /// Policy authoring tools
+    Policy {
+        #[command(subcommand)]
+        cmd: PolicyCmd,
+    },
+}
+
+#[derive(Subcommand)]
+enum PolicyCmd {
+    /// Semantic validation of policy files (exit code 1 on errors)
+    Lint {
+        /// Policy YAML files
+        #[arg(required = true)]
+        paths: Vec<PathBuf>,
+        /// Signal catalog YAML used to resolve signal names (defaults to builtin)
+        #[arg(long)]
+        signals: Option<PathBuf>,
+        #[arg(long, value_enum, default_value_t = Format::Text)]
+        format: Format,
+    },
+    /// Apply overlays to a base policy and print the effective policy YAML
+    Resolve {
+        /// Base policy YAML path
+        policy: PathBuf,
+        /// Overlay YAML (repeatable; merged by level)
+        #[arg(long = "overlay")]
+        overlays: Vec<PathBuf>,
+        /// Write the effective policy here instead of stdout
+        #[arg(long)]
+        out: Option<PathBuf>,
+    },
+    /// Write a detached Ed25519 signature for a policy (`<policy>.sig` by default,
+    /// `<policy>.<overlay chain>.sig` with `--overlay`)
+    Sign {
+        /// Policy YAML path
+        policy: PathBuf,
+        /// Overlay YAML (repeatable); signs the effective policy `resolve` prints
+        #[arg(long = "overlay")]
+        overlays: Vec<PathBuf>,
+        /// Keypair JSON path { "secret_hex": "<64 hex>" }
+        #[arg(long)]
+        keypair: PathBuf,
+        /// Key identifier as listed in the trust store
+        #[arg(long)]
+        key_id: String,
+        /// Output path for the signature JSON (defaults to where `decide` and
+        /// `--policy-dir` look for it)
+        #[arg(long)]
+        out: Option<PathBuf>,
+    },
+    /// Run the test vectors embedded in policy files (exit code 1 on mismatch)
+    Test {
+        /// Policy YAML files
+        #[arg(required = true)]
+        paths: Vec<PathBuf>,
+        /// Signal catalog YAML (defaults to the builtin catalog)
+        #[arg(long)]
+        signals: Option<PathBuf>,
+        #[arg(long, value_enum, default_value_t = Format::Text)]
+        format: Format,
+    },
+    /// Compare the decisions of two policy versions over an input corpus
+    Diff {
+        /// Baseline policy YAML
+        old: PathBuf,
+        /// Candidate policy YAML
+        new: PathBuf,
+        /// NDJSON file, one Input per line
+        #[arg(long)]
+        corpus: PathBuf,
+        /// Decision time (RFC 3339) for every input; defaults to each input's
+        /// newest observation or sample time
+        #[arg(long)]
+        at: Option<String>,
+        /// Signal catalog YAML (defaults to the builtin catalog)
+        #[arg(long)]
+        signals: Option<PathBuf>,
+        #[arg(long, value_enum, default_value_t = Format::Text)]
+        format: Format,
+    },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Format {
+    Text,
+    Json,
 }
 
 fn main() -> Result<()> {
@@ -59,20 +161,60 @@ fn main() -> Result<()> {
         Cmd::Decide {
             input,
             policy,
+            policy_dir,
+            overlays,
+            policy_sig,
+            trust_store,
             keypair,
+            signals,
             binary_hash,
             config_hash,
             out,
             ledger,
         } => {
-            let inp: Input = serde_json::from_str(&fs::read_to_string(&input)?)?;
-            let pol: Policy = load_policy_yaml(&policy)?;
+            let inp: Input = serde_json::from_str(&fs::read_to_string(input)?)?;
             let (sk, _vk) = keypair_from_json(&keypair)?;
+            let mut engine = match signals {
+                Some(path) => Engine::new(SignalCatalog::load(&path)?),
+                None => Engine::default(),
+            };
+            if let Some(path) = trust_store {
+                engine = engine.with_trust_store(TrustStore::load(&path)?);
+            }
             let now = OffsetDateTime::now_utc()
                 .format(&time::format_description::well_known::Rfc3339)
                 .unwrap();
 
-            let d = decide(&inp, &pol, &binary_hash, &config_hash, &sk, &now)?;
+            let overlays = overlays
+                .iter()
+                .map(|p| load_overlay_yaml(p))
+                .collect::<Result<Vec<_>, _>>()?;
+            let d = match (policy, policy_dir) {
+                (_, Some(dir)) => {
+                    let set = PolicySet::load_dir(&dir)?.with_overlays(&overlays)?;
+                    engine.decide_set(&inp, &set, &binary_hash, &config_hash, &sk, &now)?
+                }
+                (Some(policy), None) => {
+                    let base = load_policy_yaml(&policy)?;
+                    let effective = resolve_policy(&base, &overlays)?;
+                    let sig_path = match policy_sig {
+                        Some(p) => p,
+                        None => effective_sig_path(&policy, &base, &effective)?,
+                    };
+                    let signature = if sig_path.is_file() {
+                        Some(load_policy_signature(&sig_path)?)
+                    } else {
+                        None
+                    };
+                    let entry = PolicyEntry {
+                        policy: effective,
+                        path: Some(policy),
+                        signature,
+                    };
+                    engine.decide_entry(&inp, &entry, &binary_hash, &config_hash, &sk, &now)?
+                }
+                (None, None) => unreachable!("clap requires --policy or --policy-dir"),
+            };
 
             let mut decision_v = json!({
                 "ast": d.ast,
@@ -80,10 +222,16 @@ fn main() -> Result<()> {
                 "proof_pack": d.proof_pack,
                 "frontier": d.frontier,
             });
+            if let Some(news2) = &d.news2 {
+                decision_v["news2"] = json!(news2);
+            }
+            if let Some(sepsis) = &d.sepsis {
+                decision_v["sepsis"] = json!(sepsis);
+            }
 
             // If a ledger path is provided, append and link blockstamp
             if let Some(ledger_path) = ledger {
-                let h = ledger_append(&ledger_path, &decision_v)?;
+                let h = ledger_append(ledger_path, &decision_v)?;
                 if let Some(obj) = decision_v.as_object_mut() {
                     obj.insert(
                         "ledger_block_hash".to_string(),
@@ -94,31 +242,21 @@ fn main() -> Result<()> {
 
             fs::write(&out, serde_json::to_string_pretty(&decision_v)?)?;
             println!("Wrote decision to {}", out.display());
+            if let Some(links) = &d.ast.normative {
+                for a in &d.decision.actions {
+                    let Some(ids) = links.actions.get(&a.name) else {
+                        continue;
+                    };
+                    println!("  {}:", a.name);
+                    for c in ids.iter().filter_map(|id| links.citation(id)) {
+                        println!("    [{}] {}", c.id, c.label());
+                    }
+                }
+            }
         }
         Cmd::Verify { decision } => {
-            let v: serde_json::Value = serde_json::from_str(&fs::read_to_string(&decision)?)?;
-            let pp = &v["proof_pack"];
-            // Rebuild unsigned for verification
-            let unsigned = json!({
-                "input_hash": pp["input_hash"],
-                "ast_hash": pp["ast_hash"],
-                "policy_hash": pp["policy_hash"],
-                "binary_hash": pp["binary_hash"],
-                "config_hash": pp["config_hash"],
-                "decision_time": pp["decision_time"],
-                "tsa_token": pp["tsa_token"],
-                "sign": serde_json::Value::Null,
-                "link_prev": pp["link_prev"],
-            });
-            let canonical = json_canonical(&unsigned)?;
-            let sig_hex = pp["sign"]["sig"].as_str().unwrap();
-            let pub_hex = pp["sign"]["pubkey"].as_str().unwrap();
-            let sig_bytes = hex::decode(sig_hex).expect("bad sig hex");
-            let pk_bytes = hex::decode(pub_hex).expect("bad pub hex");
-            let sig = ed25519_dalek::Signature::from_bytes(&sig_bytes.try_into().unwrap());
-            let vk =
-                ed25519_dalek::VerifyingKey::from_bytes(&pk_bytes.try_into().unwrap()).unwrap();
-            verify_bytes(&vk, canonical.as_bytes(), &sig)?;
+            let v: serde_json::Value = serde_json::from_str(&fs::read_to_string(decision)?)?;
+            verify_proof_pack(&v["proof_pack"])?;
             println!("✓ Signature valid");
         }
         Cmd::GenKey { out } => {
@@ -127,7 +265,210 @@ fn main() -> Result<()> {
             let doc = serde_json::json!({ "secret_hex": hex_secret });
             fs::write(&out, serde_json::to_string_pretty(&doc)?)?;
             println!("Wrote keypair secret to {}", out.display());
+            println!("pubkey: {}", verifying_key_to_hex(&sk.verifying_key()));
+        }
+        Cmd::Policy {
+            cmd:
+                PolicyCmd::Lint {
+                    paths,
+                    signals,
+                    format,
+                },
+        } => {
+            let env = match signals {
+                Some(path) => TypeEnv::for_catalog(&SignalCatalog::load(&path)?),
+                None => TypeEnv::default(),
+            };
+            let mut failed = false;
+            let mut reports = Vec::new();
+            for path in &paths {
+                let report = lint_policy_yaml_with(&fs::read_to_string(path)?, &env);
+                failed |= report.has_errors();
+                match format {
+                    Format::Text => print!("{}", report.to_text(&path.display().to_string())),
+                    Format::Json => reports.push(json!({
+                        "file": path.display().to_string(),
+                        "diagnostics": report.diagnostics,
+                    })),
+                }
+            }
+            if let Format::Json = format {
+                println!("{}", serde_json::to_string_pretty(&reports)?);
+            }
+            if failed {
+                std::process::exit(1);
+            }
+        }
+        Cmd::Policy {
+            cmd:
+                PolicyCmd::Resolve {
+                    policy,
+                    overlays,
+                    out,
+                },
+        } => {
+            let overlays = overlays
+                .iter()
+                .map(|p| load_overlay_yaml(p))
+                .collect::<Result<Vec<_>, _>>()?;
+            let eff = resolve_policy(&load_policy_yaml(&policy)?, &overlays)?;
+            let yaml = serde_yaml::to_string(&eff)?;
+            match out {
+                Some(out) => {
+                    fs::write(&out, yaml)?;
+                    println!("Wrote effective policy to {}", out.display());
+                }
+                None => print!("{yaml}"),
+            }
+            eprintln!("policy_hash: {}", policy_hash(&eff)?);
+        }
+        Cmd::Policy {
+            cmd:
+                PolicyCmd::Sign {
+                    policy,
+                    overlays,
+                    keypair,
+                    key_id,
+                    out,
+                },
+        } => {
+            let overlays = overlays
+                .iter()
+                .map(|p| load_overlay_yaml(p))
+                .collect::<Result<Vec<_>, _>>()?;
+            let base = load_policy_yaml(&policy)?;
+            let pol = resolve_policy(&base, &overlays)?;
+            let (sk, _vk) = keypair_from_json(&keypair)?;
+            let sig = sign_policy(&pol, &key_id, &sk)?;
+            let out = match out {
+                Some(out) => out,
+                None => effective_sig_path(&policy, &base, &pol)?,
+            };
+            fs::write(&out, serde_json::to_string_pretty(&sig)?)?;
+            println!("Wrote policy signature to {}", out.display());
+        }
+        Cmd::Policy {
+            cmd:
+                PolicyCmd::Test {
+                    paths,
+                    signals,
+                    format,
+                },
+        } => {
+            let engine = match signals {
+                Some(path) => Engine::new(SignalCatalog::load(&path)?),
+                None => Engine::default(),
+            };
+            let mut failed = false;
+            let mut reports = Vec::new();
+            for path in &paths {
+                let file = path.display().to_string();
+                let pol = load_policy_yaml(path)?;
+                let results = run_policy_tests(&engine, &pol);
+                failed |= results.iter().any(|r| !r.passed);
+                match format {
+                    Format::Text => {
+                        if results.is_empty() {
+                            println!("{file}: no test vectors");
+                        }
+                        for r in &results {
+                            let status = if r.passed { "ok" } else { "FAILED" };
+                            println!("{file}: {} ... {status}", r.name);
+                            for m in r.error.iter().chain(&r.mismatches) {
+                                println!("    {m}");
+                            }
+                        }
+                    }
+                    Format::Json => reports.push(json!({
+                        "file": file,
+                        "protocol_id": pol.protocol_id,
+                        "results": results,
+                    })),
+                }
+            }
+            if let Format::Json = format {
+                println!("{}", serde_json::to_string_pretty(&reports)?);
+            }
+            if failed {
+                std::process::exit(1);
+            }
+        }
+        Cmd::Policy {
+            cmd:
+                PolicyCmd::Diff {
+                    old,
+                    new,
+                    corpus,
+                    at,
+                    signals,
+                    format,
+                },
+        } => {
+            let engine = match signals {
+                Some(path) => Engine::new(SignalCatalog::load(&path)?),
+                None => Engine::default(),
+            };
+            let old = load_policy_yaml(&old)?;
+            let new = load_policy_yaml(&new)?;
+            let corpus = parse_input_corpus(&fs::read_to_string(corpus)?)?;
+            let report = diff_policies(&engine, &old, &new, &corpus, at.as_deref());
+            match format {
+                Format::Text => print_policy_diff(&report),
+                Format::Json => println!("{}", serde_json::to_string_pretty(&report)?),
+            }
         }
     }
     Ok(())
 }
+
+fn print_policy_diff(r: &PolicyDiffReport) {
+    println!("{} -> {}", r.old, r.new);
+    for d in &r.changes {
+        println!("line {} (at {}):", d.line, d.at);
+        if let Some(r) = &d.old_refused {
+            println!("    old refused: {r}");
+        }
+        if let Some(r) = &d.new_refused {
+            println!("    new refused: {r}");
+        }
+        if let Some(e) = &d.error {
+            println!("    error: {e}");
+        }
+        if let Some((a, b)) = &d.severity {
+            println!("    severity: {a:?} -> {b:?}");
+        }
+        for a in &d.actions_added {
+            println!("    + action {a}");
+        }
+        for a in &d.actions_removed {
+            println!("    - action {a}");
+        }
+        for c in &d.deadlines {
+            let fmt = |d: Option<u64>| d.map_or("-".to_string(), |s| format!("{s}s"));
+            println!(
+                "    ~ deadline {}: {} -> {}",
+                c.action,
+                fmt(c.before),
+                fmt(c.after)
+            );
+        }
+        for h in &d.hazards_added {
+            println!("    + hazard {h}");
+        }
+        for h in &d.hazards_removed {
+            println!("    - hazard {h}");
+        }
+    }
+    let s = &r.summary;
+    println!(
+        "{} of {} inputs changed (severity {}, actions {}, deadlines {}, hazards {}, lifecycle {}, errors {})",
+        s.changed,
+        s.inputs,
+        s.severity_changed,
+        s.actions_changed,
+        s.deadlines_changed,
+        s.hazards_changed,
+        s.lifecycle_refused,
+        s.errors
+    );
+}
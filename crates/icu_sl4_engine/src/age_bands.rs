@@ -0,0 +1,312 @@
+//! Versioned age-band vital-sign tables for neonates, infants and children.
+//!
+//! Adult thresholds (`hr_bpm > 100` for tachycardia) are wrong for most
+//! children. When `Input.patient` gives an age below the last band, the
+//! limits of that age band replace the catalog thresholds of the signals
+//! each table is bound to (`low_signal`, `high_signal`), in both channels and
+//! in the frontier certificates; signals the catalog does not define are
+//! skipped. Each table carries its own version and citation, and the hash
+//! of the whole document is pinned in every `ProofPack`.
+
+use crate::normative::Citation;
+use crate::trigger::KNOWN_FEATURES;
+use crate::{blake3_hash_json, SignalCatalog};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::Path;
+use thiserror::Error;
+
+const BUILTIN_AGE_BANDS_YAML: &str = include_str!("../catalog/age_bands.yaml");
+
+static BUILTIN_AGE_BANDS: Lazy<AgeBandTables> = Lazy::new(|| {
+    AgeBandTables::from_yaml(BUILTIN_AGE_BANDS_YAML).expect("builtin age-band tables are valid")
+});
+
+#[derive(Debug, Error)]
+pub enum AgeBandError {
+    #[error("age-band YAML: {0}")]
+    Yaml(#[from] serde_yaml::Error),
+    #[error("age-band I/O: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("age band `{band}`: {msg}")]
+    Band { band: String, msg: String },
+    #[error("table `{table}`: {msg}")]
+    Table { table: String, msg: String },
+}
+
+/// Ages in `[min_age_years, max_age_years)`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct AgeBand {
+    pub id: String,
+    pub min_age_years: f64,
+    pub max_age_years: f64,
+}
+
+/// Normal range of a feature within one band; outside it, the bound
+/// signal fires.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct BandLimits {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub low: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub high: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct VitalTable {
+    pub id: String,
+    pub version: String,
+    pub feature: String,
+    pub citation: Citation,
+    /// Signal whose threshold on `feature` becomes the band's `low`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub low_signal: Option<String>,
+    /// Signal whose threshold on `feature` becomes the band's `high`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub high_signal: Option<String>,
+    /// Limits per band id; every band must be listed.
+    pub limits: BTreeMap<String, BandLimits>,
+}
+
+/// Serialized form of the tables; this is what gets hashed.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct AgeBandDoc {
+    pub tables_id: String,
+    pub version: String,
+    /// Contiguous bands, youngest first.
+    pub bands: Vec<AgeBand>,
+    pub tables: Vec<VitalTable>,
+}
+
+/// Validated age-band tables.
+#[derive(Debug, Clone)]
+pub struct AgeBandTables {
+    pub doc: AgeBandDoc,
+}
+
+impl AgeBandTables {
+    /// The tables shipped with the engine.
+    pub fn builtin() -> &'static AgeBandTables {
+        &BUILTIN_AGE_BANDS
+    }
+
+    pub fn from_yaml(s: &str) -> Result<Self, AgeBandError> {
+        Self::from_doc(serde_yaml::from_str(s)?)
+    }
+
+    pub fn load(path: &Path) -> Result<Self, AgeBandError> {
+        Self::from_yaml(&fs::read_to_string(path)?)
+    }
+
+    pub fn from_doc(doc: AgeBandDoc) -> Result<Self, AgeBandError> {
+        let mut prev_max = 0.0;
+        let mut band_ids = BTreeSet::new();
+        for band in &doc.bands {
+            let bad = |msg: &str| AgeBandError::Band {
+                band: band.id.clone(),
+                msg: msg.to_string(),
+            };
+            if !(band.min_age_years.is_finite() && band.max_age_years.is_finite()) {
+                return Err(bad("age bounds must be finite"));
+            }
+            if band.min_age_years >= band.max_age_years {
+                return Err(bad("`min_age_years` must be below `max_age_years`"));
+            }
+            if band.min_age_years != prev_max {
+                return Err(bad("bands must be contiguous from age 0, youngest first"));
+            }
+            if !band_ids.insert(band.id.as_str()) {
+                return Err(bad("declared twice"));
+            }
+            prev_max = band.max_age_years;
+        }
+        let mut table_ids = BTreeSet::new();
+        for t in &doc.tables {
+            let bad = |msg: String| AgeBandError::Table {
+                table: t.id.clone(),
+                msg,
+            };
+            if !table_ids.insert(t.id.as_str()) {
+                return Err(bad("declared twice".into()));
+            }
+            if !KNOWN_FEATURES.contains(&t.feature.as_str()) {
+                return Err(bad(format!("unknown feature `{}`", t.feature)));
+            }
+            if t.low_signal.is_none() && t.high_signal.is_none() {
+                return Err(bad("needs `low_signal` or `high_signal`".into()));
+            }
+            if let Some(b) = band_ids.iter().find(|b| !t.limits.contains_key(**b)) {
+                return Err(bad(format!("no limits for band `{b}`")));
+            }
+            for (band, l) in &t.limits {
+                if !band_ids.contains(band.as_str()) {
+                    return Err(bad(format!("limits for unknown band `{band}`")));
+                }
+                let needed = [(&t.low_signal, l.low), (&t.high_signal, l.high)];
+                if needed.iter().any(|(s, v)| s.is_some() && v.is_none()) {
+                    return Err(bad(format!("band `{band}` lacks a bound signal's limit")));
+                }
+                if [l.low, l.high].iter().flatten().any(|v| !v.is_finite()) {
+                    return Err(bad(format!("band `{band}` has a non-finite limit")));
+                }
+                if let (Some(lo), Some(hi)) = (l.low, l.high) {
+                    if lo >= hi {
+                        return Err(bad(format!("band `{band}` has `low` >= `high`")));
+                    }
+                }
+            }
+        }
+        Ok(AgeBandTables { doc })
+    }
+
+    /// Band containing `age_years`; `None` past the last band.
+    pub fn band_for(&self, age_years: f64) -> Option<&AgeBand> {
+        self.doc
+            .bands
+            .iter()
+            .find(|b| (b.min_age_years..b.max_age_years).contains(&age_years))
+    }
+
+    /// Table setting the threshold of `signal` on `feature`, if any.
+    pub fn table_for(&self, signal: &str, feature: &str) -> Option<&VitalTable> {
+        self.doc.tables.iter().find(|t| {
+            t.feature == feature
+                && (t.low_signal.as_deref() == Some(signal)
+                    || t.high_signal.as_deref() == Some(signal))
+        })
+    }
+
+    /// Set the thresholds of `band` on `catalog`.
+    pub(crate) fn apply(&self, band: &AgeBand, catalog: &mut SignalCatalog) {
+        for t in &self.doc.tables {
+            let l = &t.limits[&band.id];
+            for (signal, limit) in [(&t.low_signal, l.low), (&t.high_signal, l.high)] {
+                if let (Some(signal), Some(limit)) = (signal, limit) {
+                    catalog.set_threshold(signal, &t.feature, limit);
+                }
+            }
+        }
+    }
+
+    pub fn hash(&self) -> anyhow::Result<String> {
+        blake3_hash_json(&serde_json::to_value(&self.doc)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Engine, Input};
+    use ed25519_dalek::SigningKey;
+    use serde_json::json;
+
+    fn band(age_years: f64) -> Option<&'static str> {
+        AgeBandTables::builtin()
+            .band_for(age_years)
+            .map(|b| b.id.as_str())
+    }
+
+    #[test]
+    fn bands_are_closed_below_and_open_above() {
+        assert_eq!(band(0.0), Some("neonate"));
+        assert_eq!(band(0.0766), Some("neonate"));
+        assert_eq!(band(0.0767), Some("infant"));
+        assert_eq!(band(0.999), Some("infant"));
+        assert_eq!(band(1.0), Some("toddler"));
+        assert_eq!(band(17.99), Some("adolescent"));
+        // Adults fall back to the catalog's thresholds.
+        assert_eq!(band(18.0), None);
+        assert_eq!(band(90.0), None);
+    }
+
+    fn doc() -> AgeBandDoc {
+        AgeBandTables::builtin().doc.clone()
+    }
+
+    #[test]
+    fn malformed_tables_are_rejected() {
+        let mut gap = doc();
+        gap.bands[1].min_age_years = 0.1;
+        assert!(matches!(
+            AgeBandTables::from_doc(gap),
+            Err(AgeBandError::Band { band, .. }) if band == "infant"
+        ));
+        let mut missing = doc();
+        missing.tables[0].limits.remove("toddler");
+        assert!(matches!(
+            AgeBandTables::from_doc(missing),
+            Err(AgeBandError::Table { msg, .. }) if msg == "no limits for band `toddler`"
+        ));
+        let mut inverted = doc();
+        inverted.tables[0].limits.get_mut("infant").unwrap().low = Some(200.0);
+        assert!(AgeBandTables::from_doc(inverted).is_err());
+        let mut unbound = doc();
+        unbound.tables[0].high_signal = None;
+        unbound.tables[0].low_signal = None;
+        assert!(AgeBandTables::from_doc(unbound).is_err());
+    }
+
+    #[test]
+    fn decisions_pin_the_tables_hash_and_the_band() {
+        let input: Input = serde_json::from_value(json!({
+            "text": "",
+            "measured": { "hr_bpm": 150 },
+            "patient": { "age_years": 0.5 },
+        }))
+        .unwrap();
+        let policy = crate::parse_policy_yaml(
+            "\
+protocol_id: p
+version: 1.0.0
+jurisdiction: PT
+source: test
+triggers:
+  - hr_bpm > 200
+severity: URGENT
+hazards:
+  CRITICAL: HYPOXEMIA_CRITICAL
+  URGENT: HYPOXEMIA_MODERATE
+actions:
+  - name: call_attending
+    max_delay_s: 60
+",
+        )
+        .unwrap();
+        let decide = |engine: &Engine| {
+            engine
+                .decide(
+                    &input,
+                    &policy,
+                    "blake3:test",
+                    "blake3:test",
+                    &SigningKey::from_bytes(&[0u8; 32]),
+                    "2024-01-01T00:00:00Z",
+                )
+                .unwrap()
+        };
+        let engine = Engine::default();
+        let out = decide(&engine);
+        assert_eq!(out.ast.age_band.as_deref(), Some("infant"));
+        // 150/min is normal for an infant (limit 180), not tachycardia.
+        assert!(!out.ast.signals.iter().any(|s| s == "tachycardia"));
+        assert_eq!(
+            out.proof_pack.age_bands_hash,
+            Some(AgeBandTables::builtin().hash().unwrap())
+        );
+
+        let mut edited = doc();
+        edited.tables[0].limits.get_mut("infant").unwrap().high = Some(140.0);
+        let engine = Engine {
+            age_bands: AgeBandTables::from_doc(edited).unwrap(),
+            ..Engine::default()
+        };
+        let out2 = decide(&engine);
+        assert!(out2.ast.signals.iter().any(|s| s == "tachycardia"));
+        assert_ne!(
+            out2.proof_pack.age_bands_hash,
+            out.proof_pack.age_bands_hash
+        );
+    }
+}
@@ -0,0 +1,492 @@
+//! Versioned signal catalog consumed by both TDLN channels.
+//!
+//! The catalog declares each signal's text patterns and numeric predicates,
+//! the severity rules and proposed actions built on top of them, the
+//! plausibility limits of each measured feature and the patient threshold
+//! profiles, so clinical governance can change detection without touching
+//! Rust: the channels hold no thresholds, severities or actions of their
+//! own. Its content hash is pinned in every `ProofPack`.
+
+use crate::patient::ThresholdProfile;
+use crate::series::{window_error, Series, SeriesFn};
+use crate::trigger::{RelOp, KNOWN_FEATURES};
+use crate::{blake3_hash_json, Action, Severity};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::Path;
+use thiserror::Error;
+
+const BUILTIN_CATALOG_YAML: &str = include_str!("../catalog/signals.yaml");
+
+static BUILTIN_CATALOG: Lazy<SignalCatalog> = Lazy::new(|| {
+    SignalCatalog::from_yaml(BUILTIN_CATALOG_YAML).expect("builtin signal catalog is valid")
+});
+
+#[derive(Debug, Error)]
+pub enum CatalogError {
+    #[error("catalog YAML: {0}")]
+    Yaml(#[from] serde_yaml::Error),
+    #[error("catalog I/O: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("duplicate signal `{0}`")]
+    DuplicateSignal(String),
+    #[error("signal `{0}` has no text pattern, numeric or trend predicate")]
+    EmptySignal(String),
+    #[error("signal `{signal}`: trend on `{feature}`: {msg}")]
+    Trend {
+        signal: String,
+        feature: String,
+        msg: String,
+    },
+    #[error("signal `{signal}`: invalid pattern `{pattern}`: {msg}")]
+    Pattern {
+        signal: String,
+        pattern: String,
+        msg: String,
+    },
+    #[error("signal `{signal}`: unknown feature `{feature}`")]
+    UnknownFeature { signal: String, feature: String },
+    #[error("signal `{signal}`: threshold for `{feature}` is not finite")]
+    BadThreshold { signal: String, feature: String },
+    #[error("severity rule #{index}: unknown signal `{signal}`")]
+    UnknownSignal { index: usize, signal: String },
+    #[error("severity rule #{index}: needs `any` or `all`")]
+    EmptyRule { index: usize },
+    #[error("severity rule #{index}: rules must be ordered from most to least severe")]
+    RuleOrder { index: usize },
+    #[error("action rule `{name}`: {msg}")]
+    ActionRule { name: String, msg: String },
+    #[error("plausibility of `{feature}`: {msg}")]
+    Plausibility { feature: String, msg: String },
+    #[error("profile `{profile}`: {msg}")]
+    Profile { profile: String, msg: String },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct NumericPredicate {
+    pub feature: String,
+    pub relation: RelOp,
+    pub threshold: f64,
+}
+
+impl NumericPredicate {
+    pub fn holds(&self, v: f64) -> bool {
+        v.is_finite() && self.relation.holds(v, self.threshold)
+    }
+}
+
+/// A threshold on a trend function of `Input.series`, e.g. SpO2 falling 6
+/// points over 15 minutes.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TrendPredicate {
+    pub function: SeriesFn,
+    pub feature: String,
+    pub window_s: u32,
+    pub relation: RelOp,
+    pub threshold: f64,
+}
+
+impl TrendPredicate {
+    pub fn holds(&self, series: &Series) -> bool {
+        series
+            .eval(self.function, &self.feature, self.window_s)
+            .is_some_and(|v| self.relation.holds(v, self.threshold))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SignalDef {
+    pub id: String,
+    #[serde(default)]
+    pub text: Vec<String>,
+    #[serde(default)]
+    pub numeric: Vec<NumericPredicate>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub trend: Vec<TrendPredicate>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SeverityRule {
+    pub level: Severity,
+    #[serde(default)]
+    pub any: Vec<String>,
+    #[serde(default)]
+    pub all: Vec<String>,
+}
+
+impl SeverityRule {
+    pub fn matches(&self, signals: &BTreeSet<String>) -> bool {
+        (self.any.is_empty() || self.any.iter().any(|s| signals.contains(s)))
+            && self.all.iter().all(|s| signals.contains(s))
+    }
+}
+
+/// An action both channels propose when the detected signals match: any of
+/// `any` (when given), all of `all` and none of `none`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ActionRule {
+    pub name: String,
+    pub max_delay_s: u64,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub any: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub all: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub none: Vec<String>,
+}
+
+impl ActionRule {
+    pub fn matches(&self, signals: &BTreeSet<String>) -> bool {
+        (self.any.is_empty() || self.any.iter().any(|s| signals.contains(s)))
+            && self.all.iter().all(|s| signals.contains(s))
+            && !self.none.iter().any(|s| signals.contains(s))
+    }
+
+    pub fn action(&self) -> Action {
+        Action {
+            name: self.name.clone(),
+            max_delay_s: self.max_delay_s,
+            deadline_s: None,
+            when: None,
+            conflicts_with: vec![],
+            cites: vec![],
+        }
+    }
+}
+
+/// Physiological limits of one measured feature.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PlausibilityRule {
+    pub feature: String,
+    pub min: f64,
+    pub max: f64,
+    /// Largest fall from `Input.previous` a single sample can show.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_drop: Option<f64>,
+    /// The value needs a pulse: it is an artifact when `hr_bpm` is measured
+    /// below its plausible minimum.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub requires_pulse: bool,
+}
+
+/// Serialized form of the catalog; this is what gets hashed.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CatalogDoc {
+    pub catalog_id: String,
+    pub version: String,
+    pub signals: Vec<SignalDef>,
+    #[serde(default)]
+    pub severity: Vec<SeverityRule>,
+    /// Actions the channels propose; the policy decides what is done.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub actions: Vec<ActionRule>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub plausibility: Vec<PlausibilityRule>,
+    /// Patient threshold profiles, tried in order.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub profiles: Vec<ThresholdProfile>,
+}
+
+/// A validated catalog with its text patterns compiled.
+#[derive(Debug, Clone)]
+pub struct SignalCatalog {
+    pub doc: CatalogDoc,
+    /// Compiled `text` patterns, parallel to `doc.signals`.
+    patterns: Vec<Vec<Regex>>,
+}
+
+impl SignalCatalog {
+    /// The catalog shipped with the engine.
+    pub fn builtin() -> &'static SignalCatalog {
+        &BUILTIN_CATALOG
+    }
+
+    pub fn from_yaml(s: &str) -> Result<Self, CatalogError> {
+        Self::from_doc(serde_yaml::from_str(s)?)
+    }
+
+    pub fn load(path: &Path) -> Result<Self, CatalogError> {
+        Self::from_yaml(&fs::read_to_string(path)?)
+    }
+
+    pub fn from_doc(doc: CatalogDoc) -> Result<Self, CatalogError> {
+        let mut ids = BTreeSet::new();
+        let mut patterns = Vec::with_capacity(doc.signals.len());
+        for sig in &doc.signals {
+            if !ids.insert(sig.id.clone()) {
+                return Err(CatalogError::DuplicateSignal(sig.id.clone()));
+            }
+            if sig.text.is_empty() && sig.numeric.is_empty() && sig.trend.is_empty() {
+                return Err(CatalogError::EmptySignal(sig.id.clone()));
+            }
+            for p in &sig.numeric {
+                if !KNOWN_FEATURES.contains(&p.feature.as_str()) {
+                    return Err(CatalogError::UnknownFeature {
+                        signal: sig.id.clone(),
+                        feature: p.feature.clone(),
+                    });
+                }
+                if !p.threshold.is_finite() {
+                    return Err(CatalogError::BadThreshold {
+                        signal: sig.id.clone(),
+                        feature: p.feature.clone(),
+                    });
+                }
+            }
+            for t in &sig.trend {
+                let msg = if !KNOWN_FEATURES.contains(&t.feature.as_str()) {
+                    Some("unknown feature".to_string())
+                } else if !t.threshold.is_finite() {
+                    Some("threshold is not finite".to_string())
+                } else {
+                    window_error(t.window_s)
+                };
+                if let Some(msg) = msg {
+                    return Err(CatalogError::Trend {
+                        signal: sig.id.clone(),
+                        feature: t.feature.clone(),
+                        msg,
+                    });
+                }
+            }
+            let mut compiled = Vec::with_capacity(sig.text.len());
+            for pat in &sig.text {
+                let re = Regex::new(&format!("(?i){pat}")).map_err(|e| CatalogError::Pattern {
+                    signal: sig.id.clone(),
+                    pattern: pat.clone(),
+                    msg: e.to_string(),
+                })?;
+                compiled.push(re);
+            }
+            patterns.push(compiled);
+        }
+        let mut prev_rank = u8::MAX;
+        for (index, rule) in doc.severity.iter().enumerate() {
+            if rule.any.is_empty() && rule.all.is_empty() {
+                return Err(CatalogError::EmptyRule { index });
+            }
+            for s in rule.any.iter().chain(&rule.all) {
+                if !ids.contains(s) {
+                    return Err(CatalogError::UnknownSignal {
+                        index,
+                        signal: s.clone(),
+                    });
+                }
+            }
+            if rule.level.rank() > prev_rank {
+                return Err(CatalogError::RuleOrder { index });
+            }
+            prev_rank = rule.level.rank();
+        }
+        let mut action_names = BTreeSet::new();
+        for rule in &doc.actions {
+            let bad = |msg: String| CatalogError::ActionRule {
+                name: rule.name.clone(),
+                msg,
+            };
+            if !action_names.insert(rule.name.as_str()) {
+                return Err(bad("declared twice".into()));
+            }
+            if rule.any.is_empty() && rule.all.is_empty() {
+                return Err(bad("needs `any` or `all`".into()));
+            }
+            if let Some(s) = [&rule.any, &rule.all, &rule.none]
+                .into_iter()
+                .flatten()
+                .find(|s| !ids.contains(*s))
+            {
+                return Err(bad(format!("unknown signal `{s}`")));
+            }
+        }
+        let mut limited = BTreeSet::new();
+        for rule in &doc.plausibility {
+            let bad = |msg: &str| CatalogError::Plausibility {
+                feature: rule.feature.clone(),
+                msg: msg.to_string(),
+            };
+            if !KNOWN_FEATURES.contains(&rule.feature.as_str()) {
+                return Err(bad("unknown feature"));
+            }
+            if !limited.insert(rule.feature.as_str()) {
+                return Err(bad("declared twice"));
+            }
+            if !(rule.min.is_finite() && rule.max.is_finite() && rule.min < rule.max) {
+                return Err(bad("`min` and `max` must be finite with min < max"));
+            }
+            if rule.max_drop.is_some_and(|d| !(d.is_finite() && d > 0.0)) {
+                return Err(bad("`max_drop` must be a positive number"));
+            }
+        }
+        let mut profile_ids = BTreeSet::new();
+        for profile in &doc.profiles {
+            let bad = |msg: String| CatalogError::Profile {
+                profile: profile.id.clone(),
+                msg,
+            };
+            if !profile_ids.insert(profile.id.as_str()) {
+                return Err(bad("declared twice".into()));
+            }
+            let when = &profile.when;
+            if when.is_empty() {
+                return Err(bad("`when` must name at least one criterion".into()));
+            }
+            let ages = [when.min_age_years, when.max_age_years];
+            if ages.iter().flatten().any(|a| !(a.is_finite() && *a >= 0.0)) {
+                return Err(bad("age bounds must be non-negative numbers".into()));
+            }
+            if let [Some(min), Some(max)] = ages {
+                if min >= max {
+                    return Err(bad("`min_age_years` must be below `max_age_years`".into()));
+                }
+            }
+            if let Some(f) = when
+                .baseline
+                .iter()
+                .find(|f| !KNOWN_FEATURES.contains(&f.as_str()))
+            {
+                return Err(bad(format!("baseline of unknown feature `{f}`")));
+            }
+            for o in &profile.thresholds {
+                let target = doc.signals.iter().find(|s| s.id == o.signal);
+                if !target.is_some_and(|s| s.numeric.iter().any(|p| p.feature == o.feature)) {
+                    return Err(bad(format!(
+                        "signal `{}` has no numeric predicate on `{}`",
+                        o.signal, o.feature
+                    )));
+                }
+                match (o.threshold, o.baseline_offset) {
+                    (Some(v), None) | (None, Some(v)) if v.is_finite() => {}
+                    _ => {
+                        return Err(bad(format!(
+                            "`{}` of `{}` needs one finite `threshold` or `baseline_offset`",
+                            o.feature, o.signal
+                        )))
+                    }
+                }
+                if o.baseline_offset.is_some() && !when.baseline.contains(&o.feature) {
+                    return Err(bad(format!(
+                        "`baseline_offset` on `{}` needs `when.baseline` to include it",
+                        o.feature
+                    )));
+                }
+            }
+        }
+        Ok(SignalCatalog { doc, patterns })
+    }
+
+    pub fn signal_ids(&self) -> impl Iterator<Item = &str> {
+        self.doc.signals.iter().map(|s| s.id.as_str())
+    }
+
+    /// Signal definitions paired with their compiled text patterns.
+    pub fn signals(&self) -> impl Iterator<Item = (&SignalDef, &[Regex])> {
+        self.doc
+            .signals
+            .iter()
+            .zip(self.patterns.iter().map(|p| p.as_slice()))
+    }
+
+    /// Level of the first severity rule `signals` match, ROUTINE when none.
+    pub fn severity_of(&self, signals: &BTreeSet<String>) -> Severity {
+        self.doc
+            .severity
+            .iter()
+            .find(|r| r.matches(signals))
+            .map(|r| r.level.clone())
+            .unwrap_or(Severity::ROUTINE)
+    }
+
+    /// Signals with a numeric or trend predicate on `feature`.
+    pub fn signals_on<'a>(&'a self, feature: &'a str) -> impl Iterator<Item = &'a str> {
+        self.doc
+            .signals
+            .iter()
+            .filter(move |d| {
+                d.numeric.iter().any(|p| p.feature == feature)
+                    || d.trend.iter().any(|t| t.feature == feature)
+            })
+            .map(|d| d.id.as_str())
+    }
+
+    pub fn plausibility(&self, feature: &str) -> Option<&PlausibilityRule> {
+        self.doc.plausibility.iter().find(|r| r.feature == feature)
+    }
+
+    pub fn profile_ids(&self) -> impl Iterator<Item = &str> {
+        self.doc.profiles.iter().map(|p| p.id.as_str())
+    }
+
+    /// Move the threshold of every numeric predicate of `signal` on
+    /// `feature` to `threshold`.
+    pub(crate) fn set_threshold(&mut self, signal: &str, feature: &str, threshold: f64) {
+        for def in self.doc.signals.iter_mut().filter(|d| d.id == signal) {
+            for p in def.numeric.iter_mut().filter(|p| p.feature == feature) {
+                p.threshold = threshold;
+            }
+        }
+    }
+
+    pub fn hash(&self) -> anyhow::Result<String> {
+        blake3_hash_json(&serde_json::to_value(&self.doc)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{tdln_channel_a, tdln_channel_b, Input};
+    use serde_json::json;
+
+    fn actions(measured: serde_json::Value) -> Vec<(String, u64)> {
+        let input: Input =
+            serde_json::from_value(json!({ "text": "", "measured": measured })).unwrap();
+        let catalog = SignalCatalog::builtin();
+        let a = tdln_channel_a(&input, catalog).actions;
+        assert_eq!(a, tdln_channel_b(&input, catalog).actions);
+        a.into_iter().map(|a| (a.name, a.max_delay_s)).collect()
+    }
+
+    fn named(v: &[(&str, u64)]) -> Vec<(String, u64)> {
+        v.iter().map(|(n, d)| (n.to_string(), *d)).collect()
+    }
+
+    #[test]
+    fn channels_propose_the_catalog_actions() {
+        assert_eq!(actions(json!({ "spo2_pct": 95 })), []);
+        assert_eq!(
+            actions(json!({ "spo2_pct": 88 })),
+            named(&[("call_attending", 30), ("increase_O2_100", 0)])
+        );
+        assert_eq!(
+            actions(json!({ "spo2_pct": 80, "fio2": 1.0 })),
+            named(&[
+                ("call_attending", 30),
+                ("escalate_ventilation", 0),
+                ("prepare_intubation_kit", 60),
+            ])
+        );
+    }
+
+    #[test]
+    fn action_rules_are_validated() {
+        let mut doc = SignalCatalog::builtin().doc.clone();
+        doc.actions[0].none.push("hypoxaemia".into());
+        assert_eq!(
+            SignalCatalog::from_doc(doc).unwrap_err().to_string(),
+            "action rule `increase_O2_100`: unknown signal `hypoxaemia`"
+        );
+        let mut doc = SignalCatalog::builtin().doc.clone();
+        doc.actions[1].any.clear();
+        doc.actions[1].all.clear();
+        assert!(matches!(
+            SignalCatalog::from_doc(doc),
+            Err(CatalogError::ActionRule { name, .. }) if name == "escalate_ventilation"
+        ));
+        let mut doc = SignalCatalog::builtin().doc.clone();
+        doc.actions.push(doc.actions[0].clone());
+        assert!(SignalCatalog::from_doc(doc).is_err());
+    }
+}
@@ -0,0 +1,92 @@
+//! Values derived from the measured ones.
+//!
+//! Before the channels run, these are computed from the effective
+//! `Input.measured` when they were not measured themselves:
+//!
+//! - `shock_index`: `hr_bpm / sbp_mmhg`; above 1 the heart is compensating
+//!   for a falling stroke volume, often before the pressure drops.
+//! - `map_mmhg`: `(sbp_mmhg + 2 × dbp_mmhg) / 3`, so a cuff reading alone is
+//!   judged against the MAP threshold.
+//! - `pf_ratio`: `pao2_mmhg / fio2`, the oxygenation index of the Berlin
+//!   definition.
+//! - `sf_ratio`: `spo2_pct / fio2`, its non-invasive surrogate. Only derived
+//!   for SpO2 up to [`SF_MAX_SPO2_PCT`]: above it the oxyhemoglobin curve is
+//!   flat and the ratio says nothing about the PaO2.
+//! - the kidney features of [`crate::kdigo`]: creatinine against its
+//!   baseline and urine output over the 6, 12 and 24 hours before the
+//!   decision.
+//!
+//! Derived values are seen by the channels, the policies and the frontier
+//! certificates like measured ones and are listed in `Ast.derived`. They are
+//! computed after staleness and plausibility checks, from the values those
+//! left in place.
+
+use crate::kdigo::kidney_features;
+use crate::Input;
+use serde::{Deserialize, Serialize};
+
+/// Highest SpO2 an S/F ratio is derived from.
+pub const SF_MAX_SPO2_PCT: f64 = 97.0;
+
+/// A value computed from other features, as recorded in `Ast.derived`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct DerivedValue {
+    pub feature: String,
+    pub value: f64,
+    /// Features it was computed from.
+    pub from: Vec<String>,
+}
+
+/// Derivable features: name, operands and formula.
+type Derivation = (&'static str, [&'static str; 2], fn(f64, f64) -> Option<f64>);
+
+const DERIVATIONS: &[Derivation] = &[
+    ("shock_index", ["hr_bpm", "sbp_mmhg"], |hr, sbp| {
+        Some(hr / sbp)
+    }),
+    ("map_mmhg", ["sbp_mmhg", "dbp_mmhg"], |sbp, dbp| {
+        Some((sbp + 2.0 * dbp) / 3.0)
+    }),
+    ("pf_ratio", ["pao2_mmhg", "fio2"], |pao2, fio2| {
+        Some(pao2 / fio2)
+    }),
+    ("sf_ratio", ["spo2_pct", "fio2"], |spo2, fio2| {
+        (spo2 <= SF_MAX_SPO2_PCT).then_some(spo2 / fio2)
+    }),
+];
+
+/// `input` with the [`DERIVATIONS`] and kidney features it lacks at
+/// `now_rfc3339` filled in, and what was added.
+pub fn derived_input(input: &Input, now_rfc3339: &str) -> (Input, Vec<DerivedValue>) {
+    let mut effective = input.clone();
+    let mut derived = Vec::new();
+    for (feature, [a, b], formula) in DERIVATIONS {
+        if effective.measured.contains_key(*feature) {
+            continue;
+        }
+        let (Some(x), Some(y)) = (effective.measured.get(*a), effective.measured.get(*b)) else {
+            continue;
+        };
+        let Some(value) = formula(*x, *y).filter(|v| v.is_finite()) else {
+            continue;
+        };
+        effective.measured.insert(feature.to_string(), value);
+        derived.push(DerivedValue {
+            feature: feature.to_string(),
+            value,
+            from: vec![a.to_string(), b.to_string()],
+        });
+    }
+    for (feature, (value, from)) in kidney_features(&effective, now_rfc3339) {
+        if effective.measured.contains_key(feature) || !value.is_finite() {
+            continue;
+        }
+        effective.measured.insert(feature.to_string(), value);
+        derived.push(DerivedValue {
+            feature: feature.to_string(),
+            value,
+            from,
+        });
+    }
+    (effective, derived)
+}
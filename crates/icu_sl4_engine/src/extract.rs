@@ -0,0 +1,271 @@
+//! Deterministic extraction of vital signs written in `Input.text`.
+//!
+//! Notes often carry the numbers the monitor feed is missing (`saturação
+//! 85%`, `FC 125 bpm`, `PA 85/50 mmHg`, `T 38,5 °C`). The extractor
+//! recognises Portuguese and English forms of SpO2/SatO2, FC/HR, FR/RR,
+//! PA/BP (systolic/diastolic), PAM/MAP and temperature, normalises units
+//! (decimal commas, °F to °C) and drops values outside [`text_range`].
+//! Mentions in a negated, hypothetical or historical scope are ignored, and
+//! when a feature is written more than once the last mention wins.
+//!
+//! Text values fill gaps in `Input.measured`; they never replace a measured
+//! value. When both exist and differ by more than the feature's
+//! [`conflict_tolerance`], the AST records the conflict and the decision
+//! carries [`TEXT_VALUE_CONFLICT_HAZARD`].
+
+use crate::negation::mention_scope;
+use crate::{add_hazard, Ast, Decision, Input};
+use once_cell::sync::Lazy;
+use regex::{Captures, Regex};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Hazard added when a text value contradicts the measured one.
+pub const TEXT_VALUE_CONFLICT_HAZARD: &str = "TEXT_VALUE_CONFLICT";
+
+/// A vital sign read from `Input.text`, as recorded in `Ast.text_values`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TextValue {
+    pub feature: String,
+    pub value: f64,
+    /// The text the value was read from, e.g. `saturação 85%`.
+    pub mention: String,
+    /// Measured value of the same feature; when present it is the one used.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub measured: Option<f64>,
+    /// `value` and `measured` differ by more than the feature's tolerance.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub conflict: bool,
+}
+
+impl TextValue {
+    /// Whether the value entered the effective measured map.
+    pub fn applied(&self) -> bool {
+        self.measured.is_none()
+    }
+}
+
+const NUM: &str = r"(\d{1,3}(?:[.,]\d+)?)";
+
+fn pattern(head: &str, tail: &str) -> Regex {
+    Regex::new(&format!(
+        r"(?i)\b(?:{head})\b\s*(?:(?:[:=]|de|of|a|at|em)\s*)?{tail}"
+    ))
+    .expect("vital pattern is valid")
+}
+
+struct VitalPattern {
+    re: Regex,
+    /// Features set from the numeric groups, in group order.
+    features: &'static [&'static str],
+}
+
+static PATTERNS: Lazy<Vec<VitalPattern>> = Lazy::new(|| {
+    vec![
+        VitalPattern {
+            re: pattern(
+                r"spo2|sp02|sato2|sat\s*o2|sat|satura[çc][ãa]o(?:\s+(?:de|periférica de)\s+o2)?|satura(?:ndo)?|saturating|oxygen saturation|o2\s*sat",
+                &format!(r"{NUM}\s*%?"),
+            ),
+            features: &["spo2_pct"],
+        },
+        VitalPattern {
+            re: pattern(
+                r"fc|hr|heart rate|frequ[êe]ncia card[íi]aca|pulso|pulse",
+                &format!(r"{NUM}\s*(?:bpm|ppm|/\s*min)?"),
+            ),
+            features: &["hr_bpm"],
+        },
+        VitalPattern {
+            re: pattern(
+                r"fr|rr|resp(?:iratory)?\s+rate|frequ[êe]ncia respirat[óo]ria",
+                &format!(r"{NUM}\s*(?:rpm|cpm|irpm|ipm|/\s*min)?"),
+            ),
+            features: &["rr_rpm"],
+        },
+        VitalPattern {
+            re: pattern(
+                r"pa|ta|bp|press[ãa]o arterial|tens[ãa]o arterial|blood pressure",
+                &format!(r"{NUM}\s*[/x]\s*{NUM}\s*(?:mm\s*hg)?"),
+            ),
+            features: &["sbp_mmhg", "dbp_mmhg"],
+        },
+        VitalPattern {
+            re: pattern(
+                r"pam|map|press[ãa]o arterial m[ée]dia|mean arterial pressure",
+                &format!(r"{NUM}\s*(?:mm\s*hg)?"),
+            ),
+            features: &["map_mmhg"],
+        },
+        VitalPattern {
+            re: pattern(
+                r"temp|temperatura|temperature|t\.?\s*ax|tax|t",
+                &format!(r"{NUM}\s*(?:°|º)?\s*([cf])?\b"),
+            ),
+            features: &["temp_c"],
+        },
+    ]
+});
+
+/// Values outside these bounds are typos or units we do not read.
+pub fn text_range(feature: &str) -> Option<(f64, f64)> {
+    Some(match feature {
+        "spo2_pct" => (0.0, 100.0),
+        "hr_bpm" => (20.0, 300.0),
+        "rr_rpm" => (2.0, 80.0),
+        "sbp_mmhg" => (30.0, 300.0),
+        "dbp_mmhg" => (10.0, 200.0),
+        "map_mmhg" => (20.0, 250.0),
+        "temp_c" => (25.0, 45.0),
+        _ => return None,
+    })
+}
+
+/// Largest text/measured difference not reported as a conflict.
+pub fn conflict_tolerance(feature: &str) -> f64 {
+    match feature {
+        "spo2_pct" => 2.0,
+        "hr_bpm" => 10.0,
+        "rr_rpm" => 4.0,
+        "sbp_mmhg" | "dbp_mmhg" | "map_mmhg" => 10.0,
+        "temp_c" => 0.5,
+        _ => 0.0,
+    }
+}
+
+fn number(caps: &Captures, group: usize) -> Option<f64> {
+    caps.get(group)?.as_str().replace(',', ".").parse().ok()
+}
+
+/// Every vital sign affirmed in `text`, keyed by feature; the last mention of
+/// a feature wins. Values are `(value, mention)`.
+pub fn extract_vitals(text: &str) -> BTreeMap<String, (f64, String)> {
+    let mut found: Vec<(usize, &str, f64, String)> = Vec::new();
+    for p in PATTERNS.iter() {
+        for caps in p.re.captures_iter(text) {
+            let m = caps.get(0).expect("group 0 always matches");
+            if mention_scope(text, m.start(), m.end()).is_some() {
+                continue;
+            }
+            let mention = m.as_str().trim_end().to_string();
+            for (i, feature) in p.features.iter().enumerate() {
+                let Some(mut v) = number(&caps, i + 1) else {
+                    continue;
+                };
+                if *feature == "temp_c" {
+                    let unit = caps.get(2).map(|u| u.as_str().to_ascii_lowercase());
+                    if unit.as_deref() == Some("f") {
+                        v = (v - 32.0) * 5.0 / 9.0;
+                    }
+                }
+                let in_range = text_range(feature).is_some_and(|(lo, hi)| (lo..=hi).contains(&v));
+                if v.is_finite() && in_range {
+                    found.push((m.start(), feature, v, mention.clone()));
+                }
+            }
+        }
+    }
+    found.sort_by_key(|(at, ..)| *at);
+    found
+        .into_iter()
+        .map(|(_, f, v, mention)| (f.to_string(), (v, mention)))
+        .collect()
+}
+
+/// `input` with text values added where `measured` has none, and the record
+/// of every text value for the AST.
+pub fn effective_input(input: &Input) -> (Input, Vec<TextValue>) {
+    let mut effective = input.clone();
+    let mut values = Vec::new();
+    for (feature, (value, mention)) in extract_vitals(&input.text) {
+        let measured = input.measured.get(&feature).copied();
+        let conflict = measured.is_some_and(|m| (m - value).abs() > conflict_tolerance(&feature));
+        if measured.is_none() {
+            effective.measured.insert(feature.clone(), value);
+        }
+        values.push(TextValue {
+            feature,
+            value,
+            mention,
+            measured,
+            conflict,
+        });
+    }
+    (effective, values)
+}
+
+/// Flag text/measured conflicts recorded in `ast` on `decision`.
+pub(crate) fn flag_conflicts(ast: &Ast, decision: &mut Decision) {
+    if ast.text_values.iter().any(|v| v.conflict) {
+        decision.require_human_ack = true;
+        add_hazard(decision, TEXT_VALUE_CONFLICT_HAZARD);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn value(text: &str, feature: &str) -> Option<f64> {
+        extract_vitals(text).get(feature).map(|(v, _)| *v)
+    }
+
+    #[test]
+    fn reads_portuguese_and_english_forms() {
+        assert_eq!(value("saturação 85%", "spo2_pct"), Some(85.0));
+        assert_eq!(value("SpO2: 91", "spo2_pct"), Some(91.0));
+        assert_eq!(value("FC 125 bpm", "hr_bpm"), Some(125.0));
+        assert_eq!(value("RR 28/min", "rr_rpm"), Some(28.0));
+        assert_eq!(value("PA 85/50 mmHg", "sbp_mmhg"), Some(85.0));
+        assert_eq!(value("PA 85/50 mmHg", "dbp_mmhg"), Some(50.0));
+        assert_eq!(value("PAM de 62", "map_mmhg"), Some(62.0));
+    }
+
+    #[test]
+    fn normalises_decimal_commas_and_fahrenheit() {
+        assert_eq!(value("T 38,5 °C", "temp_c"), Some(38.5));
+        let f = value("temperature 101.3 F", "temp_c").unwrap();
+        assert!((f - 38.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn drops_values_outside_the_text_range() {
+        assert_eq!(value("saturação 185%", "spo2_pct"), None);
+        assert_eq!(value("FC 12", "hr_bpm"), None);
+        assert_eq!(value("FC 20", "hr_bpm"), Some(20.0));
+    }
+
+    #[test]
+    fn skips_scoped_out_mentions_and_keeps_the_last() {
+        assert_eq!(value("sem FC 130", "hr_bpm"), None);
+        assert_eq!(value("FC 90. agora FC 130", "hr_bpm"), Some(130.0));
+    }
+
+    #[test]
+    fn text_values_fill_gaps_and_report_conflicts() {
+        let input: Input = serde_json::from_value(json!({
+            "text": "saturação 85%, FC 125",
+            "measured": { "hr_bpm": 100 }
+        }))
+        .unwrap();
+        let (effective, values) = effective_input(&input);
+        assert_eq!(effective.measured.get("spo2_pct"), Some(&85.0));
+        assert_eq!(effective.measured.get("hr_bpm"), Some(&100.0));
+        let hr = values.iter().find(|v| v.feature == "hr_bpm").unwrap();
+        assert!(hr.conflict && !hr.applied());
+        let spo2 = values.iter().find(|v| v.feature == "spo2_pct").unwrap();
+        assert!(!spo2.conflict && spo2.applied());
+    }
+
+    #[test]
+    fn differences_within_tolerance_are_not_conflicts() {
+        let input: Input = serde_json::from_value(json!({
+            "text": "FC 110",
+            "measured": { "hr_bpm": 100 }
+        }))
+        .unwrap();
+        let (_, values) = effective_input(&input);
+        assert!(!values[0].conflict);
+    }
+}
@@ -0,0 +1,348 @@
+//! Acute kidney injury staging (KDIGO Clinical Practice Guideline for Acute
+//! Kidney Injury, 2012).
+//!
+//! The criteria are computed as derived features (see [`crate::derived`]),
+//! so the catalog signals `aki_stage_1` to `aki_stage_3` threshold them in
+//! both channels and the frontier certificates show how far each is from
+//! flipping:
+//!
+//! - `creatinine_ratio`: `creatinine_mg_dl` over the patient's documented
+//!   baseline (`Input.patient.baseline.creatinine_mg_dl`).
+//! - `creatinine_rise_mg_dl`: `creatinine_mg_dl` minus its value in
+//!   `Input.measured_24h`, within the 48 hours KDIGO allows.
+//! - `creatinine_acute_mg_dl`: `creatinine_mg_dl` when it rose acutely, by
+//!   [`ACUTE_RISE_MG_DL`] or to [`ACUTE_RATIO`] times the baseline, and 0
+//!   otherwise. KDIGO only counts a creatinine of 4 mg/dL toward stage 3
+//!   with such a rise, so a stable chronic kidney disease is not staged.
+//! - `urine_ml_kg_h_6h`, `urine_ml_kg_h_12h`, `urine_ml_kg_h_24h`: urine
+//!   output per kg (`Input.patient.weight_kg`) and hour over the last 6, 12
+//!   and 24 hours of `Input.urine_output`, and `urine_ml_24h` for SOFA.
+//!
+//! `Input.urine_output` holds hourly collections: `at` is the end of the
+//! collection, `value` its volume in mL. Windows end at the decision time;
+//! hour `k` of a window holds the collections ending between `k` and `k + 1`
+//! hours before it, and a window with an empty hour is not computed, like a
+//! missing feature. Collections that stopped hours ago therefore leave every
+//! window uncomputed, and those ending after the decision time are ignored.
+//!
+//! `Ast.aki` records the stage reached and the thresholds that put the
+//! patient there.
+
+use crate::{Input, SignalCatalog};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use thiserror::Error;
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+
+/// Urine-output windows, in hours, and the feature each one sets.
+pub const URINE_WINDOWS_H: &[(u32, &str)] = &[
+    (6, "urine_ml_kg_h_6h"),
+    (12, "urine_ml_kg_h_12h"),
+    (24, "urine_ml_kg_h_24h"),
+];
+
+/// Rise in creatinine, in mg/dL, that makes a high creatinine acute.
+pub const ACUTE_RISE_MG_DL: f64 = 0.3;
+
+/// Ratio to baseline that makes a high creatinine acute.
+pub const ACUTE_RATIO: f64 = 1.5;
+
+/// Catalog signals of each stage, lowest first.
+pub const AKI_STAGE_SIGNALS: &[&str] = &["aki_stage_1", "aki_stage_2", "aki_stage_3"];
+
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum KdigoError {
+    #[error("urine output: `{0}` is not an RFC 3339 timestamp")]
+    BadTime(String),
+    #[error("urine output at `{0}` must be a finite, non-negative volume")]
+    BadVolume(String),
+}
+
+/// Collections of `input` as Unix seconds and mL, oldest first.
+pub fn urine_collections(input: &Input) -> Result<Vec<(i64, f64)>, KdigoError> {
+    let mut out = Vec::with_capacity(input.urine_output.len());
+    for s in &input.urine_output {
+        let at = OffsetDateTime::parse(&s.at, &Rfc3339)
+            .map_err(|_| KdigoError::BadTime(s.at.clone()))?
+            .unix_timestamp();
+        if !(s.value.is_finite() && s.value >= 0.0) {
+            return Err(KdigoError::BadVolume(s.at.clone()));
+        }
+        out.push((at, s.value));
+    }
+    out.sort_by_key(|(t, _)| *t);
+    Ok(out)
+}
+
+/// Volume of `collections` over the `hours` hours up to `end` (Unix
+/// seconds); `None` when an hour is empty.
+fn window_ml(collections: &[(i64, f64)], end: i64, hours: u32) -> Option<f64> {
+    let mut per_hour = vec![None; hours as usize];
+    for (at, ml) in collections.iter().filter(|(at, _)| *at <= end) {
+        let hour = (end - at) / 3600;
+        if let Some(slot) = per_hour.get_mut(hour as usize) {
+            *slot = Some(slot.unwrap_or(0.0) + ml);
+        }
+    }
+    per_hour.into_iter().sum()
+}
+
+/// The KDIGO features `input` allows at `now_rfc3339`, by name.
+pub(crate) fn kidney_features(
+    input: &Input,
+    now_rfc3339: &str,
+) -> BTreeMap<&'static str, (f64, Vec<String>)> {
+    let mut out = BTreeMap::new();
+    let patient = input.patient.as_ref();
+    if let Some(current) = input.measured.get("creatinine_mg_dl") {
+        let baseline = patient.and_then(|p| p.baseline.get("creatinine_mg_dl"));
+        let ratio = baseline.filter(|b| **b > 0.0).map(|b| current / b);
+        let rise = input
+            .measured_24h
+            .get("creatinine_mg_dl")
+            .map(|b| current - b);
+        let mut acute_from = vec!["creatinine_mg_dl".to_string()];
+        if let Some(r) = ratio {
+            let from = vec!["creatinine_mg_dl".into(), "patient.baseline".into()];
+            out.insert("creatinine_ratio", (r, from));
+            acute_from.push("patient.baseline".into());
+        }
+        if let Some(r) = rise {
+            let from = vec!["creatinine_mg_dl".into(), "measured_24h".into()];
+            out.insert("creatinine_rise_mg_dl", (r, from));
+            acute_from.push("measured_24h".into());
+        }
+        if ratio.is_some() || rise.is_some() {
+            let acute = ratio.is_some_and(|r| r >= ACUTE_RATIO)
+                || rise.is_some_and(|r| r >= ACUTE_RISE_MG_DL);
+            let value = if acute { *current } else { 0.0 };
+            out.insert("creatinine_acute_mg_dl", (value, acute_from));
+        }
+    }
+    // Malformed collections and clocks are refused by `Engine` before this
+    // runs.
+    let collections = urine_collections(input).unwrap_or_default();
+    let Ok(now) = OffsetDateTime::parse(now_rfc3339, &Rfc3339) else {
+        return out;
+    };
+    let weight = patient.and_then(|p| p.weight_kg).filter(|w| *w > 0.0);
+    for (hours, feature) in URINE_WINDOWS_H {
+        let Some(ml) = window_ml(&collections, now.unix_timestamp(), *hours) else {
+            continue;
+        };
+        if *hours == 24 {
+            out.insert("urine_ml_24h", (ml, vec!["urine_output".into()]));
+        }
+        if let Some(w) = weight {
+            let from = vec!["urine_output".into(), "patient.weight_kg".into()];
+            out.insert(*feature, (ml / (w * *hours as f64), from));
+        }
+    }
+    out
+}
+
+/// A threshold that holds for one of the stage signals.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct AkiCriterion {
+    pub signal: String,
+    pub feature: String,
+    pub value: f64,
+    pub relation: String,
+    pub threshold: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct AkiStaging {
+    /// Highest stage reached, 0 when none.
+    pub stage: u8,
+    /// Thresholds met, lowest stage first.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub criteria: Vec<AkiCriterion>,
+    /// Stage features that could not be computed.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub missing: Vec<String>,
+}
+
+impl AkiStaging {
+    /// Staging of the effective `input` under the thresholds of `catalog`;
+    /// `None` when no stage feature could be computed.
+    pub fn from_input(input: &Input, catalog: &SignalCatalog) -> Option<AkiStaging> {
+        let mut criteria = Vec::new();
+        let mut stage = 0;
+        let mut used = Vec::new();
+        for (i, signal) in AKI_STAGE_SIGNALS.iter().enumerate() {
+            let Some(def) = catalog.doc.signals.iter().find(|d| d.id == *signal) else {
+                continue;
+            };
+            for p in &def.numeric {
+                if !used.contains(&p.feature) {
+                    used.push(p.feature.clone());
+                }
+                let Some(v) = input.measured.get(&p.feature).copied() else {
+                    continue;
+                };
+                if p.holds(v) {
+                    stage = i as u8 + 1;
+                    criteria.push(AkiCriterion {
+                        signal: signal.to_string(),
+                        feature: p.feature.clone(),
+                        value: v,
+                        relation: p.relation.as_str().to_string(),
+                        threshold: p.threshold,
+                    });
+                }
+            }
+        }
+        let (present, missing): (Vec<String>, Vec<String>) = used
+            .into_iter()
+            .partition(|f| input.measured.contains_key(f));
+        (!present.is_empty()).then_some(AkiStaging {
+            stage,
+            criteria,
+            missing,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{json, Value};
+
+    const NOW: &str = "2024-01-02T00:00:00Z";
+    const END: i64 = 1_704_153_600; // NOW
+
+    fn input(v: Value) -> Input {
+        let mut v = v;
+        v["text"] = json!("");
+        serde_json::from_value(v).unwrap()
+    }
+
+    /// One collection of `ml` ending every hour, `hours` of them, the last
+    /// one `lag_h` hours before `NOW`.
+    fn hourly(hours: i64, lag_h: i64, ml: f64) -> Value {
+        (0..hours)
+            .map(|k| {
+                let at = OffsetDateTime::from_unix_timestamp(END - (lag_h + k) * 3600).unwrap();
+                json!({ "at": at.format(&Rfc3339).unwrap(), "value": ml })
+            })
+            .collect()
+    }
+
+    #[test]
+    fn window_needs_every_hour_up_to_its_end() {
+        let full: Vec<(i64, f64)> = (0..6).map(|k| (END - k * 3600, 10.0)).collect();
+        assert_eq!(window_ml(&full, END, 6), Some(60.0));
+        assert_eq!(window_ml(&full, END, 12), None);
+        let gap: Vec<(i64, f64)> = full
+            .iter()
+            .copied()
+            .filter(|(at, _)| *at != END - 7200)
+            .collect();
+        assert_eq!(window_ml(&gap, END, 6), None);
+        // Collections ending after the window are not counted.
+        let mut late = full.clone();
+        late.push((END + 60, 500.0));
+        assert_eq!(window_ml(&late, END, 6), Some(60.0));
+        // Several collections in the same hour add up.
+        let mut split = full;
+        split.push((END - 1800, 5.0));
+        assert_eq!(window_ml(&split, END, 6), Some(65.0));
+    }
+
+    #[test]
+    fn urine_windows_end_at_the_decision_time() {
+        let weight = json!({ "weight_kg": 100 });
+        let f = kidney_features(
+            &input(json!({ "patient": weight, "urine_output": hourly(24, 0, 20.0) })),
+            NOW,
+        );
+        assert_eq!(f["urine_ml_kg_h_6h"].0, 0.2);
+        assert_eq!(f["urine_ml_kg_h_24h"].0, 0.2);
+        assert_eq!(f["urine_ml_24h"].0, 480.0);
+        // The same collections stopped three hours ago: no window is covered.
+        let old = kidney_features(
+            &input(json!({ "patient": weight, "urine_output": hourly(24, 3, 20.0) })),
+            NOW,
+        );
+        assert!(!old.contains_key("urine_ml_kg_h_6h"));
+        assert!(!old.contains_key("urine_ml_24h"));
+        // A short record covers the 6-hour window only.
+        let short = kidney_features(
+            &input(json!({ "patient": weight, "urine_output": hourly(6, 0, 20.0) })),
+            NOW,
+        );
+        assert!(short.contains_key("urine_ml_kg_h_6h"));
+        assert!(!short.contains_key("urine_ml_kg_h_12h"));
+    }
+
+    #[test]
+    fn urine_rates_need_a_weight() {
+        let f = kidney_features(&input(json!({ "urine_output": hourly(24, 0, 20.0) })), NOW);
+        assert_eq!(f["urine_ml_24h"].0, 480.0);
+        assert!(!f.contains_key("urine_ml_kg_h_24h"));
+    }
+
+    #[test]
+    fn creatinine_is_acute_only_after_a_rise() {
+        let acute = |v: Value| {
+            kidney_features(&input(v), NOW)
+                .get("creatinine_acute_mg_dl")
+                .map(|(v, _)| *v)
+        };
+        let stable = json!({ "creatinine_mg_dl": 4.2 });
+        assert_eq!(
+            acute(json!({ "measured": stable, "measured_24h": { "creatinine_mg_dl": 4.1 } })),
+            Some(0.0)
+        );
+        assert_eq!(
+            acute(json!({ "measured": stable, "measured_24h": { "creatinine_mg_dl": 3.9 } })),
+            Some(4.2)
+        );
+        assert_eq!(
+            acute(
+                json!({ "measured": stable, "patient": { "baseline": { "creatinine_mg_dl": 2.8 } } })
+            ),
+            Some(4.2)
+        );
+        assert_eq!(
+            acute(
+                json!({ "measured": stable, "patient": { "baseline": { "creatinine_mg_dl": 3.0 } } })
+            ),
+            Some(0.0)
+        );
+        assert_eq!(acute(json!({ "measured": stable })), None);
+    }
+
+    #[test]
+    fn staging_takes_the_highest_stage_met() {
+        let catalog = SignalCatalog::builtin();
+        let stage = |measured: Value| {
+            AkiStaging::from_input(&input(json!({ "measured": measured })), catalog)
+        };
+        let s = stage(json!({ "creatinine_ratio": 2.1, "creatinine_rise_mg_dl": 0.4 })).unwrap();
+        assert_eq!(s.stage, 2);
+        assert_eq!(
+            s.criteria
+                .iter()
+                .map(|c| c.signal.as_str())
+                .collect::<Vec<_>>(),
+            ["aki_stage_1", "aki_stage_1", "aki_stage_2"]
+        );
+        assert!(s.missing.contains(&"urine_ml_kg_h_6h".to_string()));
+        assert_eq!(
+            stage(json!({ "creatinine_acute_mg_dl": 0.0 }))
+                .unwrap()
+                .stage,
+            0
+        );
+        assert_eq!(
+            stage(json!({ "creatinine_acute_mg_dl": 4.0 }))
+                .unwrap()
+                .stage,
+            3
+        );
+        assert!(stage(json!({ "hr_bpm": 80 })).is_none());
+    }
+}
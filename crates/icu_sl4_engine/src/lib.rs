@@ -1,12 +1,98 @@
 use anyhow::{anyhow, Result};
 use ed25519_dalek::{Signature, Signer, SigningKey, VerifyingKey};
-use once_cell::sync::Lazy;
-use regex::Regex;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
+use std::borrow::Cow;
 use std::collections::{BTreeMap, BTreeSet};
 use std::fs;
 use std::path::Path;
+use thiserror::Error;
+
+pub mod age_bands;
+pub mod catalog;
+pub mod derived;
+pub mod extract;
+pub mod kdigo;
+pub mod lifecycle;
+pub mod lint;
+pub mod negation;
+pub mod news2;
+pub mod normative;
+pub mod observation;
+pub mod overlay;
+pub mod patient;
+pub mod plausibility;
+pub mod policy_diff;
+pub mod policy_set;
+pub mod policy_test;
+pub mod respiratory;
+pub mod sepsis;
+pub mod series;
+pub mod sofa;
+pub mod trigger;
+pub mod trust;
+
+pub use age_bands::{AgeBand, AgeBandDoc, AgeBandError, AgeBandTables, BandLimits, VitalTable};
+pub use catalog::{
+    ActionRule, CatalogError, NumericPredicate, PlausibilityRule, SeverityRule, SignalCatalog,
+    SignalDef,
+};
+pub use derived::{derived_input, DerivedValue, SF_MAX_SPO2_PCT};
+pub use extract::{effective_input, extract_vitals, TextValue, TEXT_VALUE_CONFLICT_HAZARD};
+pub use kdigo::{
+    urine_collections, AkiCriterion, AkiStaging, KdigoError, ACUTE_RATIO, ACUTE_RISE_MG_DL,
+    AKI_STAGE_SIGNALS, URINE_WINDOWS_H,
+};
+pub use lifecycle::{
+    check_lifecycle, parse_policy_time, validate_lifecycle, LifecycleError, PolicyValidity,
+    REVIEW_OVERDUE_HAZARD,
+};
+pub use lint::{lint_policy_yaml, lint_policy_yaml_with, LintDiagnostic, LintLevel, LintReport};
+pub use negation::{affirmed_match, mention_scope, MentionScope, NegatedFinding};
+pub use news2::{
+    News2, News2Component, News2Risk, NEWS2_MIN_AGE_YEARS, NEWS2_SCORE, SCALE_2_CONDITION,
+};
+pub use normative::{normative_links, Citation, NormativeLinks, NormativeReference};
+pub use observation::{
+    fresh_input, stale_values, staleness_limits, Observation, ObservationError, StaleValue,
+    MAX_CLOCK_SKEW_S,
+};
+pub use overlay::{
+    load_overlay_yaml, parse_overlay_yaml, resolve_policy, ActionPatch, OverlayError, OverlayLevel,
+    OverlayRef, PolicyOverlay,
+};
+pub use patient::{
+    patient_thresholds, select_profile, PatientContext, PatientError, PatientThresholds,
+    ProfileSelector, ThresholdOverride, ThresholdProfile, ThresholdSource,
+};
+pub use plausibility::{
+    plausible_input, sensor_artifacts, ArtifactRule, PlausibilityError, SensorArtifact,
+    SENSOR_ARTIFACT_HAZARD,
+};
+pub use policy_diff::{
+    diff_policies, input_clock, parse_input_corpus, DeadlineChange, InputDiff, PolicyDiffReport,
+    PolicyDiffSummary,
+};
+pub use policy_set::{apply_policy_set, ActionConflict, PolicyEntry, PolicySet};
+pub use policy_test::{
+    offline_clock, run_policy_tests, PolicyExpectation, PolicyTest, PolicyTestResult,
+    POLICY_TEST_CLOCK,
+};
+pub use respiratory::{BerlinCategory, BerlinOxygenation, BERLIN_MIN_PEEP_CMH2O};
+pub use sepsis::{CriteriaCount, SepsisScreen, QSOFA_SCORE, SIRS_SCORE};
+pub use series::{
+    fill_from_series, Sample, Series, SeriesError, SeriesFn, MAX_WINDOW_S, RESAMPLE_HOLD_S,
+    RESAMPLE_STEP_S,
+};
+pub use sofa::{Sofa, SofaComponent, SofaOrgan, SOFA_DELTA_SCORE, SOFA_SCORE};
+pub use trigger::{
+    check_trigger, compile_trigger, eval_trigger, parse_trigger, trigger_reads, EvalCtx,
+    TriggerError, TriggerReads, TypeEnv, KNOWN_FEATURES, KNOWN_SCORES, MAX_TRIGGER_NESTING,
+};
+pub use trust::{
+    effective_sig_path, load_policy_signature, policy_sig_path, policy_signing_bytes, sign_policy,
+    PolicySignature, PolicySigner, TrustError, TrustStore, TrustedKey,
+};
 
 // -----------------------------
 // Types
@@ -17,17 +103,50 @@ pub struct Input {
     pub text: String,
     #[serde(default)]
     pub measured: BTreeMap<String, f64>,
+    /// Preceding sample of each feature, for the single-sample drop rule.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub previous: BTreeMap<String, f64>,
+    /// Observation time and source of `measured` values, by feature.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub observations: BTreeMap<String, Observation>,
+    /// Recent samples per feature, for trend functions. The newest sample
+    /// stands in for a missing `measured` value.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub series: BTreeMap<String, Vec<Sample>>,
+    /// Age, weight, conditions and baseline, for age bands and threshold
+    /// profiles.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub patient: Option<PatientContext>,
+    /// Measured values of about 24 hours earlier, for the SOFA delta and
+    /// the creatinine rise.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub measured_24h: BTreeMap<String, f64>,
+    /// Hourly urine collections (mL, `at` ending the hour), for KDIGO.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub urine_output: Vec<Sample>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
 pub struct Action {
     pub name: String,
     pub max_delay_s: u64,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub deadline_s: Option<u64>,
+    /// Guard over AST signals/severity; the action is only emitted when it holds.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub when: Option<String>,
+    /// Actions that must not be emitted together with this one (PolicySet merge).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub conflicts_with: Vec<String>,
+    /// Ids of the policy's `normative_references` that justify this action.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub cites: Vec<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+// Derived `Ord` follows declaration order (map keys only); use `rank()` for
+// clinical ordering.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
 #[serde(rename_all = "UPPERCASE")]
 pub enum Severity {
     CRITICAL,
@@ -35,6 +154,17 @@ pub enum Severity {
     ROUTINE,
 }
 
+impl Severity {
+    /// Ordering used by guards: ROUTINE < URGENT < CRITICAL.
+    pub fn rank(&self) -> u8 {
+        match self {
+            Severity::ROUTINE => 0,
+            Severity::URGENT => 1,
+            Severity::CRITICAL => 2,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct Ast {
     pub severity: Severity,
@@ -42,7 +172,40 @@ pub struct Ast {
     pub protocols: Vec<String>,
     pub actions: Vec<Action>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub normative: Option<BTreeMap<String, String>>,
+    pub normative: Option<NormativeLinks>,
+    /// Text mentions of signals that did not fire because they were negated,
+    /// hypothetical or historical.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub negated_findings: Vec<NegatedFinding>,
+    /// Vital signs read from the text; those without a measured counterpart
+    /// were added to the values the channels and policies saw.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub text_values: Vec<TextValue>,
+    /// Measured values discarded as implausible.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub artifacts: Vec<SensorArtifact>,
+    /// Measured values discarded as older than the policies' `max_age_s`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub stale: Vec<StaleValue>,
+    /// Catalog threshold profile selected from `Input.patient`, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub profile: Option<String>,
+    /// Pediatric age band whose vital-sign limits were used, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub age_band: Option<String>,
+    /// Values computed from measured ones, such as the shock index.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub derived: Vec<DerivedValue>,
+    /// Berlin oxygenation category of the effective input, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub berlin: Option<BerlinOxygenation>,
+    /// KDIGO stage and the thresholds behind it, when any kidney feature
+    /// could be computed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub aki: Option<AkiStaging>,
+    /// SOFA breakdown of the effective input, when any organ was scored.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sofa: Option<Sofa>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -52,9 +215,18 @@ pub struct Decision {
     pub actions: Vec<Action>,
     pub hazards: Vec<String>,
     pub watchdog_armed: bool,
+    /// Policy triggers that matched the input, in policy order.
+    #[serde(default)]
+    pub triggers_fired: Vec<String>,
+    /// Actions dropped by the PolicySet conflict rule.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub conflicts: Vec<ActionConflict>,
 }
 
+/// Unknown keys are refused, so a misspelled `max_age_s` or `hazards` cannot
+/// silently drop a limit.
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
 pub struct Policy {
     pub protocol_id: String,
     pub version: String,
@@ -64,7 +236,65 @@ pub struct Policy {
     pub severity: Severity,
     pub actions: Vec<Action>,
     #[serde(default)]
-    pub normative_references: Vec<String>,
+    pub normative_references: Vec<NormativeReference>,
+    /// Citation ids backing each catalog signal, linked in `Ast.normative`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub signal_cites: BTreeMap<String, Vec<String>>,
+    /// Hazard identifier emitted for each severity when the policy fires.
+    /// CRITICAL and URGENT must be mapped; ROUTINE is optional. The severity
+    /// is that of the signals the fired triggers account for (see
+    /// [`hazard_severity`]), not the overall AST severity, so a signal this
+    /// policy never looks at does not raise its hazard.
+    #[serde(default)]
+    pub hazards: BTreeMap<Severity, String>,
+    /// Evaluation order inside a PolicySet; higher runs (and wins conflicts) first.
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub priority: u32,
+    /// Start of the validity window (date or RFC 3339); decisions before it are refused.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub effective_from: Option<String>,
+    /// End of the validity window (exclusive); decisions from then on are refused.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<String>,
+    /// Mandatory review date; later decisions are flagged `POLICY_REVIEW_OVERDUE`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub review_due: Option<String>,
+    /// Maximum age in seconds of each measured feature at decision time;
+    /// older values are treated as missing.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub max_age_s: BTreeMap<String, u64>,
+    /// Named numeric constants usable in triggers and guards as `$name`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub params: BTreeMap<String, f64>,
+    /// `params` overrides per catalog threshold profile, used when that
+    /// profile was selected for the patient.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub profiles: BTreeMap<String, BTreeMap<String, f64>>,
+    /// Overlays this effective policy was resolved through, base first.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub overlays: Vec<OverlayRef>,
+    /// Example inputs with expected outcomes, run by `icu-sl4 policy test`.
+    /// Part of the serialized policy, hence of `policy_hash`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tests: Vec<PolicyTest>,
+}
+
+fn is_zero(v: &u32) -> bool {
+    *v == 0
+}
+
+impl Policy {
+    /// `params` with the overrides of `profile` applied.
+    pub fn params_for(&self, profile: Option<&str>) -> Cow<'_, BTreeMap<String, f64>> {
+        match profile.and_then(|id| self.profiles.get(id)) {
+            Some(overrides) => {
+                let mut params = self.params.clone();
+                params.extend(overrides.iter().map(|(k, v)| (k.clone(), *v)));
+                Cow::Owned(params)
+            }
+            None => Cow::Borrowed(&self.params),
+        }
+    }
 }
 
 // -----------------------------
@@ -99,6 +329,11 @@ pub fn blake3_hash_str(s: &str) -> String {
     format!("blake3:{hex}")
 }
 
+/// Hash identifying a policy's content; what policy signatures cover.
+pub fn policy_hash(p: &Policy) -> Result<String> {
+    blake3_hash_json(&serde_json::to_value(p)?)
+}
+
 pub fn blake3_hash_json(obj: &serde_json::Value) -> Result<String> {
     Ok(blake3_hash_str(&json_canonical(obj)?))
 }
@@ -107,151 +342,159 @@ pub fn blake3_hash_json(obj: &serde_json::Value) -> Result<String> {
 // Dual-channel TDLN micro-impl
 // -----------------------------
 
-static RE_HYPOX: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?i)hipox[eê]mia|hypox").unwrap());
-static RE_TACHY: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?i)taqui|tachy").unwrap());
-static RE_DIAPHO: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?i)sudorese|diaphores").unwrap());
+/// Scoped-out mentions as reported in the AST: sorted, one per distinct
+/// (signal, scope, mention, cue).
+fn negated_findings(
+    mentions: Vec<(String, (MentionScope, String, String))>,
+) -> Vec<NegatedFinding> {
+    let found: BTreeSet<NegatedFinding> = mentions
+        .into_iter()
+        .map(|(signal, (scope, mention, cue))| NegatedFinding {
+            signal,
+            scope,
+            mention: mention.to_lowercase(),
+            cue: cue.to_lowercase(),
+        })
+        .collect();
+    found.into_iter().collect()
+}
 
-pub fn tdln_channel_a(input: &Input) -> Ast {
+/// Channel A: walks the catalog signal by signal, first-match severity. Both
+/// channels emit only what the catalog's signals, severity and action rules
+/// give; the policy decides what is done.
+pub fn tdln_channel_a(input: &Input, catalog: &SignalCatalog) -> Ast {
     let mut signals = BTreeSet::new();
-    let mut actions: Vec<Action> = Vec::new();
+    let mut mentions = Vec::new();
     let text = input.text.as_str();
+    // Malformed series are refused by `Engine` before the channels run.
+    let series = Series::from_input(input).unwrap_or_default();
 
-    if RE_HYPOX.is_match(text)
-        || input
-            .measured
-            .get("spo2_pct")
-            .map(|v| *v < 90.0)
-            .unwrap_or(false)
-    {
-        signals.insert("hypoxemia".to_string());
-    }
-    if input
-        .measured
-        .get("spo2_pct")
-        .map(|v| *v < 85.0)
-        .unwrap_or(false)
-    {
-        signals.insert("severe_hypoxemia".to_string());
-    }
-    if RE_TACHY.is_match(text)
-        || input
-            .measured
-            .get("hr_bpm")
-            .map(|v| *v > 100.0)
-            .unwrap_or(false)
-    {
-        signals.insert("tachycardia".to_string());
-    }
-    if RE_DIAPHO.is_match(text) {
-        signals.insert("diaphoresis".to_string());
+    for (def, patterns) in catalog.signals() {
+        let (by_text, excluded) = affirmed_match(text, patterns);
+        let by_value = def.numeric.iter().any(|p| {
+            input
+                .measured
+                .get(&p.feature)
+                .map(|v| p.holds(*v))
+                .unwrap_or(false)
+        });
+        let by_trend = def.trend.iter().any(|t| t.holds(&series));
+        if by_text || by_value || by_trend {
+            signals.insert(def.id.clone());
+        } else {
+            mentions.extend(excluded.into_iter().map(|m| (def.id.clone(), m)));
+        }
     }
 
-    if signals.contains("hypoxemia") {
-        actions.push(Action {
-            name: "increase_O2_100".into(),
-            max_delay_s: 0,
-            deadline_s: None,
-        });
-        actions.push(Action {
-            name: "call_attending".into(),
-            max_delay_s: 30,
-            deadline_s: None,
-        });
-        if signals.contains("severe_hypoxemia") {
-            actions.push(Action {
-                name: "prepare_intubation_kit".into(),
-                max_delay_s: 60,
-                deadline_s: None,
-            });
+    let mut actions: Vec<Action> = Vec::new();
+    for rule in &catalog.doc.actions {
+        if rule.matches(&signals) {
+            actions.push(rule.action());
         }
     }
 
     actions.sort_by(|a, b| a.name.cmp(&b.name));
 
-    let severity = if signals.contains("severe_hypoxemia") {
-        Severity::CRITICAL
-    } else if signals.contains("hypoxemia") || signals.contains("tachycardia") {
-        Severity::URGENT
-    } else {
-        Severity::ROUTINE
-    };
+    let severity = catalog
+        .doc
+        .severity
+        .iter()
+        .find(|r| r.matches(&signals))
+        .map(|r| r.level.clone())
+        .unwrap_or(Severity::ROUTINE);
 
     Ast {
-        severity: severity.clone(),
+        severity,
         signals: signals.into_iter().collect(),
-        protocols: if severity != Severity::ROUTINE {
-            vec!["hypoxemia_acute".into()]
-        } else {
-            vec![]
-        },
+        protocols: vec![],
         actions,
         normative: None,
+        negated_findings: negated_findings(mentions),
+        text_values: vec![],
+        artifacts: vec![],
+        stale: vec![],
+        profile: None,
+        age_band: None,
+        derived: vec![],
+        berlin: None,
+        aki: None,
+        sofa: None,
     }
 }
 
-pub fn tdln_channel_b(input: &Input) -> Ast {
+/// Channel B: walks the measured values first, then the series, then the
+/// text, and takes the highest-ranked matching severity rule.
+pub fn tdln_channel_b(input: &Input, catalog: &SignalCatalog) -> Ast {
     let mut s = BTreeSet::new();
-    let mut a: Vec<Action> = Vec::new();
-
-    let spo2 = input.measured.get("spo2_pct").copied();
-    let hr = input.measured.get("hr_bpm").copied();
     let text = input.text.as_str();
 
-    if spo2.map(|v| v < 90.0).unwrap_or(false) || RE_HYPOX.is_match(text) {
-        s.insert("hypoxemia".to_string());
-    }
-    if spo2.map(|v| v < 85.0).unwrap_or(false) {
-        s.insert("severe_hypoxemia".to_string());
-    }
-    if hr.map(|v| v > 100.0).unwrap_or(false) || RE_TACHY.is_match(text) {
-        s.insert("tachycardia".to_string());
-    }
-    if RE_DIAPHO.is_match(text) {
-        s.insert("diaphoresis".to_string());
-    }
-
-    if s.contains("hypoxemia") {
-        a.extend_from_slice(&[
-            Action {
-                name: "increase_O2_100".into(),
-                max_delay_s: 0,
-                deadline_s: None,
-            },
-            Action {
-                name: "call_attending".into(),
-                max_delay_s: 30,
-                deadline_s: None,
-            },
-        ]);
-        if s.contains("severe_hypoxemia") {
-            a.push(Action {
-                name: "prepare_intubation_kit".into(),
-                max_delay_s: 60,
-                deadline_s: None,
-            });
+    for (feature, v) in &input.measured {
+        for def in &catalog.doc.signals {
+            if def
+                .numeric
+                .iter()
+                .any(|p| &p.feature == feature && p.holds(*v))
+            {
+                s.insert(def.id.clone());
+            }
+        }
+    }
+    let series = Series::from_input(input).unwrap_or_default();
+    for def in &catalog.doc.signals {
+        if def.trend.iter().any(|t| t.holds(&series)) {
+            s.insert(def.id.clone());
+        }
+    }
+    let mut mentions = Vec::new();
+    for (def, patterns) in catalog.signals() {
+        if s.contains(&def.id) {
+            continue;
+        }
+        match affirmed_match(text, patterns) {
+            (true, _) => {
+                s.insert(def.id.clone());
+            }
+            (false, excluded) => {
+                mentions.extend(excluded.into_iter().map(|m| (def.id.clone(), m)));
+            }
         }
     }
 
+    let mut a: Vec<Action> = catalog
+        .doc
+        .actions
+        .iter()
+        .filter(|r| r.matches(&s))
+        .map(|r| r.action())
+        .collect();
+
     a.sort_by(|x, y| x.name.cmp(&y.name));
 
-    let sev = if s.contains("severe_hypoxemia") {
-        Severity::CRITICAL
-    } else if s.contains("hypoxemia") || s.contains("tachycardia") {
-        Severity::URGENT
-    } else {
-        Severity::ROUTINE
-    };
+    let sev = catalog
+        .doc
+        .severity
+        .iter()
+        .filter(|r| r.matches(&s))
+        .max_by_key(|r| r.level.rank())
+        .map(|r| r.level.clone())
+        .unwrap_or(Severity::ROUTINE);
 
     Ast {
-        severity: sev.clone(),
+        severity: sev,
         signals: s.into_iter().collect(),
-        protocols: if sev != Severity::ROUTINE {
-            vec!["hypoxemia_acute".into()]
-        } else {
-            vec![]
-        },
+        protocols: vec![],
         actions: a,
         normative: None,
+        negated_findings: negated_findings(mentions),
+        text_values: vec![],
+        artifacts: vec![],
+        stale: vec![],
+        profile: None,
+        age_band: None,
+        derived: vec![],
+        berlin: None,
+        aki: None,
+        sofa: None,
     }
 }
 
@@ -259,29 +502,357 @@ pub fn tdln_channel_b(input: &Input) -> Ast {
 // Policy & Decision
 // -----------------------------
 
-pub fn apply_policy(ast: &Ast, p: &Policy) -> Decision {
-    let mut actions: Vec<Action> = p
+#[derive(Debug, Error)]
+pub enum PolicyError {
+    #[error("policy YAML: {0}")]
+    Yaml(#[from] serde_yaml::Error),
+    #[error("policy `{protocol_id}` has no triggers")]
+    NoTriggers { protocol_id: String },
+    #[error("policy `{protocol_id}` has no hazard mapped for severity {severity:?}")]
+    MissingHazard {
+        protocol_id: String,
+        severity: Severity,
+    },
+    #[error("hazard identifier `{0}` must be UPPER_SNAKE_CASE")]
+    BadHazardId(String),
+    #[error("parameter `{0}` must be a finite number")]
+    BadParam(String),
+    #[error("profile `{profile}`: {msg}")]
+    BadProfile { profile: String, msg: String },
+    #[error("`max_age_s` of `{feature}`: {msg}")]
+    BadMaxAge { feature: String, msg: &'static str },
+    #[error(transparent)]
+    Lifecycle(#[from] LifecycleError),
+    #[error(transparent)]
+    Series(#[from] SeriesError),
+    #[error(transparent)]
+    Patient(#[from] PatientError),
+    #[error("duplicate normative reference id `{0}`")]
+    DuplicateCitation(String),
+    #[error("{owner} cites unknown normative reference `{id}`")]
+    UnknownCitation { owner: String, id: String },
+    #[error("trigger #{index} `{trigger}`: {source}")]
+    Trigger {
+        index: usize,
+        trigger: String,
+        #[source]
+        source: TriggerError,
+    },
+    #[error("guard of action `{action}` `{guard}`: {source}")]
+    Guard {
+        action: String,
+        guard: String,
+        #[source]
+        source: TriggerError,
+    },
+}
+
+pub(crate) fn is_hazard_id(id: &str) -> bool {
+    id.starts_with(|c: char| c.is_ascii_uppercase())
+        && id
+            .chars()
+            .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_')
+}
+
+/// Parse and type-check every trigger and guard of `p` against the builtin catalog.
+pub fn validate_policy(p: &Policy) -> std::result::Result<(), PolicyError> {
+    validate_policy_with(p, &TypeEnv::default())
+}
+
+/// Like [`validate_policy`], resolving signal names against `env`.
+pub fn validate_policy_with(p: &Policy, env: &TypeEnv) -> std::result::Result<(), PolicyError> {
+    if p.triggers.is_empty() {
+        return Err(PolicyError::NoTriggers {
+            protocol_id: p.protocol_id.clone(),
+        });
+    }
+    for severity in [Severity::CRITICAL, Severity::URGENT] {
+        if !p.hazards.contains_key(&severity) {
+            return Err(PolicyError::MissingHazard {
+                protocol_id: p.protocol_id.clone(),
+                severity,
+            });
+        }
+    }
+    for id in p.hazards.values() {
+        if !is_hazard_id(id) {
+            return Err(PolicyError::BadHazardId(id.clone()));
+        }
+    }
+    validate_lifecycle(p)?;
+    let mut cited = BTreeSet::new();
+    for c in p
+        .normative_references
+        .iter()
+        .filter_map(NormativeReference::citation)
+    {
+        if !cited.insert(c.id.as_str()) {
+            return Err(PolicyError::DuplicateCitation(c.id.clone()));
+        }
+    }
+    let owners = p
         .actions
         .iter()
-        .map(|a| Action {
-            name: a.name.clone(),
-            max_delay_s: a.max_delay_s,
-            deadline_s: Some(a.max_delay_s),
-        })
-        .collect();
+        .map(|a| (format!("action `{}`", a.name), &a.cites))
+        .chain(
+            p.signal_cites
+                .iter()
+                .map(|(s, ids)| (format!("signal `{s}`"), ids)),
+        );
+    for (owner, ids) in owners {
+        if let Some(id) = ids.iter().find(|id| !cited.contains(id.as_str())) {
+            return Err(PolicyError::UnknownCitation {
+                owner,
+                id: id.clone(),
+            });
+        }
+    }
+    for (name, v) in &p.params {
+        if !v.is_finite() {
+            return Err(PolicyError::BadParam(name.clone()));
+        }
+    }
+    for (profile, overrides) in &p.profiles {
+        let msg = if !env.profiles.contains(profile) {
+            "not a profile of the signal catalog".to_string()
+        } else if let Some(name) = overrides.keys().find(|k| !p.params.contains_key(*k)) {
+            format!("overrides undeclared parameter `{name}`")
+        } else if let Some(name) = overrides
+            .iter()
+            .find(|(_, v)| !v.is_finite())
+            .map(|(k, _)| k)
+        {
+            format!("parameter `{name}` must be a finite number")
+        } else {
+            continue;
+        };
+        return Err(PolicyError::BadProfile {
+            profile: profile.clone(),
+            msg,
+        });
+    }
+    for (feature, &max) in &p.max_age_s {
+        let msg = if !env.features.contains(feature) {
+            "unknown measured feature"
+        } else if max == 0 {
+            "must be positive"
+        } else {
+            continue;
+        };
+        return Err(PolicyError::BadMaxAge {
+            feature: feature.clone(),
+            msg,
+        });
+    }
+    let env = &env.with_params(p.params.keys());
+    for (index, t) in p.triggers.iter().enumerate() {
+        compile_trigger(t, env).map_err(|source| PolicyError::Trigger {
+            index,
+            trigger: t.clone(),
+            source,
+        })?;
+    }
+    for a in &p.actions {
+        if let Some(g) = &a.when {
+            compile_trigger(g, env).map_err(|source| PolicyError::Guard {
+                action: a.name.clone(),
+                guard: g.clone(),
+                source,
+            })?;
+        }
+    }
+    Ok(())
+}
+
+/// Scores of `input` that triggers can compare, by [`KNOWN_SCORES`] name.
+pub fn input_scores(input: &Input) -> BTreeMap<String, f64> {
+    let mut scores = BTreeMap::new();
+    if let Some(n) = News2::from_input(input) {
+        scores.insert(NEWS2_SCORE.to_string(), n.score as f64);
+    }
+    if let Some(screen) = SepsisScreen::from_input(input) {
+        for (name, score) in screen.scores() {
+            scores.insert(name.to_string(), score as f64);
+        }
+    }
+    if let Some(s) = Sofa::from_input(input) {
+        scores.insert(SOFA_SCORE.to_string(), s.total as f64);
+        if let Some(d) = s.delta_24h {
+            scores.insert(SOFA_DELTA_SCORE.to_string(), d as f64);
+        }
+    }
+    scores
+}
+
+/// Triggers of `p` that match `input`. A policy fires when any trigger matches.
+/// Triggers are re-parsed but not re-checked; type checking happens at load.
+pub fn policy_triggers_fired(
+    input: &Input,
+    ast: &Ast,
+    p: &Policy,
+) -> std::result::Result<Vec<String>, PolicyError> {
+    let series = Series::from_input(input)?;
+    let scores = input_scores(input);
+    let params = p.params_for(ast.profile.as_deref());
+    let ctx = EvalCtx {
+        text: &input.text,
+        measured: &input.measured,
+        signals: &ast.signals,
+        severity: &ast.severity,
+        params: &params,
+        series: &series,
+        scores: &scores,
+    };
+    let mut fired = Vec::new();
+    for (index, t) in p.triggers.iter().enumerate() {
+        let e = parse_trigger(t).map_err(|source| PolicyError::Trigger {
+            index,
+            trigger: t.clone(),
+            source,
+        })?;
+        if eval_trigger(&e, &ctx) {
+            fired.push(t.clone());
+        }
+    }
+    Ok(fired)
+}
+
+/// Actions of `p` whose guard holds for this input/AST. Emitted actions keep
+/// their `when` so the decision (and ledger) records which guard admitted them.
+pub fn admitted_actions(
+    input: &Input,
+    ast: &Ast,
+    p: &Policy,
+) -> std::result::Result<Vec<Action>, PolicyError> {
+    let series = Series::from_input(input)?;
+    let scores = input_scores(input);
+    let params = p.params_for(ast.profile.as_deref());
+    let ctx = EvalCtx {
+        text: &input.text,
+        measured: &input.measured,
+        signals: &ast.signals,
+        severity: &ast.severity,
+        params: &params,
+        series: &series,
+        scores: &scores,
+    };
+    let mut out = Vec::new();
+    for a in &p.actions {
+        let admitted = match &a.when {
+            None => true,
+            Some(g) => {
+                let e = parse_trigger(g).map_err(|source| PolicyError::Guard {
+                    action: a.name.clone(),
+                    guard: g.clone(),
+                    source,
+                })?;
+                eval_trigger(&e, &ctx)
+            }
+        };
+        if admitted {
+            out.push(Action {
+                name: a.name.clone(),
+                max_delay_s: a.max_delay_s,
+                deadline_s: Some(a.max_delay_s),
+                when: a.when.clone(),
+                conflicts_with: vec![],
+                cites: vec![],
+            });
+        }
+    }
+    Ok(out)
+}
+
+/// Add an engine-level hazard to `decision`, keeping hazards sorted and unique.
+pub(crate) fn add_hazard(decision: &mut Decision, hazard: &str) {
+    if !decision.hazards.iter().any(|h| h == hazard) {
+        decision.hazards.push(hazard.to_string());
+        decision.hazards.sort();
+    }
+}
+
+/// Severity whose hazard `p` reports when `fired` matched: the level the
+/// severity rules of `catalog` give to the AST signals those triggers account
+/// for. A trigger accounts for the raised signals it names and for those with
+/// a predicate on a feature it compares or a named signal is defined on, or
+/// on a value derived from one (so `hypoxemia` covers `severe_hypoxemia` and,
+/// through the S/F ratio, `oxygenation_severe`). One reading the text, a
+/// score or the severity itself accounts for every AST signal.
+pub fn hazard_severity(
+    ast: &Ast,
+    p: &Policy,
+    fired: &[String],
+    catalog: &SignalCatalog,
+) -> std::result::Result<Severity, PolicyError> {
+    let raised: BTreeSet<&str> = ast.signals.iter().map(String::as_str).collect();
+    let mut covered = BTreeSet::new();
+    for t in fired {
+        let index = p.triggers.iter().position(|x| x == t).unwrap_or_default();
+        let e = parse_trigger(t).map_err(|source| PolicyError::Trigger {
+            index,
+            trigger: t.clone(),
+            source,
+        })?;
+        let mut reads = trigger_reads(&e);
+        if reads.global {
+            return Ok(ast.severity.clone());
+        }
+        reads.signals.retain(|s| raised.contains(s.as_str()));
+        for def in catalog.doc.signals.iter() {
+            if reads.signals.contains(&def.id) {
+                let numeric = def.numeric.iter().map(|p| &p.feature);
+                reads
+                    .features
+                    .extend(numeric.chain(def.trend.iter().map(|t| &t.feature)).cloned());
+            }
+        }
+        for d in &ast.derived {
+            if d.from.iter().any(|f| reads.features.contains(f)) {
+                reads.features.insert(d.feature.clone());
+            }
+        }
+        covered.extend(reads.signals);
+        for f in &reads.features {
+            let on = catalog.signals_on(f).filter(|s| raised.contains(s));
+            covered.extend(on.map(str::to_string));
+        }
+    }
+    Ok(catalog.severity_of(&covered))
+}
+
+pub fn apply_policy(
+    input: &Input,
+    ast: &Ast,
+    p: &Policy,
+    catalog: &SignalCatalog,
+) -> Result<Decision> {
+    let triggers_fired = policy_triggers_fired(input, ast, p)?;
+    let fires = !triggers_fired.is_empty();
+
+    let mut actions: Vec<Action> = if fires {
+        admitted_actions(input, ast, p)?
+    } else {
+        vec![]
+    };
     actions.sort_by(|a, b| a.name.cmp(&b.name));
 
-    Decision {
+    let hazards = match fires {
+        true => {
+            let severity = hazard_severity(ast, p, &triggers_fired, catalog)?;
+            p.hazards.get(&severity).cloned().into_iter().collect()
+        }
+        false => vec![],
+    };
+
+    Ok(Decision {
         sensitivity_bias: "ZFN".to_string(),
         require_human_ack: matches!(ast.severity, Severity::CRITICAL | Severity::URGENT),
         actions,
-        hazards: match ast.severity {
-            Severity::CRITICAL => vec!["HYPOXEMIA_CRITICAL".into()],
-            Severity::URGENT => vec!["HYPOXEMIA_MODERATE".into()],
-            Severity::ROUTINE => vec![],
-        },
+        hazards,
         watchdog_armed: false,
-    }
+        triggers_fired,
+        conflicts: vec![],
+    })
 }
 
 // -----------------------------
@@ -299,6 +870,20 @@ pub struct ProofPack {
     pub tsa_token: Option<String>, // RFC 3161 (stub)
     pub sign: SignatureBlock,
     pub link_prev: Option<String>, // previous ledger entry hash (blockstamp)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub catalog_hash: Option<String>, // signal catalog used by both channels
+    /// Age-band vital-sign tables used by both channels.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub age_bands_hash: Option<String>,
+    /// Trust-store signer of each applied policy (empty when no trust store).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub policy_signers: Vec<PolicySigner>,
+    /// Overlay chains the applied policies were resolved through.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub policy_overlays: Vec<OverlayRef>,
+    /// Validity window of each applied policy that declares one.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub policy_validity: Vec<PolicyValidity>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -308,9 +893,16 @@ pub struct SignatureBlock {
     pub sig: String,
 }
 
+/// Deserialize a policy and reject it if any trigger fails to parse or type-check.
+pub fn parse_policy_yaml(s: &str) -> std::result::Result<Policy, PolicyError> {
+    let p: Policy = serde_yaml::from_str(s)?;
+    validate_policy(&p)?;
+    Ok(p)
+}
+
 pub fn load_policy_yaml(path: &Path) -> Result<Policy> {
     let s = fs::read_to_string(path)?;
-    Ok(serde_yaml::from_str(&s)?)
+    Ok(parse_policy_yaml(&s)?)
 }
 
 pub fn keypair_from_json(path: &Path) -> Result<(SigningKey, VerifyingKey)> {
@@ -339,6 +931,67 @@ pub fn verify_bytes(vk: &VerifyingKey, msg: &[u8], sig: &Signature) -> Result<()
         .map_err(|e| anyhow!("verify failed: {e}"))
 }
 
+/// Canonical bytes covered by the proof-pack signature: the serialized pack
+/// with `sign` set to null.
+pub fn proof_pack_signing_bytes(pp: &serde_json::Value) -> Result<String> {
+    let mut unsigned = pp.clone();
+    match unsigned.as_object_mut() {
+        Some(obj) => {
+            obj.insert("sign".to_string(), serde_json::Value::Null);
+        }
+        None => return Err(anyhow!("proof pack is not a JSON object")),
+    }
+    json_canonical(&unsigned)
+}
+
+/// (Re)sign `pp` in place over its current contents.
+pub fn sign_proof_pack(pp: &mut ProofPack, sign_key: &SigningKey) -> Result<()> {
+    let canonical = proof_pack_signing_bytes(&serde_json::to_value(&*pp)?)?;
+    let sig = sign_bytes(sign_key, canonical.as_bytes());
+    pp.sign = SignatureBlock {
+        alg: "Ed25519".into(),
+        pubkey: verifying_key_to_hex(&sign_key.verifying_key()),
+        sig: hex::encode(sig.to_bytes()),
+    };
+    Ok(())
+}
+
+/// Check the Ed25519 signature of a serialized proof pack.
+pub fn verify_proof_pack(pp: &serde_json::Value) -> Result<()> {
+    let canonical = proof_pack_signing_bytes(pp)?;
+    let sig_hex = pp["sign"]["sig"]
+        .as_str()
+        .ok_or_else(|| anyhow!("missing sign.sig"))?;
+    let pub_hex = pp["sign"]["pubkey"]
+        .as_str()
+        .ok_or_else(|| anyhow!("missing sign.pubkey"))?;
+    let sig_bytes: [u8; 64] = hex::decode(sig_hex)
+        .map_err(|_| anyhow!("bad sig hex"))?
+        .try_into()
+        .map_err(|_| anyhow!("bad sig length"))?;
+    let pk_bytes: [u8; 32] = hex::decode(pub_hex)
+        .map_err(|_| anyhow!("bad pub hex"))?
+        .try_into()
+        .map_err(|_| anyhow!("bad pubkey length"))?;
+    let sig = Signature::from_bytes(&sig_bytes);
+    let vk = VerifyingKey::from_bytes(&pk_bytes).map_err(|e| anyhow!("bad pubkey: {e}"))?;
+    verify_bytes(&vk, canonical.as_bytes(), &sig)?;
+
+    if let Some(signers) = pp.get("policy_signers") {
+        let signers: Vec<PolicySigner> = serde_json::from_value(signers.clone())?;
+        for s in &signers {
+            let pk: [u8; 32] = hex::decode(&s.pubkey)
+                .map_err(|_| anyhow!("bad policy signer pubkey hex"))?
+                .try_into()
+                .map_err(|_| anyhow!("bad policy signer pubkey length"))?;
+            let vk = VerifyingKey::from_bytes(&pk).map_err(|e| anyhow!("bad pubkey: {e}"))?;
+            trust::verify_policy_hash_sig(&s.policy_hash, &vk, &s.sig)
+                .map_err(|e| anyhow!("policy signature of `{}` invalid: {e}", s.protocol_id))?;
+        }
+    }
+    Ok(())
+}
+
 pub fn make_proof_pack(
     input: &Input,
     ast: &Ast,
@@ -353,22 +1006,7 @@ pub fn make_proof_pack(
     let input_hash = blake3_hash_json(&input_v)?;
     let ast_hash = blake3_hash_json(&ast_v)?;
 
-    let unsigned = json!({
-        "input_hash": input_hash,
-        "ast_hash": ast_hash,
-        "policy_hash": policy_hash,
-        "binary_hash": binary_hash,
-        "config_hash": config_hash,
-        "decision_time": decision_time,
-        "tsa_token": serde_json::Value::Null,
-        "sign": serde_json::Value::Null,
-        "link_prev": serde_json::Value::Null,
-    });
-    let canonical = json_canonical(&unsigned)?;
-    let sig = sign_bytes(sign_key, canonical.as_bytes());
-    let sig_hex = hex::encode(sig.to_bytes());
-
-    let pp = ProofPack {
+    let mut pp = ProofPack {
         input_hash,
         ast_hash,
         policy_hash: policy_hash.to_string(),
@@ -378,11 +1016,17 @@ pub fn make_proof_pack(
         tsa_token: None,
         sign: SignatureBlock {
             alg: "Ed25519".into(),
-            pubkey: verifying_key_to_hex(&sign_key.verifying_key()),
-            sig: sig_hex,
+            pubkey: String::new(),
+            sig: String::new(),
         },
         link_prev: None,
+        catalog_hash: None,
+        age_bands_hash: None,
+        policy_signers: vec![],
+        policy_overlays: vec![],
+        policy_validity: vec![],
     };
+    sign_proof_pack(&mut pp, sign_key)?;
     Ok(pp)
 }
 
@@ -392,45 +1036,58 @@ pub fn make_proof_pack(
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FrontierCert {
+    #[serde(default)]
+    pub signal: String,
     pub feature: String,
     pub threshold: f64,
     pub relation: String, // "<", ">", "<=", ">="
     pub margin_to_flip: f64,
+    /// Patient profile that set `threshold`, when it is not the catalog's.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub profile: Option<String>,
+    /// Age band whose table set `threshold`, when it is not the catalog's.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub age_band: Option<String>,
 }
 
-pub fn frontier_for_hypoxemia(input: &Input) -> Vec<FrontierCert> {
+/// Distance of every measured value to every catalog threshold on it, with
+/// the thresholds of the patient's age band and profile.
+pub fn frontier_certificates(
+    input: &Input,
+    catalog: &SignalCatalog,
+    tables: &AgeBandTables,
+) -> Vec<FrontierCert> {
+    let thresholds = patient_thresholds(catalog, tables, input.patient.as_ref());
     let mut out = vec![];
-    if let Some(spo2) = input.measured.get("spo2_pct").copied() {
-        let thr = 90.0;
-        let margin = if spo2 < thr { thr - spo2 } else { spo2 - thr };
-        out.push(FrontierCert {
-            feature: "spo2_pct".into(),
-            threshold: 90.0,
-            relation: "<".to_string(),
-            margin_to_flip: margin,
-        });
-        if spo2 < 85.0 {
-            out.push(FrontierCert {
-                feature: "spo2_pct".into(),
-                threshold: 85.0,
-                relation: "<".to_string(),
-                margin_to_flip: 85.0 - spo2,
-            });
+    for def in &thresholds.catalog.doc.signals {
+        for p in &def.numeric {
+            if let Some(v) = input.measured.get(&p.feature).copied() {
+                let source = thresholds.source(&def.id, &p.feature, tables);
+                out.push(FrontierCert {
+                    signal: def.id.clone(),
+                    feature: p.feature.clone(),
+                    threshold: p.threshold,
+                    relation: p.relation.as_str().to_string(),
+                    margin_to_flip: (v - p.threshold).abs(),
+                    profile: thresholds
+                        .profile
+                        .filter(|_| source == ThresholdSource::Profile)
+                        .map(|pr| pr.id.clone()),
+                    age_band: thresholds
+                        .age_band
+                        .filter(|_| source == ThresholdSource::AgeBand)
+                        .map(|b| b.id.clone()),
+                });
+            }
         }
     }
-    if let Some(hr) = input.measured.get("hr_bpm").copied() {
-        let thr = 100.0;
-        let margin = if hr > thr { hr - thr } else { thr - hr };
-        out.push(FrontierCert {
-            feature: "hr_bpm".into(),
-            threshold: 100.0,
-            relation: ">".to_string(),
-            margin_to_flip: margin,
-        });
-    }
     out
 }
 
+pub fn frontier_for_hypoxemia(input: &Input) -> Vec<FrontierCert> {
+    frontier_certificates(input, SignalCatalog::builtin(), AgeBandTables::builtin())
+}
+
 // -----------------------------
 // Public API
 // -----------------------------
@@ -441,8 +1098,285 @@ pub struct DecideOutput {
     pub decision: Decision,
     pub proof_pack: ProofPack,
     pub frontier: Vec<FrontierCert>,
+    /// NEWS2 of the effective input, next to the protocol severity.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub news2: Option<News2>,
+    /// qSOFA and SIRS counts of the effective input.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sepsis: Option<SepsisScreen>,
+}
+
+/// What the proof pack records about the applied policies.
+struct PolicyProvenance {
+    hash: String,
+    signers: Vec<PolicySigner>,
+    overlays: Vec<OverlayRef>,
+    validity: Vec<PolicyValidity>,
+}
+
+/// Configuration shared by every decision: what the channels detect with and
+/// which policy signers are trusted.
+#[derive(Debug, Clone)]
+pub struct Engine {
+    pub catalog: SignalCatalog,
+    /// Pediatric vital-sign limits applied when the patient's age is known.
+    pub age_bands: AgeBandTables,
+    /// When set, every policy must carry a valid signature by one of its keys.
+    pub trust: Option<TrustStore>,
+}
+
+impl Default for Engine {
+    fn default() -> Self {
+        Engine {
+            catalog: SignalCatalog::builtin().clone(),
+            age_bands: AgeBandTables::builtin().clone(),
+            trust: None,
+        }
+    }
+}
+
+impl Engine {
+    pub fn new(catalog: SignalCatalog) -> Self {
+        Engine {
+            catalog,
+            age_bands: AgeBandTables::builtin().clone(),
+            trust: None,
+        }
+    }
+
+    pub fn with_trust_store(mut self, trust: TrustStore) -> Self {
+        self.trust = Some(trust);
+        self
+    }
+
+    /// Verify every entry against the trust store, if one is configured.
+    fn check_trust<'a>(
+        &self,
+        entries: impl IntoIterator<Item = &'a PolicyEntry>,
+    ) -> Result<Vec<PolicySigner>> {
+        let Some(trust) = &self.trust else {
+            return Ok(vec![]);
+        };
+        let mut signers = Vec::new();
+        for e in entries {
+            signers.push(trust.verify(&e.policy, e.signature.as_ref())?);
+        }
+        Ok(signers)
+    }
+
+    /// Refuse policies outside their validity window at `now_rfc3339`; flag
+    /// overdue reviews on `decision`.
+    fn check_lifecycle<'a>(
+        &self,
+        policies: impl IntoIterator<Item = &'a Policy>,
+        now_rfc3339: &str,
+        decision: &mut Decision,
+    ) -> Result<Vec<PolicyValidity>> {
+        let mut out = Vec::new();
+        for p in policies {
+            let v = check_lifecycle(p, now_rfc3339)?;
+            if v.review_overdue {
+                add_hazard(decision, REVIEW_OVERDUE_HAZARD);
+            }
+            if lifecycle::has_lifecycle(p) {
+                out.push(v);
+            }
+        }
+        Ok(out)
+    }
+
+    /// Run both channels, with the thresholds of the patient's age band and
+    /// profile, over `input` (its series' newest samples filling gaps)
+    /// without its stale values, completed with its text values, cleared of
+    /// sensor artifacts and completed with derived values; returns the AST
+    /// and that effective input.
+    fn run_channels(
+        &self,
+        input: &Input,
+        max_age_s: &BTreeMap<String, u64>,
+        now_rfc3339: &str,
+    ) -> Result<(Ast, Input)> {
+        if let Some(patient) = &input.patient {
+            patient.validate()?;
+        }
+        urine_collections(input)?;
+        let input = fill_from_series(input)?;
+        let (input, stale) = fresh_input(&input, max_age_s, now_rfc3339)?;
+        let (input, text_values) = effective_input(&input);
+        let (input, artifacts) = plausible_input(&input, &self.catalog)?;
+        let (input, derived) = derived_input(&input, now_rfc3339);
+        let thresholds = patient_thresholds(&self.catalog, &self.age_bands, input.patient.as_ref());
+        let mut a = tdln_channel_a(&input, &thresholds.catalog);
+        let b = tdln_channel_b(&input, &thresholds.catalog);
+        if a.actions != b.actions
+            || a.severity != b.severity
+            || a.negated_findings != b.negated_findings
+        {
+            return Err(anyhow!("dual-channel divergence; entering safe mode"));
+        }
+        a.text_values = text_values;
+        a.artifacts = artifacts;
+        a.stale = stale;
+        a.profile = thresholds.profile.map(|p| p.id.clone());
+        a.age_band = thresholds.age_band.map(|b| b.id.clone());
+        a.derived = derived;
+        a.berlin = BerlinOxygenation::from_input(&input);
+        a.aki = AkiStaging::from_input(&input, &thresholds.catalog);
+        a.sofa = Sofa::from_input(&input);
+        Ok((a, input))
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn finish(
+        &self,
+        input: &Input,
+        effective: &Input,
+        ast: Ast,
+        decision: Decision,
+        provenance: PolicyProvenance,
+        binary_hash: &str,
+        config_hash: &str,
+        sign_key: &SigningKey,
+        now_rfc3339: &str,
+    ) -> Result<DecideOutput> {
+        let mut proof = make_proof_pack(
+            input,
+            &ast,
+            &provenance.hash,
+            binary_hash,
+            config_hash,
+            now_rfc3339,
+            sign_key,
+        )?;
+        proof.catalog_hash = Some(self.catalog.hash()?);
+        proof.age_bands_hash = Some(self.age_bands.hash()?);
+        proof.policy_signers = provenance.signers;
+        proof.policy_overlays = provenance.overlays;
+        proof.policy_validity = provenance.validity;
+        sign_proof_pack(&mut proof, sign_key)?;
+        let frontier = frontier_certificates(effective, &self.catalog, &self.age_bands);
+        Ok(DecideOutput {
+            ast,
+            decision,
+            proof_pack: proof,
+            frontier,
+            news2: News2::from_input(effective),
+            sepsis: SepsisScreen::from_input(effective),
+        })
+    }
+
+    /// Decide with an unsigned policy; fails if a trust store is configured.
+    pub fn decide(
+        &self,
+        input: &Input,
+        policy: &Policy,
+        binary_hash: &str,
+        config_hash: &str,
+        sign_key: &SigningKey,
+        now_rfc3339: &str,
+    ) -> Result<DecideOutput> {
+        let entry = PolicyEntry {
+            policy: policy.clone(),
+            path: None,
+            signature: None,
+        };
+        self.decide_entry(
+            input,
+            &entry,
+            binary_hash,
+            config_hash,
+            sign_key,
+            now_rfc3339,
+        )
+    }
+
+    /// Like [`Engine::decide`], with the policy's detached signature checked
+    /// against the trust store before the policy is applied.
+    pub fn decide_entry(
+        &self,
+        input: &Input,
+        entry: &PolicyEntry,
+        binary_hash: &str,
+        config_hash: &str,
+        sign_key: &SigningKey,
+        now_rfc3339: &str,
+    ) -> Result<DecideOutput> {
+        let signers = self.check_trust([entry])?;
+        let policy = &entry.policy;
+        let limits = staleness_limits([policy]);
+        let (mut a, effective) = self.run_channels(input, &limits, now_rfc3339)?;
+        let mut decision = apply_policy(&effective, &a, policy, &self.catalog)?;
+        extract::flag_conflicts(&a, &mut decision);
+        plausibility::flag_artifacts(&a, &mut decision);
+        let validity = self.check_lifecycle([policy], now_rfc3339, &mut decision)?;
+        if !decision.triggers_fired.is_empty() {
+            a.protocols = vec![policy.protocol_id.clone()];
+            a.normative = normative_links([policy], &a, &decision);
+        }
+        let provenance = PolicyProvenance {
+            hash: policy_hash(policy)?,
+            signers,
+            overlays: policy.overlays.clone(),
+            validity,
+        };
+        self.finish(
+            input,
+            &effective,
+            a,
+            decision,
+            provenance,
+            binary_hash,
+            config_hash,
+            sign_key,
+            now_rfc3339,
+        )
+    }
+
+    /// Like [`Engine::decide`], but over every protocol of a [`PolicySet`].
+    /// The proof pack's `policy_hash` commits to the whole set.
+    pub fn decide_set(
+        &self,
+        input: &Input,
+        set: &PolicySet,
+        binary_hash: &str,
+        config_hash: &str,
+        sign_key: &SigningKey,
+        now_rfc3339: &str,
+    ) -> Result<DecideOutput> {
+        let signers = self.check_trust(&set.entries)?;
+        let limits = staleness_limits(set.policies());
+        let (mut a, effective) = self.run_channels(input, &limits, now_rfc3339)?;
+        let (mut decision, matched) = apply_policy_set(&effective, &a, set, &self.catalog)?;
+        extract::flag_conflicts(&a, &mut decision);
+        plausibility::flag_artifacts(&a, &mut decision);
+        let validity = self.check_lifecycle(set.policies(), now_rfc3339, &mut decision)?;
+        a.normative = normative_links(
+            set.policies().filter(|p| matched.contains(&p.protocol_id)),
+            &a,
+            &decision,
+        );
+        a.protocols = matched;
+        let provenance = PolicyProvenance {
+            hash: set.hash()?,
+            signers,
+            overlays: set.policies().flat_map(|p| p.overlays.clone()).collect(),
+            validity,
+        };
+        self.finish(
+            input,
+            &effective,
+            a,
+            decision,
+            provenance,
+            binary_hash,
+            config_hash,
+            sign_key,
+            now_rfc3339,
+        )
+    }
 }
 
+/// [`Engine::decide`] with the builtin signal catalog.
 pub fn decide(
     input: &Input,
     policy: &Policy,
@@ -451,31 +1385,26 @@ pub fn decide(
     sign_key: &SigningKey,
     now_rfc3339: &str,
 ) -> Result<DecideOutput> {
-    let a = tdln_channel_a(input);
-    let b = tdln_channel_b(input);
-    if a.actions != b.actions || a.severity != b.severity {
-        return Err(anyhow!("dual-channel divergence; entering safe mode"));
-    }
-    let decision = apply_policy(&a, policy);
-    let policy_v = serde_json::to_value(policy)?;
-    let policy_hash = blake3_hash_json(&policy_v)?;
-
-    let proof = make_proof_pack(
+    Engine::default().decide(
         input,
-        &a,
-        &policy_hash,
+        policy,
         binary_hash,
         config_hash,
-        now_rfc3339,
         sign_key,
-    )?;
-    let frontier = frontier_for_hypoxemia(input);
-    Ok(DecideOutput {
-        ast: a,
-        decision,
-        proof_pack: proof,
-        frontier,
-    })
+        now_rfc3339,
+    )
+}
+
+/// [`Engine::decide_set`] with the builtin signal catalog.
+pub fn decide_set(
+    input: &Input,
+    set: &PolicySet,
+    binary_hash: &str,
+    config_hash: &str,
+    sign_key: &SigningKey,
+    now_rfc3339: &str,
+) -> Result<DecideOutput> {
+    Engine::default().decide_set(input, set, binary_hash, config_hash, sign_key, now_rfc3339)
 }
 
 // Ledger append-only NDJSON with blockstamp
@@ -0,0 +1,245 @@
+//! Policy lifecycle: when a protocol is in force and when it must be reviewed.
+//!
+//! `effective_from`, `expires_at` and `review_due` accept a date
+//! (`2025-01-01`, midnight UTC) or an RFC 3339 timestamp. A decision whose
+//! clock falls outside `[effective_from, expires_at)` is refused; one made
+//! after `review_due` goes through but carries the `POLICY_REVIEW_OVERDUE`
+//! hazard. The window actually checked is recorded in the `ProofPack`.
+
+use crate::Policy;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use time::format_description::well_known::Rfc3339;
+use time::macros::format_description;
+use time::{Date, OffsetDateTime};
+
+/// Hazard added to decisions taken under a policy past its review date.
+pub const REVIEW_OVERDUE_HAZARD: &str = "POLICY_REVIEW_OVERDUE";
+
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum LifecycleError {
+    #[error("`{field}` = `{value}` is neither a date nor an RFC 3339 timestamp")]
+    BadTime { field: &'static str, value: String },
+    #[error("policy `{protocol_id}`: `expires_at` must be after `effective_from`")]
+    EmptyWindow { protocol_id: String },
+    #[error("policy `{protocol_id}` is not in force until {effective_from} (decision time {now})")]
+    NotYetEffective {
+        protocol_id: String,
+        effective_from: String,
+        now: String,
+    },
+    #[error("policy `{protocol_id}` expired at {expires_at} (decision time {now})")]
+    Expired {
+        protocol_id: String,
+        expires_at: String,
+        now: String,
+    },
+}
+
+/// Lifecycle of one applied policy, as checked at decision time.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PolicyValidity {
+    pub protocol_id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub effective_from: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub review_due: Option<String>,
+    pub review_overdue: bool,
+}
+
+/// Parse a lifecycle field: a calendar date (midnight UTC) or RFC 3339.
+pub fn parse_policy_time(
+    field: &'static str,
+    value: &str,
+) -> Result<OffsetDateTime, LifecycleError> {
+    if let Ok(t) = OffsetDateTime::parse(value, &Rfc3339) {
+        return Ok(t);
+    }
+    Date::parse(value, format_description!("[year]-[month]-[day]"))
+        .map(|d| d.midnight().assume_utc())
+        .map_err(|_| LifecycleError::BadTime {
+            field,
+            value: value.to_string(),
+        })
+}
+
+fn field(name: &'static str, v: &Option<String>) -> Result<Option<OffsetDateTime>, LifecycleError> {
+    v.as_deref().map(|s| parse_policy_time(name, s)).transpose()
+}
+
+/// Check that the lifecycle fields parse and describe a non-empty window.
+pub fn validate_lifecycle(p: &Policy) -> Result<(), LifecycleError> {
+    let from = field("effective_from", &p.effective_from)?;
+    let until = field("expires_at", &p.expires_at)?;
+    field("review_due", &p.review_due)?;
+    if let (Some(from), Some(until)) = (from, until) {
+        if until <= from {
+            return Err(LifecycleError::EmptyWindow {
+                protocol_id: p.protocol_id.clone(),
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Check `p` against the decision clock `now_rfc3339`.
+pub fn check_lifecycle(p: &Policy, now_rfc3339: &str) -> Result<PolicyValidity, LifecycleError> {
+    let now = parse_policy_time("decision_time", now_rfc3339)?;
+    if let Some(from) = field("effective_from", &p.effective_from)? {
+        if now < from {
+            return Err(LifecycleError::NotYetEffective {
+                protocol_id: p.protocol_id.clone(),
+                effective_from: p.effective_from.clone().unwrap_or_default(),
+                now: now_rfc3339.to_string(),
+            });
+        }
+    }
+    if let Some(until) = field("expires_at", &p.expires_at)? {
+        if now >= until {
+            return Err(LifecycleError::Expired {
+                protocol_id: p.protocol_id.clone(),
+                expires_at: p.expires_at.clone().unwrap_or_default(),
+                now: now_rfc3339.to_string(),
+            });
+        }
+    }
+    let review_overdue = field("review_due", &p.review_due)?.is_some_and(|due| now >= due);
+    Ok(PolicyValidity {
+        protocol_id: p.protocol_id.clone(),
+        effective_from: p.effective_from.clone(),
+        expires_at: p.expires_at.clone(),
+        review_due: p.review_due.clone(),
+        review_overdue,
+    })
+}
+
+/// Whether `p` declares any lifecycle field.
+pub fn has_lifecycle(p: &Policy) -> bool {
+    p.effective_from.is_some() || p.expires_at.is_some() || p.review_due.is_some()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse_policy_yaml, Engine, Input};
+    use ed25519_dalek::SigningKey;
+    use serde_json::json;
+
+    fn policy(lifecycle: &str) -> Policy {
+        parse_policy_yaml(&format!(
+            "\
+protocol_id: p
+version: 1.0.0
+jurisdiction: PT
+source: test
+triggers:
+  - spo2_pct < 90
+severity: CRITICAL
+hazards:
+  CRITICAL: HYPOXEMIA_CRITICAL
+  URGENT: HYPOXEMIA_MODERATE
+actions:
+  - name: call_attending
+    max_delay_s: 60
+{lifecycle}"
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn window_is_closed_at_the_start_and_open_at_the_end() {
+        let p = policy("effective_from: 2025-01-01\nexpires_at: 2026-01-01T00:00:00+01:00\n");
+        assert!(matches!(
+            check_lifecycle(&p, "2024-12-31T23:59:59Z"),
+            Err(LifecycleError::NotYetEffective { .. })
+        ));
+        // A date is midnight UTC, so the first second of the day is in force.
+        assert!(check_lifecycle(&p, "2025-01-01T00:00:00Z").is_ok());
+        assert!(check_lifecycle(&p, "2025-12-31T22:59:59Z").is_ok());
+        // `expires_at` carries an offset: 2025-12-31T23:00:00Z is its instant.
+        assert_eq!(
+            check_lifecycle(&p, "2025-12-31T23:00:00Z"),
+            Err(LifecycleError::Expired {
+                protocol_id: "p".into(),
+                expires_at: "2026-01-01T00:00:00+01:00".into(),
+                now: "2025-12-31T23:00:00Z".into(),
+            })
+        );
+    }
+
+    #[test]
+    fn review_is_overdue_from_its_due_date() {
+        let p = policy("review_due: 2025-06-01\n");
+        let before = check_lifecycle(&p, "2025-05-31T23:59:59Z").unwrap();
+        assert!(!before.review_overdue);
+        let at = check_lifecycle(&p, "2025-06-01T00:00:00Z").unwrap();
+        assert!(at.review_overdue);
+        assert_eq!(at.review_due.as_deref(), Some("2025-06-01"));
+        assert_eq!(at.effective_from, None);
+    }
+
+    #[test]
+    fn malformed_or_empty_windows_are_rejected() {
+        assert_eq!(
+            parse_policy_time("review_due", "01/06/2025"),
+            Err(LifecycleError::BadTime {
+                field: "review_due",
+                value: "01/06/2025".into(),
+            })
+        );
+        let mut p = policy("");
+        p.effective_from = Some("2025-01-01".into());
+        p.expires_at = Some("2025-01-01T00:00:00Z".into());
+        assert_eq!(
+            validate_lifecycle(&p),
+            Err(LifecycleError::EmptyWindow {
+                protocol_id: "p".into()
+            })
+        );
+        assert!(parse_policy_yaml(&serde_yaml::to_string(&p).unwrap()).is_err());
+    }
+
+    fn decide(p: &Policy, now: &str) -> anyhow::Result<crate::DecideOutput> {
+        let input: Input =
+            serde_json::from_value(json!({ "text": "", "measured": { "spo2_pct": 85 } })).unwrap();
+        Engine::default().decide(
+            &input,
+            p,
+            "blake3:test",
+            "blake3:test",
+            &SigningKey::from_bytes(&[0u8; 32]),
+            now,
+        )
+    }
+
+    #[test]
+    fn decisions_carry_the_overdue_hazard_and_the_checked_window() {
+        let p = policy("effective_from: 2025-01-01\nreview_due: 2025-06-01\n");
+        let out = decide(&p, "2025-07-01T00:00:00Z").unwrap();
+        assert!(out
+            .decision
+            .hazards
+            .iter()
+            .any(|h| h == REVIEW_OVERDUE_HAZARD));
+        assert_eq!(out.proof_pack.policy_validity.len(), 1);
+        assert!(out.proof_pack.policy_validity[0].review_overdue);
+
+        let on_time = decide(&p, "2025-05-01T00:00:00Z").unwrap();
+        assert!(!on_time
+            .decision
+            .hazards
+            .iter()
+            .any(|h| h == REVIEW_OVERDUE_HAZARD));
+
+        let err = decide(&p, "2024-12-31T00:00:00Z").unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<LifecycleError>(),
+            Some(LifecycleError::NotYetEffective { .. })
+        ));
+        // Policies without lifecycle fields leave nothing in the proof pack.
+        let timeless = decide(&policy(""), "2025-07-01T00:00:00Z").unwrap();
+        assert!(timeless.proof_pack.policy_validity.is_empty());
+    }
+}
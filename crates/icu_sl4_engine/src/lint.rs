@@ -0,0 +1,1015 @@
+//! Semantic validation of policy files.
+//!
+//! `parse_policy_yaml` stops at the first error; the linter keeps going and
+//! reports every problem it can find with a 1-based line and column in the
+//! YAML source, so authors can fix a policy in one pass.
+
+use crate::trigger::{Expr, NumExpr, RelOp};
+use crate::{
+    check_trigger, is_hazard_id, parse_policy_time, parse_trigger, NormativeReference, Policy,
+    Severity, TriggerError, TypeEnv,
+};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Keys a policy document may use; `Policy` refuses any other.
+const POLICY_KEYS: &[&str] = &[
+    "protocol_id",
+    "version",
+    "jurisdiction",
+    "source",
+    "triggers",
+    "severity",
+    "actions",
+    "normative_references",
+    "signal_cites",
+    "hazards",
+    "priority",
+    "effective_from",
+    "expires_at",
+    "review_due",
+    "max_age_s",
+    "params",
+    "profiles",
+    "overlays",
+    "tests",
+];
+
+/// Keys an item of `actions` may use.
+const ACTION_KEYS: &[&str] = &[
+    "name",
+    "max_delay_s",
+    "deadline_s",
+    "when",
+    "conflicts_with",
+    "cites",
+];
+
+static RE_SEMVER: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"^(0|[1-9]\d*)\.(0|[1-9]\d*)\.(0|[1-9]\d*)(-[0-9A-Za-z-]+(\.[0-9A-Za-z-]+)*)?(\+[0-9A-Za-z-]+(\.[0-9A-Za-z-]+)*)?$",
+    )
+    .unwrap()
+});
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum LintLevel {
+    Error,
+    Warning,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct LintDiagnostic {
+    pub level: LintLevel,
+    /// Stable kebab-case identifier, e.g. `unknown-feature`.
+    pub code: String,
+    pub message: String,
+    pub line: usize,
+    pub column: usize,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct LintReport {
+    pub diagnostics: Vec<LintDiagnostic>,
+}
+
+impl LintReport {
+    pub fn has_errors(&self) -> bool {
+        self.diagnostics.iter().any(|d| d.level == LintLevel::Error)
+    }
+
+    /// One `file:line:col: level[code]: message` line per diagnostic.
+    pub fn to_text(&self, file: &str) -> String {
+        let mut out = String::new();
+        for d in &self.diagnostics {
+            let level = match d.level {
+                LintLevel::Error => "error",
+                LintLevel::Warning => "warning",
+            };
+            out.push_str(&format!(
+                "{file}:{}:{}: {level}[{}]: {}\n",
+                d.line, d.column, d.code, d.message
+            ));
+        }
+        out
+    }
+
+    fn push(&mut self, level: LintLevel, code: &str, message: String, at: (usize, usize)) {
+        self.diagnostics.push(LintDiagnostic {
+            level,
+            code: code.to_string(),
+            message,
+            line: at.0,
+            column: at.1,
+        });
+    }
+}
+
+// -----------------------------
+// Source locations
+// -----------------------------
+
+/// Line-based locator for block-style YAML; falls back to the nearest
+/// enclosing key (or 1:1) for flow style.
+struct Locator<'a> {
+    lines: Vec<&'a str>,
+}
+
+fn indent(line: &str) -> usize {
+    line.len() - line.trim_start().len()
+}
+
+impl<'a> Locator<'a> {
+    fn new(src: &'a str) -> Self {
+        Locator {
+            lines: src.lines().collect(),
+        }
+    }
+
+    fn key(&self, key: &str) -> Option<(usize, usize)> {
+        let prefix = format!("{key}:");
+        self.lines
+            .iter()
+            .position(|l| l.starts_with(&prefix))
+            .map(|i| (i + 1, 1))
+    }
+
+    fn key_or_top(&self, key: &str) -> (usize, usize) {
+        self.key(key).unwrap_or((1, 1))
+    }
+
+    /// First line of the block under top-level `key` that mentions `needle`.
+    fn value_under(&self, key: &str, needle: &str) -> (usize, usize) {
+        let Some((start, _)) = self.key(key) else {
+            return (1, 1);
+        };
+        for (i, l) in self.lines.iter().enumerate().skip(start) {
+            if indent(l) == 0 && !l.trim().is_empty() {
+                break;
+            }
+            if let Some(col) = l.find(needle) {
+                return (i + 1, col + 1);
+            }
+        }
+        (start, 1)
+    }
+
+    /// Block sequence items under a top-level key: (line index, column of content).
+    fn items(&self, key: &str) -> Vec<(usize, usize)> {
+        let Some((start, _)) = self.key(key) else {
+            return vec![];
+        };
+        let mut out = vec![];
+        for (i, l) in self.lines.iter().enumerate().skip(start) {
+            let t = l.trim_start();
+            if t.is_empty() || t.starts_with('#') {
+                continue;
+            }
+            if indent(l) == 0 && !t.starts_with('-') {
+                break;
+            }
+            if let Some(rest) = t.strip_prefix('-') {
+                let content = rest.trim_start();
+                let col = indent(l) + 1 + (rest.len() - content.len());
+                out.push((i, col));
+            }
+        }
+        out
+    }
+
+    fn item(&self, key: &str, index: usize) -> (usize, usize) {
+        match self.items(key).get(index) {
+            Some((line, col)) => (line + 1, col + 1),
+            None => self.key_or_top(key),
+        }
+    }
+
+    /// Position of `field:` inside the `index`-th item of a block sequence.
+    fn item_field(&self, key: &str, index: usize, field: &str) -> (usize, usize) {
+        let items = self.items(key);
+        let Some(&(first, _)) = items.get(index) else {
+            return self.key_or_top(key);
+        };
+        let end = items
+            .get(index + 1)
+            .map(|(l, _)| *l)
+            .unwrap_or(self.lines.len());
+        let prefix = format!("{field}:");
+        for i in first..end {
+            let l = self.lines[i];
+            let body = l.trim_start().trim_start_matches('-').trim_start();
+            if body.starts_with(&prefix) {
+                let col = l.len() - body.len() + prefix.len();
+                let value_off = l[col..].len() - l[col..].trim_start().len();
+                return (i + 1, col + value_off + 1);
+            }
+        }
+        (first + 1, 1)
+    }
+}
+
+/// Column of a byte offset inside a scalar that starts at `at`; quoted
+/// scalars shift by the opening quote.
+fn within(src_line: Option<&str>, at: (usize, usize), pos: usize) -> (usize, usize) {
+    let quoted = src_line
+        .and_then(|l| l.get(at.1 - 1..))
+        .map(|rest| rest.starts_with('"') || rest.starts_with('\''))
+        .unwrap_or(false);
+    (at.0, at.1 + pos + usize::from(quoted))
+}
+
+/// Remove the keys `Policy` would refuse from `doc`: top-level ones, and those
+/// of `actions` items with the item index.
+fn drop_unknown_keys(doc: &mut serde_yaml::Value) -> Vec<(Option<usize>, String)> {
+    let mut out = Vec::new();
+    let Some(map) = doc.as_mapping_mut() else {
+        return out;
+    };
+    map.retain(|k, _| match k.as_str() {
+        Some(k) if POLICY_KEYS.contains(&k) => true,
+        other => {
+            out.push((None, other.map_or_else(|| format!("{k:?}"), str::to_string)));
+            false
+        }
+    });
+    if let Some(actions) = map.get_mut("actions").and_then(|a| a.as_sequence_mut()) {
+        for (i, a) in actions.iter_mut().enumerate() {
+            let Some(a) = a.as_mapping_mut() else {
+                continue;
+            };
+            a.retain(|k, _| match k.as_str() {
+                Some(k) if ACTION_KEYS.contains(&k) => true,
+                other => {
+                    out.push((
+                        Some(i),
+                        other.map_or_else(|| format!("{k:?}"), str::to_string),
+                    ));
+                    false
+                }
+            });
+        }
+    }
+    out
+}
+
+// -----------------------------
+// Satisfiability of comparisons
+// -----------------------------
+
+#[derive(Debug, Clone, Copy)]
+struct Bound {
+    v: f64,
+    strict: bool,
+}
+
+#[derive(Debug, Default)]
+struct Interval {
+    lo: Option<Bound>,
+    hi: Option<Bound>,
+}
+
+impl Interval {
+    fn restrict(&mut self, op: RelOp, c: f64) {
+        let (lo, hi) = match op {
+            RelOp::Gt => (Some(Bound { v: c, strict: true }), None),
+            RelOp::Ge => (
+                Some(Bound {
+                    v: c,
+                    strict: false,
+                }),
+                None,
+            ),
+            RelOp::Lt => (None, Some(Bound { v: c, strict: true })),
+            RelOp::Le => (
+                None,
+                Some(Bound {
+                    v: c,
+                    strict: false,
+                }),
+            ),
+            RelOp::Eq => (
+                Some(Bound {
+                    v: c,
+                    strict: false,
+                }),
+                Some(Bound {
+                    v: c,
+                    strict: false,
+                }),
+            ),
+            RelOp::Ne => (None, None),
+        };
+        if let Some(n) = lo {
+            self.lo = Some(match self.lo {
+                Some(o) if o.v > n.v || (o.v == n.v && o.strict) => o,
+                _ => n,
+            });
+        }
+        if let Some(n) = hi {
+            self.hi = Some(match self.hi {
+                Some(o) if o.v < n.v || (o.v == n.v && o.strict) => o,
+                _ => n,
+            });
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        match (self.lo, self.hi) {
+            (Some(l), Some(h)) => l.v > h.v || (l.v == h.v && (l.strict || h.strict)),
+            _ => false,
+        }
+    }
+}
+
+/// Value of a literal or of a declared `$param`.
+fn constant(e: &NumExpr, params: &BTreeMap<String, f64>) -> Option<f64> {
+    match e {
+        NumExpr::Const(c) => Some(*c),
+        NumExpr::Param(id) => params.get(&id.name).copied(),
+        _ => None,
+    }
+}
+
+/// `feature RELOP const` (either orientation), normalised to feature-first.
+fn simple_compare<'a>(
+    e: &'a Expr,
+    params: &BTreeMap<String, f64>,
+) -> Option<(&'a str, RelOp, f64)> {
+    let Expr::Compare { lhs, op, rhs } = e else {
+        return None;
+    };
+    match (lhs, rhs) {
+        (NumExpr::Feature(f), c) => Some((f.name.as_str(), *op, constant(c, params)?)),
+        (c, NumExpr::Feature(f)) => {
+            let c = constant(c, params)?;
+            let flipped = match op {
+                RelOp::Lt => RelOp::Gt,
+                RelOp::Le => RelOp::Ge,
+                RelOp::Gt => RelOp::Lt,
+                RelOp::Ge => RelOp::Le,
+                other => *other,
+            };
+            Some((f.name.as_str(), flipped, c))
+        }
+        _ => None,
+    }
+}
+
+/// True when `e` can never hold, judged from literals and from numeric
+/// comparisons of one feature against constants.
+fn unsatisfiable(e: &Expr, params: &BTreeMap<String, f64>) -> bool {
+    match e {
+        Expr::Bool(v) => !v,
+        Expr::Not(x) => matches!(**x, Expr::Bool(true)),
+        Expr::SeverityCmp { op, level } => {
+            !(0..=2u8).any(|rank| op.holds(rank as f64, level.rank() as f64))
+        }
+        Expr::Or(xs) => xs.iter().all(|x| unsatisfiable(x, params)),
+        Expr::And(xs) => {
+            if xs.iter().any(|x| unsatisfiable(x, params)) {
+                return true;
+            }
+            let mut per_feature: BTreeMap<&str, Interval> = BTreeMap::new();
+            for x in xs {
+                if let Some((f, op, c)) = simple_compare(x, params) {
+                    per_feature.entry(f).or_default().restrict(op, c);
+                }
+            }
+            per_feature.values().any(Interval::is_empty)
+        }
+        _ => false,
+    }
+}
+
+/// `a` implies `b` for single comparisons on the same feature.
+fn implies(a: (&str, RelOp, f64), b: (&str, RelOp, f64)) -> bool {
+    if a.0 != b.0 {
+        return false;
+    }
+    match (a.1, b.1) {
+        (RelOp::Lt | RelOp::Le, RelOp::Lt) => a.2 < b.2 || (a.2 == b.2 && a.1 == RelOp::Lt),
+        (RelOp::Lt | RelOp::Le, RelOp::Le) => a.2 <= b.2,
+        (RelOp::Gt | RelOp::Ge, RelOp::Gt) => a.2 > b.2 || (a.2 == b.2 && a.1 == RelOp::Gt),
+        (RelOp::Gt | RelOp::Ge, RelOp::Ge) => a.2 >= b.2,
+        _ => false,
+    }
+}
+
+fn trigger_error_code(e: &TriggerError) -> &'static str {
+    match e {
+        TriggerError::Syntax { .. } => "syntax",
+        TriggerError::Regex { .. } => "invalid-regex",
+        TriggerError::Type { .. } => "type",
+        TriggerError::UnknownFeature { .. } => "unknown-feature",
+        TriggerError::UnknownSignal { .. } => "unknown-signal",
+        TriggerError::UnknownParam { .. } => "unknown-param",
+    }
+}
+
+// -----------------------------
+// Lint
+// -----------------------------
+
+/// Lint a policy against the builtin signal catalog.
+pub fn lint_policy_yaml(src: &str) -> LintReport {
+    lint_policy_yaml_with(src, &TypeEnv::default())
+}
+
+pub fn lint_policy_yaml_with(src: &str, env: &TypeEnv) -> LintReport {
+    let mut r = LintReport::default();
+    let loc = Locator::new(src);
+
+    // Unknown keys are reported with their location and dropped, so the rest
+    // of the policy is still linted.
+    let mut doc: serde_yaml::Value = match serde_yaml::from_str(src) {
+        Ok(v) => v,
+        Err(e) => {
+            let at = e
+                .location()
+                .map(|l| (l.line(), l.column()))
+                .unwrap_or((1, 1));
+            r.push(LintLevel::Error, "yaml", e.to_string(), at);
+            return r;
+        }
+    };
+    let unknown = drop_unknown_keys(&mut doc);
+    for (action, key) in &unknown {
+        let (msg, at) = match action {
+            None => (format!("unknown key `{key}`"), loc.key_or_top(key)),
+            Some(i) => (
+                format!("action #{i}: unknown key `{key}`"),
+                loc.item_field("actions", *i, key),
+            ),
+        };
+        r.push(LintLevel::Error, "unknown-key", msg, at);
+    }
+    // Re-emit rather than `from_value`: an untagged `version: 1.0` is a
+    // number in a `Value` but still deserializes into the `String` field.
+    let parsed = if unknown.is_empty() {
+        serde_yaml::from_str(src)
+    } else {
+        serde_yaml::to_string(&doc).and_then(|s| serde_yaml::from_str(&s))
+    };
+    let p: Policy = match parsed {
+        Ok(p) => p,
+        Err(e) => {
+            let at = e
+                .location()
+                .map(|l| (l.line(), l.column()))
+                .unwrap_or((1, 1));
+            r.push(LintLevel::Error, "yaml", e.to_string(), at);
+            return r;
+        }
+    };
+
+    let env = &env.with_params(p.params.keys());
+
+    if !RE_SEMVER.is_match(&p.version) {
+        r.push(
+            LintLevel::Error,
+            "invalid-version",
+            format!("version `{}` is not semver (MAJOR.MINOR.PATCH)", p.version),
+            loc.key_or_top("version"),
+        );
+    }
+
+    // Triggers
+    if p.triggers.is_empty() {
+        r.push(
+            LintLevel::Error,
+            "no-triggers",
+            "policy has no triggers and can never fire".into(),
+            loc.key_or_top("triggers"),
+        );
+    }
+    let mut seen_triggers: BTreeMap<&str, usize> = BTreeMap::new();
+    let mut simple: Vec<(usize, String, RelOp, f64)> = Vec::new();
+    for (i, t) in p.triggers.iter().enumerate() {
+        let at = loc.item("triggers", i);
+        if let Some(first) = seen_triggers.insert(t.as_str(), i) {
+            r.push(
+                LintLevel::Warning,
+                "duplicate-trigger",
+                format!("trigger `{t}` duplicates trigger #{first}"),
+                at,
+            );
+        }
+        let parsed = parse_trigger(t).and_then(|e| check_trigger(&e, env).map(|_| e));
+        match parsed {
+            Err(e) => {
+                let line = loc.lines.get(at.0 - 1).copied();
+                r.push(
+                    LintLevel::Error,
+                    trigger_error_code(&e),
+                    format!("trigger `{t}`: {e}"),
+                    within(line, at, e.pos()),
+                );
+            }
+            Ok(e) => {
+                if unsatisfiable(&e, &p.params) {
+                    r.push(
+                        LintLevel::Error,
+                        "contradictory-trigger",
+                        format!("trigger `{t}` can never match"),
+                        at,
+                    );
+                } else if matches!(e, Expr::Bool(true)) {
+                    r.push(
+                        LintLevel::Warning,
+                        "always-true-trigger",
+                        format!("trigger `{t}` always matches"),
+                        at,
+                    );
+                }
+                if let Some((f, op, c)) = simple_compare(&e, &p.params) {
+                    simple.push((i, f.to_string(), op, c));
+                }
+            }
+        }
+    }
+    for (i, fa, opa, ca) in &simple {
+        let a = (fa.as_str(), *opa, *ca);
+        let shadow = simple.iter().find(|(j, fb, opb, cb)| {
+            let b = (fb.as_str(), *opb, *cb);
+            j != i && implies(a, b) && !(implies(b, a) && j > i)
+        });
+        if let Some((j, ..)) = shadow {
+            let (i, j) = (*i, *j);
+            r.push(
+                LintLevel::Warning,
+                "unreachable-trigger",
+                format!(
+                    "trigger `{}` is shadowed by trigger #{j} `{}` and never changes the outcome",
+                    p.triggers[i], p.triggers[j]
+                ),
+                loc.item("triggers", i),
+            );
+        }
+    }
+
+    // Actions
+    if p.actions.is_empty() {
+        r.push(
+            LintLevel::Error,
+            "no-actions",
+            "policy has an empty action list".into(),
+            loc.key_or_top("actions"),
+        );
+    }
+    let mut seen_actions: BTreeMap<&str, usize> = BTreeMap::new();
+    for (i, a) in p.actions.iter().enumerate() {
+        if let Some(first) = seen_actions.insert(a.name.as_str(), i) {
+            r.push(
+                LintLevel::Error,
+                "duplicate-action",
+                format!("action `{}` duplicates action #{first}", a.name),
+                loc.item_field("actions", i, "name"),
+            );
+        }
+        if let Some(g) = &a.when {
+            let at = loc.item_field("actions", i, "when");
+            match parse_trigger(g).and_then(|e| check_trigger(&e, env).map(|_| e)) {
+                Err(e) => {
+                    let line = loc.lines.get(at.0 - 1).copied();
+                    r.push(
+                        LintLevel::Error,
+                        trigger_error_code(&e),
+                        format!("guard of `{}`: {e}", a.name),
+                        within(line, at, e.pos()),
+                    );
+                }
+                Ok(e) if unsatisfiable(&e, &p.params) => r.push(
+                    LintLevel::Warning,
+                    "unreachable-action",
+                    format!("guard `{g}` can never hold; `{}` is never emitted", a.name),
+                    at,
+                ),
+                Ok(_) => {}
+            }
+        }
+    }
+    let names: BTreeSet<&str> = p.actions.iter().map(|a| a.name.as_str()).collect();
+    for (i, a) in p.actions.iter().enumerate() {
+        if a.conflicts_with.iter().any(|c| c == &a.name) {
+            r.push(
+                LintLevel::Error,
+                "self-conflict",
+                format!("action `{}` conflicts with itself", a.name),
+                loc.item_field("actions", i, "conflicts_with"),
+            );
+        }
+        for c in &a.conflicts_with {
+            if names.contains(c.as_str()) && c != &a.name {
+                r.push(
+                    LintLevel::Warning,
+                    "internal-conflict",
+                    format!(
+                        "actions `{}` and `{c}` of the same policy conflict; one will never be emitted with the other",
+                        a.name
+                    ),
+                    loc.item_field("actions", i, "conflicts_with"),
+                );
+            }
+        }
+    }
+
+    // Hazards
+    for severity in [Severity::CRITICAL, Severity::URGENT] {
+        if !p.hazards.contains_key(&severity) {
+            r.push(
+                LintLevel::Error,
+                "missing-hazard",
+                format!("no hazard mapped for severity {severity:?}"),
+                loc.key_or_top("hazards"),
+            );
+        }
+    }
+    for id in p.hazards.values() {
+        if !is_hazard_id(id) {
+            r.push(
+                LintLevel::Error,
+                "invalid-hazard",
+                format!("hazard identifier `{id}` must be UPPER_SNAKE_CASE"),
+                loc.value_under("hazards", id),
+            );
+        }
+    }
+
+    // Lifecycle
+    let mut times = BTreeMap::new();
+    for (key, v) in [
+        ("effective_from", &p.effective_from),
+        ("expires_at", &p.expires_at),
+        ("review_due", &p.review_due),
+    ] {
+        let Some(v) = v else { continue };
+        match parse_policy_time(key, v) {
+            Ok(t) => {
+                times.insert(key, t);
+            }
+            Err(e) => r.push(
+                LintLevel::Error,
+                "invalid-date",
+                e.to_string(),
+                loc.key_or_top(key),
+            ),
+        }
+    }
+    let (from, until) = (times.get("effective_from"), times.get("expires_at"));
+    if let (Some(from), Some(until)) = (from, until) {
+        if until <= from {
+            r.push(
+                LintLevel::Error,
+                "empty-validity-window",
+                "`expires_at` must be after `effective_from`".into(),
+                loc.key_or_top("expires_at"),
+            );
+        }
+    }
+    if let Some(due) = times.get("review_due") {
+        if from.is_some_and(|f| due < f) || until.is_some_and(|u| due >= u) {
+            r.push(
+                LintLevel::Warning,
+                "review-outside-window",
+                "`review_due` falls outside the validity window".into(),
+                loc.key_or_top("review_due"),
+            );
+        }
+    }
+
+    // Parameters
+    for (name, v) in &p.params {
+        if !v.is_finite() {
+            r.push(
+                LintLevel::Error,
+                "invalid-param",
+                format!("parameter `{name}` must be a finite number"),
+                loc.value_under("params", name),
+            );
+        }
+    }
+
+    // Patient profiles
+    for (profile, overrides) in &p.profiles {
+        let at = loc.value_under("profiles", profile);
+        if !env.profiles.contains(profile) {
+            r.push(
+                LintLevel::Error,
+                "unknown-profile",
+                format!("`profiles` names unknown threshold profile `{profile}`"),
+                at,
+            );
+        }
+        for name in overrides.keys().filter(|k| !p.params.contains_key(*k)) {
+            r.push(
+                LintLevel::Error,
+                "unknown-param",
+                format!("profile `{profile}` overrides undeclared parameter `{name}`"),
+                at,
+            );
+        }
+        for (name, _) in overrides.iter().filter(|(_, v)| !v.is_finite()) {
+            r.push(
+                LintLevel::Error,
+                "invalid-param",
+                format!("profile `{profile}`: parameter `{name}` must be a finite number"),
+                at,
+            );
+        }
+    }
+
+    // Staleness limits
+    for (feature, &max) in &p.max_age_s {
+        let at = loc.value_under("max_age_s", feature);
+        if !env.features.contains(feature) {
+            r.push(
+                LintLevel::Error,
+                "unknown-feature",
+                format!("`max_age_s` names unknown measured feature `{feature}`"),
+                at,
+            );
+        } else if max == 0 {
+            r.push(
+                LintLevel::Error,
+                "invalid-max-age",
+                format!("`max_age_s` of `{feature}` must be positive"),
+                at,
+            );
+        }
+    }
+
+    // Normative references
+    if p.normative_references.is_empty() {
+        r.push(
+            LintLevel::Warning,
+            "missing-normative-reference",
+            "policy cites no normative references".into(),
+            loc.key("normative_references")
+                .unwrap_or_else(|| (loc.lines.len().max(1), 1)),
+        );
+    }
+    let mut cited: BTreeMap<&str, usize> = BTreeMap::new();
+    for (i, c) in p.normative_references.iter().enumerate() {
+        let Some(c) = c.citation() else { continue };
+        if let Some(first) = cited.insert(c.id.as_str(), i) {
+            r.push(
+                LintLevel::Error,
+                "duplicate-citation",
+                format!("reference id `{}` duplicates reference #{first}", c.id),
+                loc.item_field("normative_references", i, "id"),
+            );
+        }
+    }
+    for (i, a) in p.actions.iter().enumerate() {
+        for id in a.cites.iter().filter(|id| !cited.contains_key(id.as_str())) {
+            r.push(
+                LintLevel::Error,
+                "unknown-citation",
+                format!("action `{}` cites unknown reference `{id}`", a.name),
+                loc.item_field("actions", i, "cites"),
+            );
+        }
+    }
+    for (signal, ids) in &p.signal_cites {
+        if !env.signals.contains(signal) {
+            r.push(
+                LintLevel::Error,
+                "unknown-signal",
+                format!("`signal_cites` names unknown signal `{signal}`"),
+                loc.value_under("signal_cites", signal),
+            );
+        }
+        for id in ids.iter().filter(|id| !cited.contains_key(id.as_str())) {
+            r.push(
+                LintLevel::Error,
+                "unknown-citation",
+                format!("signal `{signal}` cites unknown reference `{id}`"),
+                loc.value_under("signal_cites", signal),
+            );
+        }
+    }
+    if !cited.is_empty() {
+        for (i, a) in p.actions.iter().enumerate() {
+            if a.cites.is_empty() {
+                r.push(
+                    LintLevel::Warning,
+                    "uncited-action",
+                    format!("action `{}` cites no normative reference", a.name),
+                    loc.item_field("actions", i, "name"),
+                );
+            }
+        }
+    }
+    if p.normative_references
+        .iter()
+        .any(|r| matches!(r, NormativeReference::Text(_)))
+        && !cited.is_empty()
+    {
+        r.push(
+            LintLevel::Warning,
+            "unstructured-reference",
+            "free-text references cannot be cited by actions or signals".into(),
+            loc.key_or_top("normative_references"),
+        );
+    }
+
+    r.diagnostics
+        .sort_by(|a, b| (a.line, a.column, a.level).cmp(&(b.line, b.column, b.level)));
+    r
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A policy the linter has nothing to say about.
+    const CLEAN: &str = "\
+protocol_id: p
+version: 1.0.0
+jurisdiction: PT
+source: test
+triggers:
+  - spo2_pct < 90
+severity: CRITICAL
+hazards:
+  CRITICAL: HYPOXEMIA_CRITICAL
+  URGENT: HYPOXEMIA_MODERATE
+actions:
+  - name: call_attending
+    max_delay_s: 30
+    cites: [ATS-2020]
+normative_references:
+  - id: ATS-2020
+    title: ATS guidelines
+    year: 2020
+";
+
+    /// `(code, line, column)` of every diagnostic of `CLEAN` with `from`
+    /// replaced by `to`.
+    fn lint(from: &str, to: &str) -> Vec<(String, usize, usize)> {
+        assert!(CLEAN.contains(from), "{from}");
+        lint_policy_yaml(&CLEAN.replacen(from, to, 1))
+            .diagnostics
+            .into_iter()
+            .map(|d| (d.code, d.line, d.column))
+            .collect()
+    }
+
+    fn diag(code: &str, line: usize, column: usize) -> (String, usize, usize) {
+        (code.to_string(), line, column)
+    }
+
+    #[test]
+    fn clean_policy_has_no_diagnostics() {
+        assert_eq!(lint_policy_yaml(CLEAN).diagnostics, []);
+    }
+
+    #[test]
+    fn version_must_be_semver() {
+        assert_eq!(lint("1.0.0", "1.0"), [diag("invalid-version", 2, 1)]);
+        assert_eq!(lint("1.0.0", "1.0.0-rc.1+build.5"), []);
+        assert_eq!(lint("1.0.0", "01.0.0"), [diag("invalid-version", 2, 1)]);
+    }
+
+    #[test]
+    fn contradictory_and_constant_triggers() {
+        let contradiction = "  - spo2_pct < 90 and spo2_pct > 95";
+        assert_eq!(
+            lint("  - spo2_pct < 90", contradiction),
+            [diag("contradictory-trigger", 6, 5)]
+        );
+        assert_eq!(
+            lint("  - spo2_pct < 90", "  - spo2_pct >= 90 and spo2_pct <= 90"),
+            []
+        );
+        assert_eq!(
+            lint("  - spo2_pct < 90", "  - spo2_pct > 90 and spo2_pct <= 90"),
+            [diag("contradictory-trigger", 6, 5)]
+        );
+        assert_eq!(
+            lint("  - spo2_pct < 90", "  - false"),
+            [diag("contradictory-trigger", 6, 5)]
+        );
+        assert_eq!(
+            lint("  - spo2_pct < 90", "  - true"),
+            [diag("always-true-trigger", 6, 5)]
+        );
+    }
+
+    #[test]
+    fn implied_trigger_is_unreachable() {
+        let two = "  - spo2_pct < 90\n  - 85 > spo2_pct";
+        assert_eq!(
+            lint("  - spo2_pct < 90", two),
+            [diag("unreachable-trigger", 7, 5)]
+        );
+        // Of two equivalent triggers the later one is reported.
+        let same = "  - spo2_pct < 90\n  - 90 > spo2_pct";
+        assert_eq!(
+            lint("  - spo2_pct < 90", same),
+            [diag("unreachable-trigger", 7, 5)]
+        );
+        let disjoint = "  - spo2_pct < 90\n  - hr_bpm > 130";
+        assert_eq!(lint("  - spo2_pct < 90", disjoint), []);
+    }
+
+    #[test]
+    fn guard_that_never_holds_makes_the_action_unreachable() {
+        let guard = "    max_delay_s: 30\n    when: spo2_pct > 100 and spo2_pct < 50";
+        assert_eq!(
+            lint("    max_delay_s: 30", guard),
+            [diag("unreachable-action", 14, 11)]
+        );
+    }
+
+    #[test]
+    fn duplicate_actions_point_at_the_second_name() {
+        let dup = "    cites: [ATS-2020]\n  - name: call_attending\n    max_delay_s: 60\n    cites: [ATS-2020]";
+        assert_eq!(
+            lint("    cites: [ATS-2020]", dup),
+            [diag("duplicate-action", 15, 11)]
+        );
+    }
+
+    #[test]
+    fn trigger_errors_point_inside_the_scalar() {
+        // Column of the second `<`, shifted by the opening quote.
+        assert_eq!(
+            lint("  - spo2_pct < 90", "  - spo2_pct < < 90"),
+            [diag("syntax", 6, 16)]
+        );
+        assert_eq!(
+            lint("  - spo2_pct < 90", "  - \"spo2_pct < < 90\""),
+            [diag("syntax", 6, 17)]
+        );
+        let r = lint_policy_yaml(&CLEAN.replace("spo2_pct < 90", "spo2_pc < 90"));
+        assert_eq!(
+            r.to_text("p.yaml"),
+            "p.yaml:6:5: error[unknown-feature]: trigger `spo2_pc < 90`: \
+             type error at column 1: unknown measured feature `spo2_pc`\n"
+        );
+        assert!(r.has_errors());
+    }
+
+    #[test]
+    fn unknown_keys_are_reported_and_the_rest_still_linted() {
+        let typo = "source: test\nmax_age: { spo2_pct: 900 }";
+        assert_eq!(lint("source: test", typo), [diag("unknown-key", 5, 1)]);
+        let action_typo = "    max_delay_s: 30\n    wen: spo2_pct < 80";
+        assert_eq!(
+            lint("    max_delay_s: 30", action_typo),
+            [diag("unknown-key", 14, 10)]
+        );
+        let both = CLEAN
+            .replace("source: test", "source: test\nhazard: {}")
+            .replace("1.0.0", "1.0");
+        let codes: Vec<String> = lint_policy_yaml(&both)
+            .diagnostics
+            .into_iter()
+            .map(|d| d.code)
+            .collect();
+        assert_eq!(codes, ["invalid-version", "unknown-key"]);
+        assert!(crate::parse_policy_yaml(&CLEAN.replace("source: test", typo)).is_err());
+    }
+
+    #[test]
+    fn non_finite_params_are_located() {
+        let src = "\
+protocol_id: p
+version: 1.0.0
+jurisdiction: PT
+source: test
+params:
+  spo2_low: 90
+  spo2_crit: .nan
+profiles:
+  copd:
+    spo2_low: .inf
+triggers:
+  - spo2_pct < $spo2_low
+severity: URGENT
+actions: []
+";
+        let r = lint_policy_yaml(src);
+        let found: Vec<(usize, usize, &str)> = r
+            .diagnostics
+            .iter()
+            .filter(|d| d.code == "invalid-param")
+            .map(|d| (d.line, d.column, d.message.as_str()))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                (7, 3, "parameter `spo2_crit` must be a finite number"),
+                (
+                    9,
+                    3,
+                    "profile `copd`: parameter `spo2_low` must be a finite number"
+                ),
+            ]
+        );
+    }
+}
@@ -0,0 +1,454 @@
+//! NegEx-style scope detection for clinical free text (Portuguese and English).
+//!
+//! A text mention of a finding is *affirmed* unless a cue puts it in one of
+//! three scopes:
+//!
+//! - negated: `sem hipoxemia`, `nega sudorese`, `taquicardia descartada`,
+//!   `denies diaphoresis`, `no hypoxemia`;
+//! - hypothetical: `se hipoxemia`, `em caso de taquicardia`, `if tachycardic`;
+//! - historical: `história de hipoxemia`, `history of tachycardia`.
+//!
+//! Pre-cues reach up to [`SCOPE_WINDOW`] words forward, post-cues up to
+//! [`POST_WINDOW`] words back, and neither crosses a sentence end or an
+//! adversative (`mas`, `porém`, `but`, ...). A comma also ends the scope
+//! unless the clause is a list closed by `ou`/`nem`/`or`/`nor`, so
+//! `sem hipoxemia, taquicardia ou sudorese` negates all three while
+//! `sem hipoxemia, taquicárdico` still affirms tachycardia. Pseudo-cues such
+//! as `sem melhora` are not negations. When in doubt a mention stays
+//! affirmed: the engine is biased towards zero false negatives.
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+/// Maximum words between a pre-cue and the mention it scopes.
+pub const SCOPE_WINDOW: usize = 5;
+/// Maximum words between a mention and a post-cue that scopes it.
+pub const POST_WINDOW: usize = 2;
+
+/// Why a text mention did not count as a finding.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum MentionScope {
+    Negated,
+    Hypothetical,
+    Historical,
+}
+
+/// A text mention of a catalog signal that was scoped out, as reported in
+/// `Ast.negated_findings`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+pub struct NegatedFinding {
+    pub signal: String,
+    pub scope: MentionScope,
+    /// The word(s) matched by the signal's pattern.
+    pub mention: String,
+    /// The cue that put the mention in scope, as written.
+    pub cue: String,
+}
+
+fn cue_regex(cues: &[&str]) -> Regex {
+    Regex::new(&format!(r"(?i)\b(?:{})\b", cues.join("|"))).expect("cue list is valid")
+}
+
+static PRE_NEGATION: Lazy<Regex> = Lazy::new(|| {
+    cue_regex(&[
+        "sem",
+        "nega",
+        "negou",
+        "negam",
+        "não",
+        "nao",
+        "nenhum",
+        "nenhuma",
+        "ausência de",
+        "ausencia de",
+        "descartad[oa]s?",
+        "descarta",
+        "afastad[oa]s?",
+        "exclui",
+        "excluíd[oa]",
+        "not",
+        "denies",
+        "denied",
+        "without",
+        "negative for",
+        "absence of",
+        "free of",
+        "rules? out",
+        "ruled out",
+    ])
+});
+
+static POST_NEGATION: Lazy<Regex> = Lazy::new(|| {
+    cue_regex(&[
+        "descartad[oa]s?",
+        "afastad[oa]s?",
+        "ausente",
+        "ausentes",
+        "excluíd[oa]s?",
+        "negativ[oa]s?",
+        "ruled out",
+        "absent",
+        "excluded",
+        "negative",
+    ])
+});
+
+/// English `no` only when it directly precedes the mention: in Portuguese
+/// `no` is a contraction (`no leito`, `no passado`).
+static ADJACENT_NEGATION: Lazy<Regex> = Lazy::new(|| cue_regex(&["no"]));
+
+/// Conditionals only when they open the clause: `se` is also a pronoun
+/// (`mantém-se taquicárdico`).
+static CONDITIONAL: Lazy<Regex> = Lazy::new(|| cue_regex(&["se", "caso", "if"]));
+
+static PRE_HYPOTHETICAL: Lazy<Regex> = Lazy::new(|| {
+    cue_regex(&[
+        "em caso de",
+        "na eventualidade de",
+        "risco de",
+        "vigiar",
+        "in case of",
+        "risk of",
+        "watch for",
+        "monitor for",
+    ])
+});
+
+static PRE_HISTORICAL: Lazy<Regex> = Lazy::new(|| {
+    cue_regex(&[
+        "história de",
+        "historia de",
+        "antecedentes? de",
+        "episódio prévio de",
+        "history of",
+        "hx of",
+        "prior",
+        "previous",
+    ])
+});
+
+static POST_HISTORICAL: Lazy<Regex> = Lazy::new(|| {
+    cue_regex(&[
+        "prévi[oa]",
+        "previ[oa]",
+        "resolvid[oa]",
+        "no passado",
+        "resolved",
+        "in the past",
+    ])
+});
+
+/// Phrases that contain a negation cue without negating the finding.
+static PSEUDO: Lazy<Regex> = Lazy::new(|| {
+    cue_regex(&[
+        "sem melhora",
+        "sem melhoria",
+        "sem resolução",
+        "sem resolucao",
+        "não melhora",
+        "nao melhora",
+        "não resolve",
+        "não só",
+        "nao so",
+        "no improvement",
+        "no change",
+        "not improved",
+        "not resolved",
+        "not only",
+    ])
+});
+
+/// Sentence ends and adversatives; scopes never cross them.
+static TERMINATOR: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"(?i)[;!?\n]|[.:](?:\s|$)|\b(?:mas|porém|porem|contudo|todavia|embora|exceto|but|however|although|though|except)\b",
+    )
+    .expect("terminator is valid")
+});
+
+/// Words that start a new, affirmed finding inside a clause.
+static GAP_BREAK: Lazy<Regex> = Lazy::new(|| {
+    cue_regex(&[
+        "com",
+        "apresenta",
+        "mantém",
+        "mantem",
+        "with",
+        "presents?",
+        "has",
+    ])
+});
+
+static LIST_CLOSER: Lazy<Regex> = Lazy::new(|| cue_regex(&["ou", "nem", "or", "nor"]));
+
+static WORD: Lazy<Regex> = Lazy::new(|| Regex::new(r"\w+").expect("word is valid"));
+
+fn words(s: &str) -> usize {
+    WORD.find_iter(s).count()
+}
+
+/// Bounds of the clause around `[start, end)`.
+fn clause(text: &str, start: usize, end: usize) -> (usize, usize) {
+    let from = TERMINATOR
+        .find_iter(&text[..start])
+        .last()
+        .map_or(0, |m| m.end());
+    let to = TERMINATOR
+        .find(&text[end..])
+        .map_or(text.len(), |m| end + m.start());
+    (from, to)
+}
+
+/// Widen `[start, end)` to whole words.
+fn whole_word(text: &str, start: usize, end: usize) -> (usize, usize) {
+    let start = text[..start]
+        .char_indices()
+        .rev()
+        .take_while(|(_, c)| c.is_alphanumeric())
+        .last()
+        .map_or(start, |(i, _)| i);
+    let end = end
+        + text[end..]
+            .char_indices()
+            .find(|(_, c)| !c.is_alphanumeric())
+            .map_or(text.len() - end, |(i, _)| i);
+    (start, end)
+}
+
+fn in_pseudo(clause: &str, offset: usize, cue: (usize, usize)) -> bool {
+    PSEUDO
+        .find_iter(clause)
+        .any(|m| offset + m.start() <= cue.0 && cue.1 <= offset + m.end())
+}
+
+enum Reach {
+    /// Up to [`SCOPE_WINDOW`] words before the mention.
+    Window,
+    /// Immediately before the mention.
+    Adjacent,
+    /// First word of the clause, within [`SCOPE_WINDOW`] words.
+    ClauseStart,
+}
+
+/// Scope of the mention at `[start, end)` in `text`, with the cue that set it.
+/// `None` means the mention is affirmed.
+pub fn mention_scope(text: &str, start: usize, end: usize) -> Option<(MentionScope, String)> {
+    let (from, to) = clause(text, start, end);
+    let before = &text[from..start];
+    let after = &text[end..to];
+    let list_closed = LIST_CLOSER.is_match(&text[from..to]);
+
+    let pre = [
+        (MentionScope::Negated, &*PRE_NEGATION, Reach::Window),
+        (MentionScope::Negated, &*ADJACENT_NEGATION, Reach::Adjacent),
+        (
+            MentionScope::Hypothetical,
+            &*CONDITIONAL,
+            Reach::ClauseStart,
+        ),
+        (
+            MentionScope::Hypothetical,
+            &*PRE_HYPOTHETICAL,
+            Reach::Window,
+        ),
+        (MentionScope::Historical, &*PRE_HISTORICAL, Reach::Window),
+    ];
+    // Nearest pre-cue wins; ties are broken by the order above.
+    let mut best: Option<(usize, MentionScope, String)> = None;
+    for (scope, re, reach) in pre {
+        for m in re.find_iter(before) {
+            let span = (from + m.start(), from + m.end());
+            if in_pseudo(before, from, span) {
+                continue;
+            }
+            let gap = &text[span.1..start];
+            let in_reach = match reach {
+                Reach::Window => words(gap) <= SCOPE_WINDOW,
+                Reach::Adjacent => words(gap) == 0,
+                Reach::ClauseStart => {
+                    words(&before[..m.start()]) == 0 && words(gap) <= SCOPE_WINDOW
+                }
+            };
+            if !in_reach || GAP_BREAK.is_match(gap) || (gap.contains(',') && !list_closed) {
+                continue;
+            }
+            if best.as_ref().map_or(true, |(at, _, _)| span.1 > *at) {
+                best = Some((span.1, scope, m.as_str().to_string()));
+            }
+        }
+    }
+    if let Some((_, scope, cue)) = best {
+        return Some((scope, cue));
+    }
+
+    let post = [
+        (MentionScope::Negated, &*POST_NEGATION),
+        (MentionScope::Historical, &*POST_HISTORICAL),
+    ];
+    let mut best: Option<(usize, MentionScope, String)> = None;
+    for (scope, re) in post {
+        if let Some(m) = re.find(after) {
+            let gap = &after[..m.start()];
+            if words(gap) > POST_WINDOW || gap.contains(',') {
+                continue;
+            }
+            if best.as_ref().map_or(true, |(at, _, _)| m.start() < *at) {
+                best = Some((m.start(), scope, m.as_str().to_string()));
+            }
+        }
+    }
+    best.map(|(_, scope, cue)| (scope, cue))
+}
+
+/// Sort the mentions at `spans` into affirmed or scoped out.
+fn scope_spans(
+    text: &str,
+    spans: impl Iterator<Item = (usize, usize)>,
+    excluded: &mut Vec<(MentionScope, String, String)>,
+) -> bool {
+    for (start, end) in spans {
+        let (start, end) = whole_word(text, start, end);
+        match mention_scope(text, start, end) {
+            None => return true,
+            Some((scope, cue)) => excluded.push((scope, text[start..end].to_string(), cue)),
+        }
+    }
+    false
+}
+
+/// Match `patterns` against `text`, skipping scoped-out mentions.
+///
+/// Returns whether any mention is affirmed and, when none is, every mention
+/// that was scoped out as `(scope, mention, cue)`, in pattern then text order.
+pub fn affirmed_match(
+    text: &str,
+    patterns: &[Regex],
+) -> (bool, Vec<(MentionScope, String, String)>) {
+    let mut excluded = Vec::new();
+    for re in patterns {
+        let spans = re.find_iter(text).map(|m| (m.start(), m.end()));
+        if scope_spans(text, spans, &mut excluded) {
+            return (true, vec![]);
+        }
+    }
+    (false, excluded)
+}
+
+/// Whether `re` has at least one affirmed match in `text`.
+pub fn is_affirmed_match(text: &str, re: &Regex) -> bool {
+    affirmed_match(text, std::slice::from_ref(re)).0
+}
+
+/// Whether `needle` occurs affirmed in `text`.
+pub fn is_affirmed_substring(text: &str, needle: &str) -> bool {
+    let spans = text.match_indices(needle).map(|(i, m)| (i, i + m.len()));
+    scope_spans(text, spans, &mut Vec::new())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Scope of the first occurrence of `mention` in `text`.
+    fn scope(text: &str, mention: &str) -> Option<MentionScope> {
+        let start = text.find(mention).expect("mention is in the text");
+        mention_scope(text, start, start + mention.len()).map(|(s, _)| s)
+    }
+
+    #[test]
+    fn pre_cues_scope_within_the_window() {
+        assert_eq!(
+            scope("sem hipoxemia", "hipoxemia"),
+            Some(MentionScope::Negated)
+        );
+        assert_eq!(
+            scope("se hipoxemia chamar", "hipoxemia"),
+            Some(MentionScope::Hypothetical)
+        );
+        assert_eq!(
+            scope("história de taquicardia", "taquicardia"),
+            Some(MentionScope::Historical)
+        );
+        assert_eq!(
+            scope("sem a b c d hipoxemia", "hipoxemia"),
+            Some(MentionScope::Negated)
+        );
+        assert_eq!(scope("sem a b c d e f hipoxemia", "hipoxemia"), None);
+    }
+
+    #[test]
+    fn scopes_end_at_sentences_and_adversatives() {
+        assert_eq!(scope("sem febre. hipoxemia", "hipoxemia"), None);
+        assert_eq!(scope("sem febre; hipoxemia", "hipoxemia"), None);
+        assert_eq!(scope("sem febre mas hipoxemia", "hipoxemia"), None);
+        assert_eq!(scope("no fever but tachycardic", "tachycardic"), None);
+        assert_eq!(scope("sem febre\nhipoxemia", "hipoxemia"), None);
+    }
+
+    #[test]
+    fn a_comma_ends_the_scope_unless_a_list_closes_it() {
+        assert_eq!(scope("sem hipoxemia, taquicárdico", "taquicárdico"), None);
+        assert_eq!(
+            scope("sem hipoxemia, taquicardia ou sudorese", "taquicardia"),
+            Some(MentionScope::Negated)
+        );
+        assert_eq!(
+            scope("sem hipoxemia, taquicardia ou sudorese", "sudorese"),
+            Some(MentionScope::Negated)
+        );
+    }
+
+    #[test]
+    fn gap_breaks_start_an_affirmed_finding() {
+        assert_eq!(scope("sem febre com taquicardia", "taquicardia"), None);
+        assert_eq!(scope("without fever with tachycardia", "tachycardia"), None);
+    }
+
+    #[test]
+    fn post_cues_reach_two_words_back() {
+        assert_eq!(
+            scope("taquicardia descartada", "taquicardia"),
+            Some(MentionScope::Negated)
+        );
+        assert_eq!(
+            scope("hipoxemia foi entretanto descartada", "hipoxemia"),
+            Some(MentionScope::Negated)
+        );
+        assert_eq!(
+            scope("hipoxemia foi depois entretanto descartada", "hipoxemia"),
+            None
+        );
+        assert_eq!(scope("hipoxemia, descartada febre", "hipoxemia"), None);
+        assert_eq!(
+            scope("hipoxemia resolvida", "hipoxemia"),
+            Some(MentionScope::Historical)
+        );
+    }
+
+    #[test]
+    fn ambiguous_portuguese_words_are_not_cues() {
+        assert_eq!(scope("dispneia no leito", "dispneia"), None);
+        assert_eq!(
+            scope("no hypoxemia", "hypoxemia"),
+            Some(MentionScope::Negated)
+        );
+        assert_eq!(scope("no leito hipoxemia", "hipoxemia"), None);
+        assert_eq!(scope("mantém-se taquicárdico", "taquicárdico"), None);
+        assert_eq!(scope("sem melhora da hipoxemia", "hipoxemia"), None);
+    }
+
+    #[test]
+    fn any_affirmed_mention_wins() {
+        let re = Regex::new("(?i)hipox").unwrap();
+        assert!(is_affirmed_match("sem hipoxemia. agora hipoxemia", &re));
+        let (affirmed, excluded) = affirmed_match("nega hipoxemia", std::slice::from_ref(&re));
+        assert!(!affirmed);
+        assert_eq!(
+            excluded,
+            vec![(MentionScope::Negated, "hipoxemia".into(), "nega".into())]
+        );
+        assert!(!is_affirmed_substring("sem hipoxemia", "hipox"));
+    }
+}
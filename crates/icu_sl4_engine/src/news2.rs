@@ -0,0 +1,273 @@
+//! National Early Warning Score 2 (Royal College of Physicians, 2017).
+//!
+//! NEWS2 is computed from the effective `Input.measured` next to the
+//! protocol-based severity, never instead of it. Each parameter scores 0–3:
+//!
+//! | parameter           | feature    | 3               | 2        | 1                    | 0         |
+//! |---------------------|------------|-----------------|----------|----------------------|-----------|
+//! | respiration rate    | `rr_rpm`   | ≤8 or ≥25       | 21–24    | 9–11                 | 12–20     |
+//! | SpO2 (scale 1)      | `spo2_pct` | ≤91             | 92–93    | 94–95                | ≥96       |
+//! | supplemental oxygen | `fio2`     |                 | > 0.21   |                      | air       |
+//! | systolic BP         | `sbp_mmhg` | ≤90 or ≥220     | 91–100   | 101–110              | 111–219   |
+//! | pulse               | `hr_bpm`   | ≤40 or ≥131     | 111–130  | 41–50 or 91–110      | 51–90     |
+//! | consciousness       | `acvpu`    | C, V, P, U (≥1) |          |                      | alert (0) |
+//! | temperature         | `temp_c`   | ≤35.0           | ≥39.1    | 35.1–36.0, 38.1–39.0 | 36.1–38.0 |
+//!
+//! SpO2 scale 2 (target 88–92%) is used when `Input.patient` lists the
+//! condition [`SCALE_2_CONDITION`]: ≤83 scores 3, 84–85 2, 86–87 1, 88–92
+//! (or above on air) 0, and on oxygen 93–94 1, 95–96 2, ≥97 3.
+//!
+//! Missing parameters score nothing and are listed in `missing`, so an
+//! incomplete score is a lower bound of the full one; policies see it as
+//! `news2`. Values between the table's integers fall in the band above the
+//! last bound they exceed (a respiration rate of 20.5 scores 2). NEWS2 is
+//! not validated below 16 years, so no score is given for younger patients.
+
+use crate::Input;
+use serde::{Deserialize, Serialize};
+
+/// Trigger identifier of the aggregate score.
+pub const NEWS2_SCORE: &str = "news2";
+
+/// Patient condition selecting SpO2 scale 2.
+pub const SCALE_2_CONDITION: &str = "hypercapnic_respiratory_failure";
+
+/// Youngest age NEWS2 applies to.
+pub const NEWS2_MIN_AGE_YEARS: f64 = 16.0;
+
+/// Clinical response band of a score.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum News2Risk {
+    /// 0–4.
+    Low,
+    /// 0–4 with a single parameter scoring 3.
+    LowMedium,
+    /// 5–6.
+    Medium,
+    /// 7 or more.
+    High,
+}
+
+/// Sub-score of one NEWS2 parameter.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct News2Component {
+    /// NEWS2 parameter, e.g. `respiration_rate`.
+    pub parameter: String,
+    pub feature: String,
+    pub value: f64,
+    pub points: u8,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct News2 {
+    pub score: u8,
+    pub risk: News2Risk,
+    /// SpO2 scale used, 1 or 2.
+    pub spo2_scale: u8,
+    /// Sub-scores of the parameters present, in NEWS2 chart order.
+    pub breakdown: Vec<News2Component>,
+    /// Parameters without a measured value.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub missing: Vec<String>,
+}
+
+/// Points of `v` given the upper bounds of each band, lowest band first.
+fn band(v: f64, bounds: &[(f64, u8)], above: u8) -> u8 {
+    bounds
+        .iter()
+        .find(|(upper, _)| v <= *upper)
+        .map_or(above, |(_, points)| *points)
+}
+
+fn spo2_points(spo2: f64, scale_2: bool, on_oxygen: bool) -> u8 {
+    if !scale_2 {
+        return band(spo2, &[(91.0, 3), (93.0, 2), (95.0, 1)], 0);
+    }
+    if spo2 <= 92.0 || !on_oxygen {
+        return band(spo2, &[(83.0, 3), (85.0, 2), (87.0, 1)], 0);
+    }
+    band(spo2, &[(94.0, 1), (96.0, 2)], 3)
+}
+
+impl News2 {
+    /// NEWS2 of `input`; `None` when no parameter is measured or the
+    /// patient is younger than [`NEWS2_MIN_AGE_YEARS`].
+    pub fn from_input(input: &Input) -> Option<News2> {
+        let patient = input.patient.as_ref();
+        if patient
+            .and_then(|p| p.age_years)
+            .is_some_and(|age| age < NEWS2_MIN_AGE_YEARS)
+        {
+            return None;
+        }
+        let scale_2 = patient.is_some_and(|p| p.has_condition(SCALE_2_CONDITION));
+        let m = &input.measured;
+        let on_oxygen = m.get("fio2").map(|f| *f > 0.21);
+
+        let mut breakdown = Vec::new();
+        let mut missing = Vec::new();
+        let mut add =
+            |parameter: &str, feature: &str, points: &dyn Fn(f64) -> u8| match m.get(feature) {
+                Some(&value) => breakdown.push(News2Component {
+                    parameter: parameter.to_string(),
+                    feature: feature.to_string(),
+                    value,
+                    points: points(value),
+                }),
+                None => missing.push(parameter.to_string()),
+            };
+        add("respiration_rate", "rr_rpm", &|v| {
+            band(v, &[(8.0, 3), (11.0, 1), (20.0, 0), (24.0, 2)], 3)
+        });
+        add("spo2", "spo2_pct", &|v| {
+            spo2_points(v, scale_2, on_oxygen.unwrap_or(false))
+        });
+        add("supplemental_oxygen", "fio2", &|v| {
+            if v > 0.21 {
+                2
+            } else {
+                0
+            }
+        });
+        add("systolic_bp", "sbp_mmhg", &|v| {
+            band(v, &[(90.0, 3), (100.0, 2), (110.0, 1), (219.0, 0)], 3)
+        });
+        add("pulse", "hr_bpm", &|v| {
+            band(
+                v,
+                &[(40.0, 3), (50.0, 1), (90.0, 0), (110.0, 1), (130.0, 2)],
+                3,
+            )
+        });
+        add("consciousness", "acvpu", &|v| if v >= 1.0 { 3 } else { 0 });
+        add("temperature", "temp_c", &|v| {
+            band(v, &[(35.0, 3), (36.0, 1), (38.0, 0), (39.0, 1)], 2)
+        });
+
+        if breakdown.is_empty() {
+            return None;
+        }
+        let score: u8 = breakdown.iter().map(|c| c.points).sum();
+        let risk = match score {
+            7.. => News2Risk::High,
+            5..=6 => News2Risk::Medium,
+            _ if breakdown.iter().any(|c| c.points == 3) => News2Risk::LowMedium,
+            _ => News2Risk::Low,
+        };
+        Some(News2 {
+            score,
+            risk,
+            spo2_scale: if scale_2 { 2 } else { 1 },
+            breakdown,
+            missing,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{json, Value};
+
+    fn news2(measured: Value, patient: Value) -> Option<News2> {
+        let input: Input = serde_json::from_value(json!({
+            "text": "",
+            "measured": measured,
+            "patient": patient,
+        }))
+        .unwrap();
+        News2::from_input(&input)
+    }
+
+    /// Points of `feature` at each of `values`.
+    fn points(feature: &str, values: &[f64]) -> Vec<u8> {
+        values
+            .iter()
+            .map(|v| news2(json!({ feature: v }), Value::Null).unwrap().score)
+            .collect()
+    }
+
+    #[test]
+    fn respiration_rate_band_edges() {
+        let rr = [8.0, 9.0, 11.0, 12.0, 20.0, 20.5, 21.0, 24.0, 25.0];
+        assert_eq!(points("rr_rpm", &rr), [3, 1, 1, 0, 0, 2, 2, 2, 3]);
+    }
+
+    #[test]
+    fn spo2_scale_1_band_edges() {
+        let spo2 = [91.0, 92.0, 93.0, 94.0, 95.0, 96.0];
+        assert_eq!(points("spo2_pct", &spo2), [3, 2, 2, 1, 1, 0]);
+    }
+
+    #[test]
+    fn systolic_band_edges() {
+        let sbp = [90.0, 91.0, 100.0, 101.0, 110.0, 111.0, 219.0, 220.0];
+        assert_eq!(points("sbp_mmhg", &sbp), [3, 2, 2, 1, 1, 0, 0, 3]);
+    }
+
+    #[test]
+    fn pulse_band_edges() {
+        let hr = [
+            40.0, 41.0, 50.0, 51.0, 90.0, 91.0, 110.0, 111.0, 130.0, 131.0,
+        ];
+        assert_eq!(points("hr_bpm", &hr), [3, 1, 1, 0, 0, 1, 1, 2, 2, 3]);
+    }
+
+    #[test]
+    fn temperature_band_edges() {
+        let t = [35.0, 35.1, 36.0, 36.1, 38.0, 38.1, 39.0, 39.1];
+        assert_eq!(points("temp_c", &t), [3, 1, 1, 0, 0, 1, 1, 2]);
+    }
+
+    #[test]
+    fn oxygen_and_consciousness() {
+        assert_eq!(points("fio2", &[0.21, 0.22]), [0, 2]);
+        assert_eq!(points("acvpu", &[0.0, 1.0, 4.0]), [0, 3, 3]);
+    }
+
+    #[test]
+    fn spo2_scale_2_band_edges() {
+        let patient = json!({ "conditions": [SCALE_2_CONDITION] });
+        let on_air = |spo2: f64| {
+            let n = news2(json!({ "spo2_pct": spo2 }), patient.clone()).unwrap();
+            assert_eq!(n.spo2_scale, 2);
+            n.score
+        };
+        let scores: Vec<u8> = [83.0, 84.0, 85.0, 86.0, 87.0, 88.0, 97.0]
+            .iter()
+            .map(|v| on_air(*v))
+            .collect();
+        assert_eq!(scores, [3, 2, 2, 1, 1, 0, 0]);
+        // On oxygen the supplemental oxygen points (2) come on top.
+        let on_o2: Vec<u8> = [92.0, 93.0, 94.0, 95.0, 96.0, 97.0]
+            .iter()
+            .map(|v| {
+                let n = news2(json!({ "spo2_pct": v, "fio2": 0.4 }), patient.clone());
+                n.unwrap().score - 2
+            })
+            .collect();
+        assert_eq!(on_o2, [0, 1, 1, 2, 2, 3]);
+    }
+
+    #[test]
+    fn risk_bands() {
+        let risk = |measured| news2(measured, Value::Null).unwrap().risk;
+        assert_eq!(risk(json!({ "rr_rpm": 16 })), News2Risk::Low);
+        assert_eq!(risk(json!({ "acvpu": 1 })), News2Risk::LowMedium);
+        assert_eq!(risk(json!({ "acvpu": 1, "fio2": 0.4 })), News2Risk::Medium);
+        assert_eq!(
+            risk(json!({ "acvpu": 1, "fio2": 0.4, "hr_bpm": 120 })),
+            News2Risk::High
+        );
+    }
+
+    #[test]
+    fn missing_parameters_and_young_patients() {
+        let n = news2(json!({ "hr_bpm": 80 }), Value::Null).unwrap();
+        assert_eq!(n.missing.len(), 6);
+        assert!(news2(json!({}), Value::Null).is_none());
+        assert!(news2(json!({ "hr_bpm": 80 }), json!({ "age_years": 15.9 })).is_none());
+        assert!(news2(json!({ "hr_bpm": 80 }), json!({ "age_years": 16 })).is_some());
+    }
+}
@@ -0,0 +1,126 @@
+//! Structured normative references and the links carried in `Ast.normative`.
+//!
+//! A policy lists its citations under `normative_references`; actions point
+//! at them with `cites` and AST signals with `signal_cites`. Free-text
+//! references are still accepted (and hash as before) but cannot be linked.
+
+use crate::{Ast, Decision, Policy};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// A guideline or statement backing part of a policy.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Citation {
+    /// Short key used by `cites`, e.g. `ATS-2020`.
+    pub id: String,
+    pub title: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub section: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub year: Option<u16>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub uri: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub doi: Option<String>,
+}
+
+impl Citation {
+    /// One-line human form: `Title (2020) §3.2 doi:…`.
+    pub fn label(&self) -> String {
+        let mut s = self.title.clone();
+        if let Some(y) = self.year {
+            s.push_str(&format!(" ({y})"));
+        }
+        if let Some(sec) = &self.section {
+            s.push_str(&format!(" §{sec}"));
+        }
+        if let Some(doi) = &self.doi {
+            s.push_str(&format!(" doi:{doi}"));
+        } else if let Some(uri) = &self.uri {
+            s.push_str(&format!(" {uri}"));
+        }
+        s
+    }
+}
+
+/// Entry of `Policy.normative_references`: a citation or legacy free text.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(untagged)]
+pub enum NormativeReference {
+    Cited(Citation),
+    Text(String),
+}
+
+impl NormativeReference {
+    pub fn citation(&self) -> Option<&Citation> {
+        match self {
+            NormativeReference::Cited(c) => Some(c),
+            NormativeReference::Text(_) => None,
+        }
+    }
+}
+
+/// `Ast.normative`: which citations back each decision action and AST signal.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct NormativeLinks {
+    /// Every citation referenced below, in first-use order.
+    pub references: Vec<Citation>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub actions: BTreeMap<String, Vec<String>>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub signals: BTreeMap<String, Vec<String>>,
+}
+
+impl NormativeLinks {
+    pub fn citation(&self, id: &str) -> Option<&Citation> {
+        self.references.iter().find(|c| c.id == id)
+    }
+}
+
+fn link(links: &mut NormativeLinks, key: &str, ids: &[String], p: &Policy, actions: bool) {
+    for id in ids {
+        let Some(c) = p
+            .normative_references
+            .iter()
+            .filter_map(NormativeReference::citation)
+            .find(|c| &c.id == id)
+        else {
+            continue;
+        };
+        if links.citation(id).is_none() {
+            links.references.push(c.clone());
+        }
+        let map = if actions {
+            &mut links.actions
+        } else {
+            &mut links.signals
+        };
+        let v = map.entry(key.to_string()).or_default();
+        if !v.contains(id) {
+            v.push(id.clone());
+        }
+    }
+}
+
+/// Link the decision's actions and the AST's signals to the citations of the
+/// policies that fired. `None` when nothing is cited.
+pub fn normative_links<'a>(
+    fired: impl IntoIterator<Item = &'a Policy>,
+    ast: &Ast,
+    decision: &Decision,
+) -> Option<NormativeLinks> {
+    let mut links = NormativeLinks::default();
+    for p in fired {
+        for a in &decision.actions {
+            if let Some(decl) = p.actions.iter().find(|d| d.name == a.name) {
+                link(&mut links, &a.name, &decl.cites, p, true);
+            }
+        }
+        for s in &ast.signals {
+            if let Some(ids) = p.signal_cites.get(s) {
+                link(&mut links, s, ids, p, false);
+            }
+        }
+    }
+    (!links.references.is_empty()).then_some(links)
+}
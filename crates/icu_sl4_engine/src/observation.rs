@@ -0,0 +1,181 @@
+//! When and where each measured value was observed, and how old it may be.
+//!
+//! `Input.observations` optionally carries an observation timestamp (RFC
+//! 3339) and source for each key of `Input.measured`. Being part of the
+//! input, both are covered by `input_hash`. A policy limits the age of a
+//! feature with `max_age_s`; at decision time a value older than the
+//! strictest limit among the applied policies is stale: it is treated as
+//! missing (its series included) and reported in `Ast.stale`. Values without
+//! a timestamp are taken as current. A timestamp up to [`MAX_CLOCK_SKEW_S`]
+//! ahead of the decision clock counts as age 0, to absorb device clock
+//! drift; one further ahead is refused, since a wrong clock would otherwise
+//! keep an old value fresh.
+
+use crate::{Input, Policy};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use thiserror::Error;
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+
+/// How far, in seconds, an observation may be stamped ahead of the decision
+/// clock.
+pub const MAX_CLOCK_SKEW_S: u64 = 300;
+
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum ObservationError {
+    #[error("observation of `{feature}`: `{value}` is not an RFC 3339 timestamp")]
+    BadTime { feature: String, value: String },
+    #[error("decision time `{0}` is not an RFC 3339 timestamp")]
+    BadClock(String),
+    #[error(
+        "observation of `{feature}` at `{value}` is more than {MAX_CLOCK_SKEW_S} s ahead of the decision time"
+    )]
+    Future { feature: String, value: String },
+}
+
+/// Provenance of one measured value.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct Observation {
+    /// Observation time (RFC 3339).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub at: Option<String>,
+    /// Device or system that produced the value, e.g. `monitor:bed-12`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+}
+
+/// A measured value left out of the decision for being too old, as recorded
+/// in `Ast.stale`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct StaleValue {
+    pub feature: String,
+    pub value: f64,
+    pub observed_at: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    pub age_s: u64,
+    pub max_age_s: u64,
+}
+
+/// Strictest `max_age_s` per feature among `policies`.
+pub fn staleness_limits<'a>(
+    policies: impl IntoIterator<Item = &'a Policy>,
+) -> BTreeMap<String, u64> {
+    let mut limits: BTreeMap<String, u64> = BTreeMap::new();
+    for p in policies {
+        for (feature, &max) in &p.max_age_s {
+            limits
+                .entry(feature.clone())
+                .and_modify(|m| *m = (*m).min(max))
+                .or_insert(max);
+        }
+    }
+    limits
+}
+
+/// Measured values of `input` older than `limits` at `now_rfc3339`.
+pub fn stale_values(
+    input: &Input,
+    limits: &BTreeMap<String, u64>,
+    now_rfc3339: &str,
+) -> Result<Vec<StaleValue>, ObservationError> {
+    let now = OffsetDateTime::parse(now_rfc3339, &Rfc3339)
+        .map_err(|_| ObservationError::BadClock(now_rfc3339.to_string()))?;
+    let mut out = Vec::new();
+    for (feature, &value) in &input.measured {
+        let Some(obs) = input.observations.get(feature) else {
+            continue;
+        };
+        let Some(at) = &obs.at else { continue };
+        let observed =
+            OffsetDateTime::parse(at, &Rfc3339).map_err(|_| ObservationError::BadTime {
+                feature: feature.clone(),
+                value: at.clone(),
+            })?;
+        let age = (now - observed).whole_seconds();
+        if -age > MAX_CLOCK_SKEW_S as i64 {
+            return Err(ObservationError::Future {
+                feature: feature.clone(),
+                value: at.clone(),
+            });
+        }
+        let Some(&max_age_s) = limits.get(feature) else {
+            continue;
+        };
+        let age_s = age.max(0) as u64;
+        if age_s > max_age_s {
+            out.push(StaleValue {
+                feature: feature.clone(),
+                value,
+                observed_at: at.clone(),
+                source: obs.source.clone(),
+                age_s,
+                max_age_s,
+            });
+        }
+    }
+    Ok(out)
+}
+
+/// `input` without its stale values, and the values removed.
+pub fn fresh_input(
+    input: &Input,
+    limits: &BTreeMap<String, u64>,
+    now_rfc3339: &str,
+) -> Result<(Input, Vec<StaleValue>), ObservationError> {
+    let stale = stale_values(input, limits, now_rfc3339)?;
+    let mut fresh = input.clone();
+    for s in &stale {
+        fresh.measured.remove(&s.feature);
+        fresh.series.remove(&s.feature);
+    }
+    Ok((fresh, stale))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const NOW: &str = "2024-01-01T12:00:00Z";
+
+    fn stale(at: &str, max_age_s: Option<u64>) -> Result<Vec<StaleValue>, ObservationError> {
+        let input: Input = serde_json::from_value(json!({
+            "text": "",
+            "measured": { "spo2_pct": 90 },
+            "observations": { "spo2_pct": { "at": at } },
+        }))
+        .unwrap();
+        let limits = max_age_s
+            .map(|m| ("spo2_pct".to_string(), m))
+            .into_iter()
+            .collect();
+        stale_values(&input, &limits, NOW)
+    }
+
+    #[test]
+    fn age_is_measured_against_the_decision_clock() {
+        assert!(stale("2024-01-01T11:55:00Z", Some(300)).unwrap().is_empty());
+        let s = stale("2024-01-01T11:54:59Z", Some(300)).unwrap();
+        assert_eq!((s[0].age_s, s[0].max_age_s), (301, 300));
+    }
+
+    #[test]
+    fn small_skew_ahead_counts_as_current() {
+        assert!(stale("2024-01-01T12:05:00Z", Some(0)).unwrap().is_empty());
+    }
+
+    #[test]
+    fn timestamps_too_far_ahead_are_refused() {
+        assert_eq!(
+            stale("2024-01-01T12:05:01Z", Some(300)),
+            Err(ObservationError::Future {
+                feature: "spo2_pct".into(),
+                value: "2024-01-01T12:05:01Z".into(),
+            })
+        );
+        // Refused even for a feature without an age limit.
+        assert!(stale("2025-01-01T00:00:00Z", None).is_err());
+    }
+}
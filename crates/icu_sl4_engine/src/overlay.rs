@@ -0,0 +1,434 @@
+//! Policy overlays: a base protocol refined per jurisdiction, hospital and unit.
+//!
+//! An overlay names the protocol it `extends` and the `level` it applies at.
+//! Overlays are applied in level order (jurisdiction, hospital, unit),
+//! whatever order they are given in, and at most one per level. The result is
+//! an ordinary [`Policy`] whose `overlays` field records the chain, so the
+//! effective policy hashes, signs and verifies like any other.
+
+use crate::{
+    blake3_hash_json, validate_policy, Action, NormativeReference, Policy, PolicyError, Severity,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum OverlayError {
+    #[error("overlay YAML: {0}")]
+    Yaml(#[from] serde_yaml::Error),
+    #[error("overlay I/O: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("overlay `{overlay_id}` extends `{extends}`, not `{protocol_id}`")]
+    WrongBase {
+        overlay_id: String,
+        extends: String,
+        protocol_id: String,
+    },
+    #[error("overlays `{first}` and `{second}` both apply at level {level:?}")]
+    DuplicateLevel {
+        level: OverlayLevel,
+        first: String,
+        second: String,
+    },
+    #[error("overlay `{overlay_id}` ({level:?}) cannot follow a {previous:?} overlay")]
+    LevelOrder {
+        overlay_id: String,
+        level: OverlayLevel,
+        previous: OverlayLevel,
+    },
+    #[error("overlay `{overlay_id}` removes unknown action `{action}`")]
+    UnknownAction { overlay_id: String, action: String },
+    #[error("overlay `{overlay_id}` adds action `{action}` without `max_delay_s`")]
+    MissingDelay { overlay_id: String, action: String },
+    #[error("effective policy after overlay `{overlay_id}`: {source}")]
+    Invalid {
+        overlay_id: String,
+        #[source]
+        source: PolicyError,
+    },
+}
+
+/// Where an overlay applies; also the merge order.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum OverlayLevel {
+    Jurisdiction,
+    Hospital,
+    Unit,
+}
+
+/// Change to one action: fields left out keep the base value. An action not
+/// in the base is added and must set `max_delay_s`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ActionPatch {
+    pub name: String,
+    #[serde(default)]
+    pub max_delay_s: Option<u64>,
+    #[serde(default)]
+    pub when: Option<String>,
+    #[serde(default)]
+    pub conflicts_with: Option<Vec<String>>,
+    #[serde(default)]
+    pub cites: Option<Vec<String>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PolicyOverlay {
+    pub overlay_id: String,
+    /// `protocol_id` of the base policy.
+    pub extends: String,
+    pub level: OverlayLevel,
+    /// Jurisdiction, hospital or unit name, e.g. `PT`, `HSJ`, `UCI-2`.
+    pub scope: String,
+    pub version: String,
+    #[serde(default)]
+    pub source: Option<String>,
+    /// Parameters to set or override (thresholds referenced as `$name`).
+    #[serde(default)]
+    pub params: BTreeMap<String, f64>,
+    /// Per-profile parameter overrides to set or override.
+    #[serde(default)]
+    pub profiles: BTreeMap<String, BTreeMap<String, f64>>,
+    /// Staleness limits to set or override, in seconds per feature.
+    #[serde(default)]
+    pub max_age_s: BTreeMap<String, u64>,
+    /// Replaces the trigger list when present.
+    #[serde(default)]
+    pub triggers: Option<Vec<String>>,
+    /// Appended after `triggers` is applied.
+    #[serde(default)]
+    pub add_triggers: Vec<String>,
+    #[serde(default)]
+    pub severity: Option<Severity>,
+    #[serde(default)]
+    pub priority: Option<u32>,
+    /// Per-severity hazard overrides.
+    #[serde(default)]
+    pub hazards: BTreeMap<Severity, String>,
+    /// Applied after `remove_actions`.
+    #[serde(default)]
+    pub actions: Vec<ActionPatch>,
+    #[serde(default)]
+    pub remove_actions: Vec<String>,
+    /// Appended to the base references.
+    #[serde(default)]
+    pub normative_references: Vec<NormativeReference>,
+    /// Per-signal citation overrides.
+    #[serde(default)]
+    pub signal_cites: BTreeMap<String, Vec<String>>,
+}
+
+/// One applied overlay, as recorded in the effective policy and `ProofPack`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct OverlayRef {
+    pub overlay_id: String,
+    pub extends: String,
+    pub level: OverlayLevel,
+    pub scope: String,
+    pub version: String,
+    pub overlay_hash: String,
+}
+
+impl PolicyOverlay {
+    pub fn hash(&self) -> anyhow::Result<String> {
+        blake3_hash_json(&serde_json::to_value(self)?)
+    }
+}
+
+pub fn parse_overlay_yaml(s: &str) -> Result<PolicyOverlay, OverlayError> {
+    Ok(serde_yaml::from_str(s)?)
+}
+
+pub fn load_overlay_yaml(path: &Path) -> Result<PolicyOverlay, OverlayError> {
+    parse_overlay_yaml(&fs::read_to_string(path)?)
+}
+
+fn apply_overlay(p: &mut Policy, o: &PolicyOverlay) -> Result<(), OverlayError> {
+    p.params
+        .extend(o.params.iter().map(|(k, v)| (k.clone(), *v)));
+    for (profile, params) in &o.profiles {
+        p.profiles
+            .entry(profile.clone())
+            .or_default()
+            .extend(params.iter().map(|(k, v)| (k.clone(), *v)));
+    }
+    p.max_age_s
+        .extend(o.max_age_s.iter().map(|(k, v)| (k.clone(), *v)));
+    if let Some(t) = &o.triggers {
+        p.triggers.clone_from(t);
+    }
+    for t in &o.add_triggers {
+        if !p.triggers.contains(t) {
+            p.triggers.push(t.clone());
+        }
+    }
+    if let Some(s) = &o.severity {
+        p.severity = s.clone();
+    }
+    if let Some(prio) = o.priority {
+        p.priority = prio;
+    }
+    p.hazards
+        .extend(o.hazards.iter().map(|(k, v)| (k.clone(), v.clone())));
+
+    for name in &o.remove_actions {
+        let before = p.actions.len();
+        p.actions.retain(|a| &a.name != name);
+        if p.actions.len() == before {
+            return Err(OverlayError::UnknownAction {
+                overlay_id: o.overlay_id.clone(),
+                action: name.clone(),
+            });
+        }
+    }
+    for patch in &o.actions {
+        match p.actions.iter_mut().find(|a| a.name == patch.name) {
+            Some(a) => {
+                if let Some(d) = patch.max_delay_s {
+                    a.max_delay_s = d;
+                }
+                if let Some(w) = &patch.when {
+                    a.when = Some(w.clone());
+                }
+                if let Some(c) = &patch.conflicts_with {
+                    a.conflicts_with.clone_from(c);
+                }
+                if let Some(c) = &patch.cites {
+                    a.cites.clone_from(c);
+                }
+            }
+            None => {
+                let max_delay_s = patch
+                    .max_delay_s
+                    .ok_or_else(|| OverlayError::MissingDelay {
+                        overlay_id: o.overlay_id.clone(),
+                        action: patch.name.clone(),
+                    })?;
+                p.actions.push(Action {
+                    name: patch.name.clone(),
+                    max_delay_s,
+                    deadline_s: None,
+                    when: patch.when.clone(),
+                    conflicts_with: patch.conflicts_with.clone().unwrap_or_default(),
+                    cites: patch.cites.clone().unwrap_or_default(),
+                });
+            }
+        }
+    }
+    for r in &o.normative_references {
+        if !p.normative_references.contains(r) {
+            p.normative_references.push(r.clone());
+        }
+    }
+    p.signal_cites
+        .extend(o.signal_cites.iter().map(|(k, v)| (k.clone(), v.clone())));
+
+    p.jurisdiction = format!("{}/{}", p.jurisdiction, o.scope);
+    if let Some(s) = &o.source {
+        p.source = format!("{}; {s}", p.source);
+    }
+    Ok(())
+}
+
+/// Apply `overlays` to `base` in level order and validate the result.
+///
+/// `base` may itself be an effective policy; new overlays must then sit at
+/// deeper levels than the ones it already records.
+pub fn resolve_policy(base: &Policy, overlays: &[PolicyOverlay]) -> anyhow::Result<Policy> {
+    let mut ordered: Vec<&PolicyOverlay> = overlays.iter().collect();
+    ordered.sort_by_key(|o| o.level);
+
+    let mut p = base.clone();
+    let mut previous = p.overlays.last().map(|r| (r.level, r.overlay_id.clone()));
+    for o in ordered {
+        if o.extends != p.protocol_id {
+            return Err(OverlayError::WrongBase {
+                overlay_id: o.overlay_id.clone(),
+                extends: o.extends.clone(),
+                protocol_id: p.protocol_id.clone(),
+            }
+            .into());
+        }
+        if let Some((level, id)) = &previous {
+            if *level == o.level {
+                return Err(OverlayError::DuplicateLevel {
+                    level: o.level,
+                    first: id.clone(),
+                    second: o.overlay_id.clone(),
+                }
+                .into());
+            }
+            if *level > o.level {
+                return Err(OverlayError::LevelOrder {
+                    overlay_id: o.overlay_id.clone(),
+                    level: o.level,
+                    previous: *level,
+                }
+                .into());
+            }
+        }
+        apply_overlay(&mut p, o)?;
+        validate_policy(&p).map_err(|source| OverlayError::Invalid {
+            overlay_id: o.overlay_id.clone(),
+            source,
+        })?;
+        p.overlays.push(OverlayRef {
+            overlay_id: o.overlay_id.clone(),
+            extends: o.extends.clone(),
+            level: o.level,
+            scope: o.scope.clone(),
+            version: o.version.clone(),
+            overlay_hash: o.hash()?,
+        });
+        previous = Some((o.level, o.overlay_id.clone()));
+    }
+    Ok(p)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse_policy_yaml, PolicyError, TriggerError};
+
+    fn base() -> Policy {
+        parse_policy_yaml(
+            "\
+protocol_id: hypox
+version: 1.0.0
+jurisdiction: PT
+source: base
+params:
+  spo2_low: 90
+triggers:
+  - spo2_pct < $spo2_low
+severity: CRITICAL
+hazards:
+  CRITICAL: HYPOXEMIA_CRITICAL
+  URGENT: HYPOXEMIA_MODERATE
+actions:
+  - name: call_attending
+    max_delay_s: 60
+  - name: increase_O2_100
+    max_delay_s: 0
+",
+        )
+        .unwrap()
+    }
+
+    fn overlay(id: &str, level: &str, body: &str) -> PolicyOverlay {
+        parse_overlay_yaml(&format!(
+            "overlay_id: {id}\nextends: hypox\nlevel: {level}\nscope: {id}\nversion: 1.0.0\n{body}"
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn overlays_merge_in_level_order_whatever_the_given_order() {
+        let unit = overlay(
+            "UCI-2",
+            "unit",
+            "params: { spo2_low: 92 }\nactions:\n  - { name: call_attending, max_delay_s: 10 }\n",
+        );
+        let hospital = overlay(
+            "HSJ",
+            "hospital",
+            "params: { spo2_low: 88 }\nactions:\n  - { name: call_attending, max_delay_s: 20 }\n",
+        );
+        let p = resolve_policy(&base(), &[unit.clone(), hospital.clone()]).unwrap();
+        assert_eq!(p.params["spo2_low"], 92.0);
+        assert_eq!(p.actions[0].max_delay_s, 10);
+        assert_eq!(p.jurisdiction, "PT/HSJ/UCI-2");
+        let chain: Vec<&str> = p.overlays.iter().map(|o| o.overlay_id.as_str()).collect();
+        assert_eq!(chain, ["HSJ", "UCI-2"]);
+        assert_eq!(p.overlays[1].overlay_hash, unit.hash().unwrap());
+        assert_eq!(p, resolve_policy(&base(), &[hospital, unit]).unwrap());
+    }
+
+    #[test]
+    fn resolved_policy_can_take_deeper_overlays_only() {
+        let hospital = overlay("HSJ", "hospital", "");
+        let p = resolve_policy(&base(), &[hospital]).unwrap();
+        assert!(resolve_policy(&p, &[overlay("UCI-2", "unit", "")]).is_ok());
+        let err = resolve_policy(&p, &[overlay("PT-N", "jurisdiction", "")]).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<OverlayError>(),
+            Some(OverlayError::LevelOrder { .. })
+        ));
+        let err = resolve_policy(
+            &base(),
+            &[overlay("A", "unit", ""), overlay("B", "unit", "")],
+        )
+        .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<OverlayError>(),
+            Some(OverlayError::DuplicateLevel { .. })
+        ));
+    }
+
+    #[test]
+    fn params_added_by_an_overlay_resolve_in_its_triggers() {
+        let o = overlay(
+            "HSJ",
+            "hospital",
+            "params: { hr_high: 130 }\nadd_triggers: ['hr_bpm > $hr_high']\n",
+        );
+        let p = resolve_policy(&base(), &[o]).unwrap();
+        assert_eq!(p.triggers, ["spo2_pct < $spo2_low", "hr_bpm > $hr_high"]);
+        assert_eq!(p.params_for(None)["hr_high"], 130.0);
+    }
+
+    #[test]
+    fn unknown_params_are_refused() {
+        let o = overlay("HSJ", "hospital", "add_triggers: ['hr_bpm > $hr_high']\n");
+        let err = resolve_policy(&base(), &[o]).unwrap_err();
+        match err.downcast_ref::<OverlayError>() {
+            Some(OverlayError::Invalid { overlay_id, source }) => {
+                assert_eq!(overlay_id, "HSJ");
+                assert!(matches!(
+                    source,
+                    PolicyError::Trigger {
+                        source: TriggerError::UnknownParam { .. },
+                        ..
+                    }
+                ));
+            }
+            other => panic!("expected an invalid effective policy, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn action_patches_are_checked() {
+        let err = resolve_policy(
+            &base(),
+            &[overlay("H", "hospital", "remove_actions: [x]\n")],
+        )
+        .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<OverlayError>(),
+            Some(OverlayError::UnknownAction { action, .. }) if action == "x"
+        ));
+        let err = resolve_policy(
+            &base(),
+            &[overlay(
+                "H",
+                "hospital",
+                "actions:\n  - { name: notify_rt }\n",
+            )],
+        )
+        .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<OverlayError>(),
+            Some(OverlayError::MissingDelay { action, .. }) if action == "notify_rt"
+        ));
+        let mut other = overlay("H", "hospital", "");
+        other.extends = "sepsis".into();
+        let err = resolve_policy(&base(), &[other]).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<OverlayError>(),
+            Some(OverlayError::WrongBase { .. })
+        ));
+    }
+}
@@ -0,0 +1,352 @@
+//! Patient context and the threshold profiles selected from it.
+//!
+//! `Input.patient` optionally describes who the measurements belong to: age,
+//! weight, known conditions and a documented baseline of measured features
+//! (e.g. the usual SpO2 of a patient with chronic hypoxemia). The catalog
+//! declares threshold profiles, each a selector over that context plus
+//! overrides of signal thresholds. Profiles are tried in catalog order and
+//! the first whose selector matches is applied to both channels, recorded in
+//! `Ast.profile` and marked on the frontier certificates it moved. Policies
+//! adjust their own `$params` per profile with `profiles`. Without a context,
+//! or when no selector matches, the catalog's thresholds apply unchanged.
+//!
+//! A known age first selects the thresholds of its [`AgeBand`]; a profile
+//! then overrides on top of them.
+
+use crate::age_bands::{AgeBand, AgeBandTables};
+use crate::trigger::KNOWN_FEATURES;
+use crate::SignalCatalog;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use thiserror::Error;
+
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum PatientError {
+    #[error("patient `{0}` must be a finite, non-negative number")]
+    BadNumber(String),
+    #[error("patient baseline names unknown feature `{0}`")]
+    UnknownBaseline(String),
+    #[error("patient baseline of `{0}` is not a finite number")]
+    BadBaseline(String),
+}
+
+/// What is known about the patient, beyond the current measurements.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct PatientContext {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub age_years: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub weight_kg: Option<f64>,
+    /// Known conditions as lowercase ids, e.g. `copd`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub conditions: Vec<String>,
+    /// Documented usual value of measured features, e.g. `spo2_pct: 88`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub baseline: BTreeMap<String, f64>,
+}
+
+impl PatientContext {
+    pub fn validate(&self) -> Result<(), PatientError> {
+        for (field, v) in [("age_years", self.age_years), ("weight_kg", self.weight_kg)] {
+            if v.is_some_and(|v| !(v.is_finite() && v >= 0.0)) {
+                return Err(PatientError::BadNumber(field.into()));
+            }
+        }
+        for (feature, v) in &self.baseline {
+            if !KNOWN_FEATURES.contains(&feature.as_str()) {
+                return Err(PatientError::UnknownBaseline(feature.clone()));
+            }
+            if !v.is_finite() {
+                return Err(PatientError::BadBaseline(feature.clone()));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn has_condition(&self, id: &str) -> bool {
+        self.conditions.iter().any(|c| c.eq_ignore_ascii_case(id))
+    }
+}
+
+/// Which patients a profile applies to. Every criterion given must hold; a
+/// criterion on age needs the age to be known.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct ProfileSelector {
+    /// Any one of these conditions.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub conditions: Vec<String>,
+    /// Inclusive lower age bound.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_age_years: Option<f64>,
+    /// Exclusive upper age bound.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_age_years: Option<f64>,
+    /// Features whose baseline must be documented.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub baseline: Vec<String>,
+}
+
+impl ProfileSelector {
+    pub fn is_empty(&self) -> bool {
+        self.conditions.is_empty()
+            && self.min_age_years.is_none()
+            && self.max_age_years.is_none()
+            && self.baseline.is_empty()
+    }
+
+    pub fn matches(&self, patient: &PatientContext) -> bool {
+        let age_ok = match (self.min_age_years, self.max_age_years) {
+            (None, None) => true,
+            (min, max) => patient
+                .age_years
+                .is_some_and(|age| min.map_or(true, |m| age >= m) && max.map_or(true, |m| age < m)),
+        };
+        age_ok
+            && (self.conditions.is_empty()
+                || self.conditions.iter().any(|c| patient.has_condition(c)))
+            && self
+                .baseline
+                .iter()
+                .all(|f| patient.baseline.contains_key(f))
+    }
+}
+
+/// New threshold for the numeric predicates of `signal` on `feature`: either
+/// a fixed `threshold` or `baseline_offset` added to the patient's baseline.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ThresholdOverride {
+    pub signal: String,
+    pub feature: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub threshold: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub baseline_offset: Option<f64>,
+}
+
+impl ThresholdOverride {
+    /// The threshold for `patient`; `None` for a baseline offset without a
+    /// documented baseline.
+    pub fn threshold_for(&self, patient: &PatientContext) -> Option<f64> {
+        match (self.threshold, self.baseline_offset) {
+            (Some(t), _) => Some(t),
+            (None, Some(off)) => patient.baseline.get(&self.feature).map(|b| b + off),
+            (None, None) => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ThresholdProfile {
+    pub id: String,
+    pub when: ProfileSelector,
+    pub thresholds: Vec<ThresholdOverride>,
+}
+
+impl ThresholdProfile {
+    /// Whether this profile moves the threshold of `signal` on `feature`.
+    pub fn overrides(&self, signal: &str, feature: &str) -> bool {
+        self.thresholds
+            .iter()
+            .any(|o| o.signal == signal && o.feature == feature)
+    }
+}
+
+/// First profile of `profiles` that applies to `patient`.
+pub fn select_profile<'a>(
+    profiles: &'a [ThresholdProfile],
+    patient: Option<&PatientContext>,
+) -> Option<&'a ThresholdProfile> {
+    let patient = patient?;
+    profiles.iter().find(|p| p.when.matches(patient))
+}
+
+/// The thresholds in force for one patient.
+#[derive(Debug, Clone)]
+pub struct PatientThresholds<'a> {
+    /// The catalog with the band's, then the profile's, thresholds applied.
+    pub catalog: Cow<'a, SignalCatalog>,
+    pub age_band: Option<&'a AgeBand>,
+    pub profile: Option<&'a ThresholdProfile>,
+}
+
+impl PatientThresholds<'_> {
+    /// Where the threshold of `signal` on `feature` came from: the profile
+    /// or the age band, when not from the catalog itself.
+    pub fn source(&self, signal: &str, feature: &str, tables: &AgeBandTables) -> ThresholdSource {
+        if self.profile.is_some_and(|p| p.overrides(signal, feature)) {
+            ThresholdSource::Profile
+        } else if self.age_band.is_some() && tables.table_for(signal, feature).is_some() {
+            ThresholdSource::AgeBand
+        } else {
+            ThresholdSource::Catalog
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ThresholdSource {
+    Catalog,
+    AgeBand,
+    Profile,
+}
+
+/// `catalog` adapted to `patient`: its age band from `tables`, then its
+/// threshold profile.
+pub fn patient_thresholds<'a>(
+    catalog: &'a SignalCatalog,
+    tables: &'a AgeBandTables,
+    patient: Option<&PatientContext>,
+) -> PatientThresholds<'a> {
+    let age_band = patient
+        .and_then(|p| p.age_years)
+        .and_then(|age| tables.band_for(age));
+    let profile = select_profile(&catalog.doc.profiles, patient);
+    let Some(patient) = patient.filter(|_| age_band.is_some() || profile.is_some()) else {
+        return PatientThresholds {
+            catalog: Cow::Borrowed(catalog),
+            age_band,
+            profile,
+        };
+    };
+    let mut adapted = catalog.clone();
+    if let Some(band) = age_band {
+        tables.apply(band, &mut adapted);
+    }
+    for o in profile.iter().flat_map(|p| &p.thresholds) {
+        if let Some(t) = o.threshold_for(patient) {
+            adapted.set_threshold(&o.signal, &o.feature, t);
+        }
+    }
+    PatientThresholds {
+        catalog: Cow::Owned(adapted),
+        age_band,
+        profile,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn patient(v: serde_json::Value) -> PatientContext {
+        serde_json::from_value(v).unwrap()
+    }
+
+    fn threshold(catalog: &SignalCatalog, signal: &str, feature: &str) -> f64 {
+        let def = catalog.doc.signals.iter().find(|d| d.id == signal).unwrap();
+        def.numeric
+            .iter()
+            .find(|p| p.feature == feature)
+            .unwrap()
+            .threshold
+    }
+
+    #[test]
+    fn validate_rejects_bad_numbers_and_baselines() {
+        assert_eq!(
+            patient(json!({ "age_years": 0, "weight_kg": 3.2 })).validate(),
+            Ok(())
+        );
+        let mut p = patient(json!({ "age_years": -1 }));
+        assert_eq!(
+            p.validate(),
+            Err(PatientError::BadNumber("age_years".into()))
+        );
+        p.age_years = Some(f64::NAN);
+        assert_eq!(
+            p.validate(),
+            Err(PatientError::BadNumber("age_years".into()))
+        );
+        p.age_years = None;
+        p.weight_kg = Some(f64::INFINITY);
+        assert_eq!(
+            p.validate(),
+            Err(PatientError::BadNumber("weight_kg".into()))
+        );
+        let p = patient(json!({ "baseline": { "spo2": 88 } }));
+        assert_eq!(
+            p.validate(),
+            Err(PatientError::UnknownBaseline("spo2".into()))
+        );
+        let mut p = patient(json!({ "baseline": { "spo2_pct": 88 } }));
+        p.baseline.insert("spo2_pct".into(), f64::NAN);
+        assert_eq!(
+            p.validate(),
+            Err(PatientError::BadBaseline("spo2_pct".into()))
+        );
+    }
+
+    #[test]
+    fn first_matching_profile_in_catalog_order_wins() {
+        let profiles = &SignalCatalog::builtin().doc.profiles;
+        let id = |v| select_profile(profiles, Some(&patient(v))).map(|p| p.id.as_str());
+        assert_eq!(id(json!({ "conditions": ["COPD"] })), Some("copd"));
+        // A documented baseline is listed before the condition.
+        let both = json!({ "conditions": ["copd"], "baseline": { "spo2_pct": 86 } });
+        assert_eq!(id(both), Some("chronic_hypoxemia"));
+        assert_eq!(id(json!({ "conditions": ["asthma"] })), None);
+        assert_eq!(select_profile(profiles, None), None);
+    }
+
+    #[test]
+    fn age_criteria_need_a_known_age() {
+        let sel = ProfileSelector {
+            min_age_years: Some(65.0),
+            max_age_years: Some(80.0),
+            ..Default::default()
+        };
+        assert!(sel.matches(&patient(json!({ "age_years": 65 }))));
+        assert!(!sel.matches(&patient(json!({ "age_years": 80 }))));
+        assert!(!sel.matches(&patient(json!({}))));
+        assert!(ProfileSelector::default().matches(&patient(json!({}))));
+    }
+
+    #[test]
+    fn baseline_offsets_need_a_baseline() {
+        let o = ThresholdOverride {
+            signal: "hypoxemia".into(),
+            feature: "spo2_pct".into(),
+            threshold: None,
+            baseline_offset: Some(-3.0),
+        };
+        let p = patient(json!({ "baseline": { "spo2_pct": 88 } }));
+        assert_eq!(o.threshold_for(&p), Some(85.0));
+        assert_eq!(o.threshold_for(&patient(json!({}))), None);
+    }
+
+    #[test]
+    fn profile_overrides_the_age_band() {
+        let catalog = SignalCatalog::builtin();
+        let tables = AgeBandTables::builtin();
+        // An infant with COPD: the band sets the heart rate, the profile SpO2.
+        let p = patient(json!({ "age_years": 0.5, "conditions": ["copd"] }));
+        let t = patient_thresholds(catalog, tables, Some(&p));
+        assert_eq!(t.age_band.map(|b| b.id.as_str()), Some("infant"));
+        assert_eq!(t.profile.map(|p| p.id.as_str()), Some("copd"));
+        assert_eq!(threshold(&t.catalog, "hypoxemia", "spo2_pct"), 88.0);
+        assert_eq!(threshold(&t.catalog, "tachycardia", "hr_bpm"), 180.0);
+        assert_eq!(
+            t.source("hypoxemia", "spo2_pct", tables),
+            ThresholdSource::Profile
+        );
+        assert_eq!(
+            t.source("tachycardia", "hr_bpm", tables),
+            ThresholdSource::AgeBand
+        );
+        assert_eq!(
+            t.source("severe_hypoxemia", "spo2_pct", tables),
+            ThresholdSource::Catalog
+        );
+
+        // An adult without a profile keeps the catalog as is.
+        let adult = patient(json!({ "age_years": 40 }));
+        let t = patient_thresholds(catalog, tables, Some(&adult));
+        assert!(matches!(t.catalog, Cow::Borrowed(_)));
+        assert_eq!(
+            t.source("tachycardia", "hr_bpm", tables),
+            ThresholdSource::Catalog
+        );
+    }
+}
@@ -0,0 +1,311 @@
+//! Physiological plausibility of measured values and sensor-artifact rules.
+//!
+//! Non-finite values are refused outright ([`PlausibilityError`]). Finite
+//! values are checked against the catalog's `plausibility` rules; a value
+//! that fails one is a [`SensorArtifact`]: it is removed, with its series,
+//! from the values the channels and policies see, reported in
+//! `Ast.artifacts`, and the decision carries [`SENSOR_ARTIFACT_HAZARD`] with
+//! a mandatory human acknowledgement.
+
+use crate::{add_hazard, Ast, Decision, Input, SignalCatalog};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// Hazard added when any measured value was discarded as an artifact.
+pub const SENSOR_ARTIFACT_HAZARD: &str = "SENSOR_ARTIFACT";
+
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum PlausibilityError {
+    #[error("measured `{feature}` is NaN")]
+    NaN { feature: String },
+    #[error("measured `{feature}` is infinite")]
+    Infinite { feature: String },
+    #[error("previous `{feature}` is not a finite number")]
+    NonFinitePrevious { feature: String },
+    #[error("`measured_24h` of `{feature}` is not a finite number")]
+    NonFiniteEarlier { feature: String },
+}
+
+/// Which plausibility rule a value failed.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ArtifactRule {
+    /// Outside the feature's `[min, max]`.
+    OutOfRange,
+    /// Fell more than `max_drop` since `Input.previous`.
+    SuddenDrop,
+    /// Needs a pulse, but `hr_bpm` is below its plausible minimum.
+    NoPulse,
+}
+
+/// A measured value left out of the decision, as recorded in `Ast.artifacts`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SensorArtifact {
+    pub feature: String,
+    pub value: f64,
+    pub rule: ArtifactRule,
+    /// The previous sample, for `sudden_drop`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub previous: Option<f64>,
+}
+
+/// Refuse NaN and infinite values in `measured`, `previous` and
+/// `measured_24h`.
+pub fn check_finite(input: &Input) -> Result<(), PlausibilityError> {
+    for (feature, v) in &input.measured {
+        if v.is_nan() {
+            return Err(PlausibilityError::NaN {
+                feature: feature.clone(),
+            });
+        }
+        if v.is_infinite() {
+            return Err(PlausibilityError::Infinite {
+                feature: feature.clone(),
+            });
+        }
+    }
+    if let Some((feature, _)) = input.previous.iter().find(|(_, v)| !v.is_finite()) {
+        return Err(PlausibilityError::NonFinitePrevious {
+            feature: feature.clone(),
+        });
+    }
+    if let Some((feature, _)) = input.measured_24h.iter().find(|(_, v)| !v.is_finite()) {
+        return Err(PlausibilityError::NonFiniteEarlier {
+            feature: feature.clone(),
+        });
+    }
+    Ok(())
+}
+
+/// Artifacts in `input.measured` under the rules of `catalog`, in feature order.
+pub fn sensor_artifacts(input: &Input, catalog: &SignalCatalog) -> Vec<SensorArtifact> {
+    let no_pulse = match (input.measured.get("hr_bpm"), catalog.plausibility("hr_bpm")) {
+        (Some(hr), Some(rule)) => *hr < rule.min,
+        _ => false,
+    };
+    let mut out = Vec::new();
+    for (feature, &value) in &input.measured {
+        let Some(rule) = catalog.plausibility(feature) else {
+            continue;
+        };
+        let previous = input.previous.get(feature).copied();
+        let failed = if !(rule.min..=rule.max).contains(&value) {
+            Some(ArtifactRule::OutOfRange)
+        } else if rule.requires_pulse && no_pulse {
+            Some(ArtifactRule::NoPulse)
+        } else if matches!((rule.max_drop, previous), (Some(d), Some(p)) if p - value > d) {
+            Some(ArtifactRule::SuddenDrop)
+        } else {
+            None
+        };
+        if let Some(rule) = failed {
+            out.push(SensorArtifact {
+                feature: feature.clone(),
+                value,
+                rule,
+                previous: previous.filter(|_| rule == ArtifactRule::SuddenDrop),
+            });
+        }
+    }
+    out
+}
+
+/// `input` without its artifacts, and the artifacts removed.
+pub fn plausible_input(
+    input: &Input,
+    catalog: &SignalCatalog,
+) -> Result<(Input, Vec<SensorArtifact>), PlausibilityError> {
+    check_finite(input)?;
+    let artifacts = sensor_artifacts(input, catalog);
+    let mut clean = input.clone();
+    for a in &artifacts {
+        clean.measured.remove(&a.feature);
+        clean.series.remove(&a.feature);
+    }
+    Ok((clean, artifacts))
+}
+
+/// Flag the artifacts recorded in `ast` on `decision`.
+pub(crate) fn flag_artifacts(ast: &Ast, decision: &mut Decision) {
+    if !ast.artifacts.is_empty() {
+        decision.require_human_ack = true;
+        add_hazard(decision, SENSOR_ARTIFACT_HAZARD);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Engine;
+    use ed25519_dalek::SigningKey;
+    use serde_json::{json, Value};
+
+    fn input(v: Value) -> Input {
+        let mut v = v;
+        v["text"] = json!("");
+        serde_json::from_value(v).unwrap()
+    }
+
+    fn artifacts(v: Value) -> Vec<SensorArtifact> {
+        sensor_artifacts(&input(v), SignalCatalog::builtin())
+    }
+
+    fn artifact(feature: &str, value: f64, rule: ArtifactRule) -> SensorArtifact {
+        SensorArtifact {
+            feature: feature.into(),
+            value,
+            rule,
+            previous: None,
+        }
+    }
+
+    #[test]
+    fn non_finite_values_are_refused() {
+        let feature = || "spo2_pct".to_string();
+        let mut i = input(json!({ "measured": { "spo2_pct": 95 } }));
+        assert_eq!(check_finite(&i), Ok(()));
+        i.measured.insert(feature(), f64::NAN);
+        assert_eq!(
+            check_finite(&i),
+            Err(PlausibilityError::NaN { feature: feature() })
+        );
+        i.measured.insert(feature(), f64::NEG_INFINITY);
+        assert_eq!(
+            check_finite(&i),
+            Err(PlausibilityError::Infinite { feature: feature() })
+        );
+        i.measured.insert(feature(), 95.0);
+        i.previous.insert(feature(), f64::NAN);
+        assert_eq!(
+            check_finite(&i),
+            Err(PlausibilityError::NonFinitePrevious { feature: feature() })
+        );
+        i.previous.clear();
+        i.measured_24h.insert(feature(), f64::INFINITY);
+        assert_eq!(
+            check_finite(&i),
+            Err(PlausibilityError::NonFiniteEarlier { feature: feature() })
+        );
+    }
+
+    #[test]
+    fn each_rule_flags_its_artifact() {
+        assert_eq!(
+            artifacts(json!({ "measured": { "spo2_pct": 30, "hr_bpm": 250 } })),
+            []
+        );
+        assert_eq!(
+            artifacts(json!({ "measured": { "spo2_pct": 101 } })),
+            [artifact("spo2_pct", 101.0, ArtifactRule::OutOfRange)]
+        );
+        // No plausible pulse: the heart rate is out of range and the SpO2,
+        // which needs a pulse, is discarded with it.
+        assert_eq!(
+            artifacts(json!({ "measured": { "spo2_pct": 95, "hr_bpm": 5, "rr_rpm": 20 } })),
+            [
+                artifact("hr_bpm", 5.0, ArtifactRule::OutOfRange),
+                artifact("spo2_pct", 95.0, ArtifactRule::NoPulse),
+            ]
+        );
+        // A drop of exactly `max_drop` is plausible; one more is not.
+        let drop = |to: f64| {
+            artifacts(json!({ "measured": { "spo2_pct": to }, "previous": { "spo2_pct": 95 } }))
+        };
+        assert_eq!(drop(80.0), []);
+        assert_eq!(
+            drop(79.0),
+            [SensorArtifact {
+                previous: Some(95.0),
+                ..artifact("spo2_pct", 79.0, ArtifactRule::SuddenDrop)
+            }]
+        );
+        // Bounds are inclusive.
+        assert_eq!(
+            artifacts(json!({ "measured": { "gcs": 3, "fio2": 1.0 } })),
+            []
+        );
+        assert_eq!(
+            artifacts(json!({ "measured": { "fio2": 21 } })),
+            [artifact("fio2", 21.0, ArtifactRule::OutOfRange)]
+        );
+    }
+
+    #[test]
+    fn artifacts_are_removed_with_their_series() {
+        let i = input(json!({
+            "measured": { "spo2_pct": 120, "hr_bpm": 80 },
+            "series": {
+                "spo2_pct": [{ "at": "2024-01-01T10:00:00Z", "value": 96 }],
+                "hr_bpm": [{ "at": "2024-01-01T10:00:00Z", "value": 80 }],
+            },
+        }));
+        let (clean, removed) = plausible_input(&i, SignalCatalog::builtin()).unwrap();
+        assert_eq!(
+            removed,
+            [artifact("spo2_pct", 120.0, ArtifactRule::OutOfRange)]
+        );
+        assert!(!clean.measured.contains_key("spo2_pct"));
+        assert!(!clean.series.contains_key("spo2_pct"));
+        assert_eq!(clean.measured["hr_bpm"], 80.0);
+        assert!(clean.series.contains_key("hr_bpm"));
+    }
+
+    #[test]
+    fn artifacts_need_a_human_and_do_not_fire_triggers() {
+        let policy = crate::parse_policy_yaml(
+            "\
+protocol_id: p
+version: 1.0.0
+jurisdiction: PT
+source: test
+triggers:
+  - spo2_pct < 90
+severity: CRITICAL
+hazards:
+  CRITICAL: HYPOXEMIA_CRITICAL
+  URGENT: HYPOXEMIA_MODERATE
+actions:
+  - name: call_attending
+    max_delay_s: 60
+",
+        )
+        .unwrap();
+        let decide = |v: Value| {
+            Engine::default()
+                .decide(
+                    &input(v),
+                    &policy,
+                    "blake3:test",
+                    "blake3:test",
+                    &SigningKey::from_bytes(&[0u8; 32]),
+                    "2024-01-01T00:00:00Z",
+                )
+                .unwrap()
+        };
+        // A probe falling off the finger: 97 to 60 in one reading.
+        let out = decide(json!({
+            "measured": { "spo2_pct": 60 },
+            "previous": { "spo2_pct": 97 },
+        }));
+        assert_eq!(out.ast.artifacts.len(), 1);
+        assert!(out.decision.triggers_fired.is_empty());
+        assert!(out.decision.require_human_ack);
+        assert!(out
+            .decision
+            .hazards
+            .iter()
+            .any(|h| h == SENSOR_ARTIFACT_HAZARD));
+
+        let real = decide(json!({
+            "measured": { "spo2_pct": 85 },
+            "previous": { "spo2_pct": 92 },
+        }));
+        assert!(real.ast.artifacts.is_empty());
+        assert_eq!(real.decision.triggers_fired, ["spo2_pct < 90"]);
+        assert!(!real
+            .decision
+            .hazards
+            .iter()
+            .any(|h| h == SENSOR_ARTIFACT_HAZARD));
+    }
+}
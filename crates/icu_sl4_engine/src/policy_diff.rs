@@ -0,0 +1,381 @@
+//! Semantic diff of two policy versions over a corpus of inputs.
+//!
+//! Both versions are run through [`Engine::decide`] on every input and the
+//! resulting severities, actions, deadlines and hazards are compared, so the
+//! report answers "which patients would be handled differently".
+//!
+//! Both versions decide an input at the same clock, so staleness limits,
+//! trend windows and urine-output windows cannot tell them apart: the clock
+//! given to [`diff_policies`], or else the input's newest observation, series
+//! sample or urine collection ([`input_clock`]), or else the candidate's
+//! `effective_from`. A version whose validity window excludes that clock is
+//! reported as a lifecycle refusal, not as an error.
+
+use crate::policy_test::{decide_offline, offline_clock};
+use crate::{Action, Engine, Input, LifecycleError, Policy, Severity};
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct DeadlineChange {
+    pub action: String,
+    pub before: Option<u64>,
+    pub after: Option<u64>,
+}
+
+/// Differences for one corpus input; `line` is 1-based in the NDJSON file.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct InputDiff {
+    pub line: usize,
+    /// Decision time both versions were run at (RFC 3339).
+    pub at: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub severity: Option<(Severity, Severity)>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub actions_added: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub actions_removed: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub deadlines: Vec<DeadlineChange>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub hazards_added: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub hazards_removed: Vec<String>,
+    /// The baseline's validity window excludes `at`; nothing else is compared.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub old_refused: Option<String>,
+    /// The candidate's validity window excludes `at`; nothing else is compared.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub new_refused: Option<String>,
+    /// `decide` failed for one of the versions; nothing else is compared.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl InputDiff {
+    fn actions_changed(&self) -> bool {
+        !self.actions_added.is_empty() || !self.actions_removed.is_empty()
+    }
+
+    fn hazards_changed(&self) -> bool {
+        !self.hazards_added.is_empty() || !self.hazards_removed.is_empty()
+    }
+
+    fn refused(&self) -> bool {
+        self.old_refused.is_some() || self.new_refused.is_some()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.severity.is_none()
+            && !self.actions_changed()
+            && self.deadlines.is_empty()
+            && !self.hazards_changed()
+            && !self.refused()
+            && self.error.is_none()
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct PolicyDiffSummary {
+    pub inputs: usize,
+    pub changed: usize,
+    pub severity_changed: usize,
+    pub actions_changed: usize,
+    pub deadlines_changed: usize,
+    pub hazards_changed: usize,
+    pub lifecycle_refused: usize,
+    pub errors: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PolicyDiffReport {
+    /// `protocol_id@version` of each side.
+    pub old: String,
+    pub new: String,
+    pub summary: PolicyDiffSummary,
+    /// Only inputs whose outcome differs.
+    pub changes: Vec<InputDiff>,
+}
+
+/// Parse an NDJSON corpus (one `Input` per line, blank lines skipped) into
+/// `(line, input)` pairs.
+pub fn parse_input_corpus(s: &str) -> Result<Vec<(usize, Input)>> {
+    let mut out = Vec::new();
+    for (i, l) in s.lines().enumerate() {
+        if l.trim().is_empty() {
+            continue;
+        }
+        let input = serde_json::from_str(l).map_err(|e| anyhow!("corpus line {}: {e}", i + 1))?;
+        out.push((i + 1, input));
+    }
+    Ok(out)
+}
+
+fn added(from: &BTreeSet<&String>, to: &BTreeSet<&String>) -> Vec<String> {
+    to.difference(from).map(|s| s.to_string()).collect()
+}
+
+fn deadlines(actions: &[Action]) -> BTreeMap<&str, Option<u64>> {
+    actions
+        .iter()
+        .map(|a| (a.name.as_str(), a.deadline_s))
+        .collect()
+}
+
+/// Newest timestamp `input` carries: observation times, series samples and
+/// urine collections. Malformed timestamps are skipped; `decide` reports them.
+pub fn input_clock(input: &Input) -> Option<String> {
+    let observed = input.observations.values().filter_map(|o| o.at.as_deref());
+    let sampled = input
+        .series
+        .values()
+        .flatten()
+        .chain(&input.urine_output)
+        .map(|s| s.at.as_str());
+    observed
+        .chain(sampled)
+        .filter_map(|at| OffsetDateTime::parse(at, &Rfc3339).ok())
+        .max()
+        .and_then(|t| t.format(&Rfc3339).ok())
+}
+
+/// The lifecycle refusal behind a failed decision, if that is what it was.
+fn lifecycle_refusal(e: &anyhow::Error) -> Option<String> {
+    e.downcast_ref::<LifecycleError>().and_then(|l| match l {
+        LifecycleError::NotYetEffective { .. } | LifecycleError::Expired { .. } => {
+            Some(l.to_string())
+        }
+        _ => None,
+    })
+}
+
+/// Decide every corpus input under `old` and `new` and report the differences.
+///
+/// `at` (RFC 3339) fixes the decision time of every input; without it each
+/// input is decided at its [`input_clock`], or at the start of `new`'s
+/// validity window when it carries no timestamp.
+pub fn diff_policies(
+    engine: &Engine,
+    old: &Policy,
+    new: &Policy,
+    corpus: &[(usize, Input)],
+    at: Option<&str>,
+) -> PolicyDiffReport {
+    let mut summary = PolicyDiffSummary {
+        inputs: corpus.len(),
+        ..Default::default()
+    };
+    let mut changes = Vec::new();
+    let fallback = offline_clock(new);
+
+    for (line, input) in corpus {
+        let clock = match at {
+            Some(at) => at.to_string(),
+            None => input_clock(input).unwrap_or_else(|| fallback.clone()),
+        };
+        let mut d = InputDiff {
+            line: *line,
+            ..Default::default()
+        };
+        let outcomes = (
+            decide_offline(engine, input, old, &clock),
+            decide_offline(engine, input, new, &clock),
+        );
+        d.at = clock;
+        match outcomes {
+            (Ok(a), Ok(b)) => {
+                if a.ast.severity != b.ast.severity {
+                    d.severity = Some((a.ast.severity, b.ast.severity));
+                }
+                let (da, db) = (
+                    deadlines(&a.decision.actions),
+                    deadlines(&b.decision.actions),
+                );
+                let na: BTreeSet<&String> = a.decision.actions.iter().map(|x| &x.name).collect();
+                let nb: BTreeSet<&String> = b.decision.actions.iter().map(|x| &x.name).collect();
+                d.actions_added = added(&na, &nb);
+                d.actions_removed = added(&nb, &na);
+                for (name, before) in &da {
+                    if let Some(after) = db.get(name) {
+                        if before != after {
+                            d.deadlines.push(DeadlineChange {
+                                action: name.to_string(),
+                                before: *before,
+                                after: *after,
+                            });
+                        }
+                    }
+                }
+                let ha: BTreeSet<&String> = a.decision.hazards.iter().collect();
+                let hb: BTreeSet<&String> = b.decision.hazards.iter().collect();
+                d.hazards_added = added(&ha, &hb);
+                d.hazards_removed = added(&hb, &ha);
+            }
+            (a, b) => {
+                let old_err = a.err();
+                let new_err = b.err();
+                d.old_refused = old_err.as_ref().and_then(lifecycle_refusal);
+                d.new_refused = new_err.as_ref().and_then(lifecycle_refusal);
+                d.error = [old_err, new_err]
+                    .into_iter()
+                    .flatten()
+                    .find(|e| lifecycle_refusal(e).is_none())
+                    .map(|e| e.to_string());
+            }
+        }
+        if d.is_empty() {
+            continue;
+        }
+        summary.changed += 1;
+        summary.severity_changed += d.severity.is_some() as usize;
+        summary.actions_changed += d.actions_changed() as usize;
+        summary.deadlines_changed += !d.deadlines.is_empty() as usize;
+        summary.hazards_changed += d.hazards_changed() as usize;
+        summary.lifecycle_refused += d.refused() as usize;
+        summary.errors += d.error.is_some() as usize;
+        changes.push(d);
+    }
+
+    PolicyDiffReport {
+        old: format!("{}@{}", old.protocol_id, old.version),
+        new: format!("{}@{}", new.protocol_id, new.version),
+        summary,
+        changes,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_policy_yaml;
+    use serde_json::{json, Value};
+
+    fn policy(version: &str, spo2_low: f64, effective_from: &str) -> Policy {
+        parse_policy_yaml(&format!(
+            "\
+protocol_id: hypox
+version: {version}
+jurisdiction: PT
+source: test
+params:
+  spo2_low: {spo2_low}
+max_age_s:
+  spo2_pct: 900
+triggers:
+  - spo2_pct < $spo2_low
+severity: CRITICAL
+hazards:
+  CRITICAL: HYPOXEMIA_CRITICAL
+  URGENT: HYPOXEMIA_MODERATE
+effective_from: {effective_from}
+expires_at: 2030-01-01
+actions:
+  - name: call_attending
+    max_delay_s: 30
+"
+        ))
+        .unwrap()
+    }
+
+    fn corpus(inputs: &[Value]) -> Vec<(usize, Input)> {
+        inputs
+            .iter()
+            .enumerate()
+            .map(|(i, v)| {
+                let mut v = v.clone();
+                v["text"] = json!("");
+                (i + 1, serde_json::from_value(v).unwrap())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn input_clock_is_the_newest_timestamp() {
+        let c = corpus(&[
+            json!({
+                "measured": { "spo2_pct": 91 },
+                "observations": { "spo2_pct": { "at": "2026-03-01T10:00:00Z" } },
+                "series": { "hr_bpm": [{ "at": "2026-03-01T10:20:00Z", "value": 110 }] },
+                "urine_output": [{ "at": "2026-03-01T10:10:00Z", "value": 40 }],
+            }),
+            json!({ "measured": { "spo2_pct": 91 } }),
+        ]);
+        assert_eq!(
+            input_clock(&c[0].1).as_deref(),
+            Some("2026-03-01T10:20:00Z")
+        );
+        assert_eq!(input_clock(&c[1].1), None);
+    }
+
+    #[test]
+    fn both_versions_decide_at_the_input_clock() {
+        // Windows start on different dates; timestamped inputs long after
+        // either must not be judged against those dates.
+        let old = policy("1.0.0", 90.0, "2024-01-01");
+        let new = policy("1.1.0", 90.0, "2025-01-01");
+        let c = corpus(&[
+            json!({
+                "measured": { "spo2_pct": 85 },
+                "observations": { "spo2_pct": { "at": "2026-03-01T10:00:00Z" } },
+            }),
+            json!({
+                "measured": { "spo2_pct": 85, "hr_bpm": 120 },
+                "observations": {
+                    "spo2_pct": { "at": "2026-03-01T09:00:00Z" },
+                    "hr_bpm": { "at": "2026-03-01T10:00:00Z" },
+                },
+            }),
+        ]);
+        let r = diff_policies(&Engine::default(), &old, &new, &c, None);
+        assert_eq!(r.summary.inputs, 2);
+        assert!(r.changes.is_empty(), "{:?}", r.changes);
+    }
+
+    #[test]
+    fn threshold_change_is_reported_at_the_given_clock() {
+        let old = policy("1.0.0", 90.0, "2024-01-01");
+        let new = policy("1.1.0", 92.0, "2024-01-01");
+        let c = corpus(&[json!({ "measured": { "spo2_pct": 91 } })]);
+        let r = diff_policies(
+            &Engine::default(),
+            &old,
+            &new,
+            &c,
+            Some("2026-03-01T10:00:00Z"),
+        );
+        assert_eq!(r.changes.len(), 1);
+        let d = &r.changes[0];
+        assert_eq!(d.at, "2026-03-01T10:00:00Z");
+        assert_eq!(d.actions_added, ["call_attending"]);
+        assert_eq!((r.summary.actions_changed, r.summary.errors), (1, 0));
+    }
+
+    #[test]
+    fn lifecycle_refusals_are_their_own_category() {
+        let old = policy("1.0.0", 90.0, "2024-01-01");
+        let new = policy("2.0.0", 90.0, "2027-01-01");
+        let c = corpus(&[json!({
+            "measured": { "spo2_pct": 85 },
+            "observations": { "spo2_pct": { "at": "2026-03-01T10:00:00Z" } },
+        })]);
+        let r = diff_policies(&Engine::default(), &old, &new, &c, None);
+        let d = &r.changes[0];
+        assert!(d.old_refused.is_none());
+        assert!(d.new_refused.as_ref().unwrap().contains("not in force"));
+        assert!(d.error.is_none());
+        assert_eq!((r.summary.lifecycle_refused, r.summary.errors), (1, 0));
+        // Without timestamps the candidate's window start is the clock, so
+        // the candidate applies and an expired baseline is refused.
+        let c = corpus(&[json!({ "measured": { "spo2_pct": 85 } })]);
+        let mut expired = policy("1.0.0", 90.0, "2024-01-01");
+        expired.expires_at = Some("2026-01-01".into());
+        let r = diff_policies(&Engine::default(), &expired, &new, &c, None);
+        let d = &r.changes[0];
+        assert_eq!(d.at, "2027-01-01T00:00:00Z");
+        assert!(d.old_refused.as_ref().unwrap().contains("expired"));
+        assert!(d.new_refused.is_none());
+    }
+}
@@ -0,0 +1,491 @@
+//! Several protocols evaluated together against one input.
+//!
+//! Policies are ordered by descending `priority`, then by `protocol_id`.
+//! Every policy whose triggers match contributes its admitted actions; an
+//! action emitted by several protocols is kept once with the tightest
+//! `max_delay_s`, and when two admitted actions conflict the one from the
+//! higher-priority protocol wins.
+
+use crate::{
+    admitted_actions, blake3_hash_json, effective_sig_path, hazard_severity, load_policy_signature,
+    parse_policy_yaml, policy_hash, policy_sig_path, policy_triggers_fired, resolve_policy, Action,
+    Ast, Decision, Input, Policy, PolicyOverlay, PolicySignature, Severity, SignalCatalog,
+};
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone)]
+pub struct PolicyEntry {
+    pub policy: Policy,
+    /// File the policy was loaded from, if any.
+    pub path: Option<PathBuf>,
+    /// Detached signature, checked when the engine has a trust store.
+    pub signature: Option<PolicySignature>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct PolicySet {
+    /// Entries in evaluation order (priority desc, then protocol_id).
+    pub entries: Vec<PolicyEntry>,
+}
+
+/// An admitted action dropped because it conflicts with a higher-priority one.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ActionConflict {
+    pub kept: String,
+    pub kept_protocol: String,
+    pub suppressed: String,
+    pub suppressed_protocol: String,
+}
+
+impl PolicySet {
+    pub fn from_entries(mut entries: Vec<PolicyEntry>) -> Result<Self> {
+        entries.sort_by(|a, b| {
+            b.policy
+                .priority
+                .cmp(&a.policy.priority)
+                .then_with(|| a.policy.protocol_id.cmp(&b.policy.protocol_id))
+        });
+        let mut seen = BTreeSet::new();
+        for e in &entries {
+            if !seen.insert(e.policy.protocol_id.as_str()) {
+                return Err(anyhow!(
+                    "duplicate protocol_id `{}` in policy set",
+                    e.policy.protocol_id
+                ));
+            }
+        }
+        Ok(PolicySet { entries })
+    }
+
+    pub fn from_policies(policies: Vec<Policy>) -> Result<Self> {
+        Self::from_entries(
+            policies
+                .into_iter()
+                .map(|policy| PolicyEntry {
+                    policy,
+                    path: None,
+                    signature: None,
+                })
+                .collect(),
+        )
+    }
+
+    /// Load every `*.yaml`/`*.yml` file of `dir` (non-recursive, by file name),
+    /// with its `<file>.sig` detached signature when present.
+    pub fn load_dir(dir: &Path) -> Result<Self> {
+        let mut paths: Vec<PathBuf> = fs::read_dir(dir)?
+            .filter_map(|e| e.ok().map(|e| e.path()))
+            .filter(|p| {
+                p.is_file()
+                    && matches!(
+                        p.extension().and_then(|x| x.to_str()),
+                        Some("yaml") | Some("yml")
+                    )
+            })
+            .collect();
+        paths.sort();
+        if paths.is_empty() {
+            return Err(anyhow!("no policy files in {}", dir.display()));
+        }
+        let mut entries = Vec::with_capacity(paths.len());
+        for path in paths {
+            let s = fs::read_to_string(&path)?;
+            let policy = parse_policy_yaml(&s).map_err(|e| anyhow!("{}: {e}", path.display()))?;
+            let sig_path = policy_sig_path(&path);
+            let signature = if sig_path.is_file() {
+                Some(load_policy_signature(&sig_path)?)
+            } else {
+                None
+            };
+            entries.push(PolicyEntry {
+                policy,
+                path: Some(path),
+                signature,
+            });
+        }
+        Self::from_entries(entries)
+    }
+
+    /// Resolve each member against the overlays that extend it. Every overlay
+    /// must extend some member. A member loaded from a file takes the
+    /// signature of its effective policy from [`effective_sig_path`], or none
+    /// when that file is missing; other members keep theirs, which must cover
+    /// the effective policy.
+    pub fn with_overlays(self, overlays: &[PolicyOverlay]) -> Result<Self> {
+        if let Some(o) = overlays.iter().find(|o| {
+            !self
+                .entries
+                .iter()
+                .any(|e| e.policy.protocol_id == o.extends)
+        }) {
+            return Err(anyhow!(
+                "overlay `{}` extends `{}`, which is not in the policy set",
+                o.overlay_id,
+                o.extends
+            ));
+        }
+        let mut entries = Vec::with_capacity(self.entries.len());
+        for mut e in self.entries {
+            let mine: Vec<PolicyOverlay> = overlays
+                .iter()
+                .filter(|o| o.extends == e.policy.protocol_id)
+                .cloned()
+                .collect();
+            if !mine.is_empty() {
+                let effective = resolve_policy(&e.policy, &mine)?;
+                if let Some(path) = &e.path {
+                    let sig_path = effective_sig_path(path, &e.policy, &effective)?;
+                    e.signature = if sig_path.is_file() {
+                        Some(load_policy_signature(&sig_path)?)
+                    } else {
+                        None
+                    };
+                }
+                e.policy = effective;
+            }
+            entries.push(e);
+        }
+        Self::from_entries(entries)
+    }
+
+    pub fn policies(&self) -> impl Iterator<Item = &Policy> {
+        self.entries.iter().map(|e| &e.policy)
+    }
+
+    /// Hash committing to every member policy and to the evaluation order.
+    pub fn hash(&self) -> Result<String> {
+        let mut members = Vec::with_capacity(self.entries.len());
+        for p in self.policies() {
+            members.push(json!({
+                "protocol_id": p.protocol_id,
+                "version": p.version,
+                "policy_hash": policy_hash(p)?,
+            }));
+        }
+        blake3_hash_json(&json!({ "policy_set": members }))
+    }
+}
+
+/// Evaluate every policy of the set and merge their outputs.
+///
+/// Returns the merged decision and the protocols that matched, in evaluation
+/// order. `triggers_fired` entries are prefixed with their `protocol_id`.
+pub fn apply_policy_set(
+    input: &Input,
+    ast: &Ast,
+    set: &PolicySet,
+    catalog: &SignalCatalog,
+) -> Result<(Decision, Vec<String>)> {
+    let mut matched = Vec::new();
+    let mut triggers_fired = Vec::new();
+    let mut hazards = BTreeSet::new();
+    // (action, declaring protocol, conflicts_with) in priority order.
+    let mut merged: Vec<(Action, String, Vec<String>)> = Vec::new();
+
+    for p in set.policies() {
+        let fired = policy_triggers_fired(input, ast, p)?;
+        if fired.is_empty() {
+            continue;
+        }
+        matched.push(p.protocol_id.clone());
+        triggers_fired.extend(fired.iter().map(|t| format!("{}: {t}", p.protocol_id)));
+        if let Some(h) = p.hazards.get(&hazard_severity(ast, p, &fired, catalog)?) {
+            hazards.insert(h.clone());
+        }
+
+        for a in admitted_actions(input, ast, p)? {
+            let conflicts = p
+                .actions
+                .iter()
+                .find(|d| d.name == a.name)
+                .map(|d| d.conflicts_with.clone())
+                .unwrap_or_default();
+            match merged.iter_mut().find(|(m, _, _)| m.name == a.name) {
+                Some((m, _, m_conflicts)) => {
+                    if a.max_delay_s < m.max_delay_s {
+                        m.max_delay_s = a.max_delay_s;
+                        m.deadline_s = Some(a.max_delay_s);
+                    }
+                    for c in conflicts {
+                        if !m_conflicts.contains(&c) {
+                            m_conflicts.push(c);
+                        }
+                    }
+                }
+                None => merged.push((a, p.protocol_id.clone(), conflicts)),
+            }
+        }
+    }
+
+    let mut kept: Vec<(Action, String, Vec<String>)> = Vec::new();
+    let mut conflicts = Vec::new();
+    for (a, proto, a_conflicts) in merged {
+        let clash = kept.iter().find(|(k, _, k_conflicts)| {
+            a_conflicts.contains(&k.name) || k_conflicts.contains(&a.name)
+        });
+        match clash {
+            Some((k, k_proto, _)) => conflicts.push(ActionConflict {
+                kept: k.name.clone(),
+                kept_protocol: k_proto.clone(),
+                suppressed: a.name.clone(),
+                suppressed_protocol: proto,
+            }),
+            None => kept.push((a, proto, a_conflicts)),
+        }
+    }
+
+    let mut actions: Vec<Action> = kept.into_iter().map(|(a, _, _)| a).collect();
+    actions.sort_by(|a, b| a.name.cmp(&b.name));
+
+    let decision = Decision {
+        sensitivity_bias: "ZFN".to_string(),
+        require_human_ack: matches!(ast.severity, Severity::CRITICAL | Severity::URGENT),
+        actions,
+        hazards: hazards.into_iter().collect(),
+        watchdog_armed: false,
+        triggers_fired,
+        conflicts,
+    };
+    Ok((decision, matched))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ed25519_dalek::SigningKey;
+    use serde_json::json;
+
+    fn policy(id: &str, priority: u32, actions: &str) -> Policy {
+        parse_policy_yaml(&format!(
+            "\
+protocol_id: {id}
+version: 1.0.0
+jurisdiction: PT
+source: test
+priority: {priority}
+triggers:
+  - spo2_pct < 90
+severity: CRITICAL
+hazards:
+  CRITICAL: HYPOXEMIA_CRITICAL
+  URGENT: HYPOXEMIA_MODERATE
+actions:
+{actions}"
+        ))
+        .unwrap()
+    }
+
+    fn decide(set: &PolicySet) -> Decision {
+        decide_on(set, json!({ "spo2_pct": 85 }))
+    }
+
+    fn decide_on(set: &PolicySet, measured: serde_json::Value) -> Decision {
+        let input: Input =
+            serde_json::from_value(json!({ "text": "", "measured": measured })).unwrap();
+        crate::Engine::default()
+            .decide_set(
+                &input,
+                set,
+                "blake3:test",
+                "blake3:test",
+                &SigningKey::from_bytes(&[0u8; 32]),
+                "2024-01-01T00:00:00Z",
+            )
+            .unwrap()
+            .decision
+    }
+
+    const CALL: &str = "  - name: call_attending\n    max_delay_s: 60\n";
+
+    #[test]
+    fn duplicate_protocol_ids_are_rejected() {
+        let err =
+            PolicySet::from_policies(vec![policy("a", 1, CALL), policy("a", 2, CALL)]).unwrap_err();
+        assert!(err.to_string().contains("duplicate protocol_id `a`"));
+    }
+
+    #[test]
+    fn order_is_priority_then_protocol_id() {
+        let set = PolicySet::from_policies(vec![
+            policy("b", 1, CALL),
+            policy("c", 5, CALL),
+            policy("a", 5, CALL),
+        ])
+        .unwrap();
+        let ids: Vec<&str> = set.policies().map(|p| p.protocol_id.as_str()).collect();
+        assert_eq!(ids, ["a", "c", "b"]);
+        assert_eq!(
+            decide(&set).triggers_fired,
+            ["a: spo2_pct < 90", "c: spo2_pct < 90", "b: spo2_pct < 90"]
+        );
+    }
+
+    #[test]
+    fn duplicate_actions_keep_the_tightest_deadline() {
+        let fast = "  - name: call_attending\n    max_delay_s: 30\n";
+        let set =
+            PolicySet::from_policies(vec![policy("a", 5, CALL), policy("b", 1, fast)]).unwrap();
+        let d = decide(&set);
+        assert_eq!(d.actions.len(), 1);
+        assert_eq!(d.actions[0].name, "call_attending");
+        assert_eq!(
+            (d.actions[0].max_delay_s, d.actions[0].deadline_s),
+            (30, Some(30))
+        );
+        assert!(d.conflicts.is_empty());
+    }
+
+    #[test]
+    fn conflicts_resolve_in_priority_order() {
+        let intubate = "  - name: intubate\n    max_delay_s: 60\n    conflicts_with: [start_niv]\n";
+        let niv = "  - name: start_niv\n    max_delay_s: 60\n";
+        let expected = |kept: &str, kept_p: &str, suppressed: &str, suppressed_p: &str| {
+            vec![ActionConflict {
+                kept: kept.into(),
+                kept_protocol: kept_p.into(),
+                suppressed: suppressed.into(),
+                suppressed_protocol: suppressed_p.into(),
+            }]
+        };
+
+        // The higher-priority protocol wins whichever side declares the
+        // conflict.
+        let set =
+            PolicySet::from_policies(vec![policy("airway", 9, intubate), policy("resp", 1, niv)])
+                .unwrap();
+        let d = decide(&set);
+        assert_eq!(
+            d.actions
+                .iter()
+                .map(|a| a.name.as_str())
+                .collect::<Vec<_>>(),
+            ["intubate"]
+        );
+        assert_eq!(
+            d.conflicts,
+            expected("intubate", "airway", "start_niv", "resp")
+        );
+
+        let set =
+            PolicySet::from_policies(vec![policy("airway", 1, intubate), policy("resp", 9, niv)])
+                .unwrap();
+        let d = decide(&set);
+        assert_eq!(
+            d.actions
+                .iter()
+                .map(|a| a.name.as_str())
+                .collect::<Vec<_>>(),
+            ["start_niv"]
+        );
+        assert_eq!(
+            d.conflicts,
+            expected("start_niv", "resp", "intubate", "airway")
+        );
+    }
+
+    #[test]
+    fn hazard_follows_the_signals_the_policy_reads() {
+        let set = PolicySet::from_policies(vec![policy("hypox", 1, CALL)]).unwrap();
+        assert_eq!(
+            decide_on(&set, json!({ "spo2_pct": 88 })).hazards,
+            ["HYPOXEMIA_MODERATE"]
+        );
+        assert_eq!(
+            decide_on(&set, json!({ "spo2_pct": 80 })).hazards,
+            ["HYPOXEMIA_CRITICAL"]
+        );
+
+        // Severe hypoxemia makes the AST CRITICAL, but a policy that only
+        // looks at the heart rate reports the tachycardia's hazard.
+        let tachy = parse_policy_yaml(&format!(
+            "\
+protocol_id: tachy
+version: 1.0.0
+jurisdiction: PT
+source: test
+triggers:
+  - tachycardia
+severity: URGENT
+hazards:
+  CRITICAL: TACHY_CRITICAL
+  URGENT: TACHY_MODERATE
+actions:
+{CALL}"
+        ))
+        .unwrap();
+        let set = PolicySet::from_policies(vec![tachy]).unwrap();
+        let d = decide_on(&set, json!({ "spo2_pct": 80, "hr_bpm": 130 }));
+        assert_eq!(d.hazards, ["TACHY_MODERATE"]);
+    }
+
+    #[test]
+    fn overlaid_directory_reads_the_effective_signature() {
+        use crate::{
+            parse_overlay_yaml, sign_policy, verifying_key_to_hex, TrustStore, TrustedKey,
+        };
+
+        let dir = std::env::temp_dir().join(format!("icu_sl4_policy_set_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("hypox.yaml");
+        let base = policy("hypox", 1, CALL);
+        fs::write(&path, serde_yaml::to_string(&base).unwrap()).unwrap();
+        let overlay = parse_overlay_yaml(
+            "overlay_id: HSJ\nextends: hypox\nlevel: hospital\nscope: HSJ\nversion: 1.0.0\n\
+             actions:\n  - { name: call_attending, max_delay_s: 20 }\n",
+        )
+        .unwrap();
+
+        let sk = SigningKey::from_bytes(&[3u8; 32]);
+        let trust = TrustStore {
+            keys: vec![TrustedKey {
+                key_id: "cg".into(),
+                owner: "governance".into(),
+                pubkey: verifying_key_to_hex(&sk.verifying_key()),
+            }],
+        };
+        let base_sig = sign_policy(&base, "cg", &sk).unwrap();
+        fs::write(
+            policy_sig_path(&path),
+            serde_json::to_string(&base_sig).unwrap(),
+        )
+        .unwrap();
+
+        // The base signature does not cover the effective policy.
+        let set = PolicySet::load_dir(&dir)
+            .unwrap()
+            .with_overlays(std::slice::from_ref(&overlay))
+            .unwrap();
+        assert!(set.entries[0].signature.is_none());
+
+        let effective = resolve_policy(&base, std::slice::from_ref(&overlay)).unwrap();
+        let sig_path = effective_sig_path(&path, &base, &effective).unwrap();
+        assert_ne!(sig_path, policy_sig_path(&path));
+        let sig = sign_policy(&effective, "cg", &sk).unwrap();
+        fs::write(&sig_path, serde_json::to_string(&sig).unwrap()).unwrap();
+
+        let plain = PolicySet::load_dir(&dir).unwrap();
+        assert_eq!(plain.entries[0].signature, Some(base_sig));
+        let set = plain.with_overlays(&[overlay]).unwrap();
+        assert_eq!(set.entries[0].signature, Some(sig));
+        let input: Input =
+            serde_json::from_value(json!({ "text": "", "measured": { "spo2_pct": 85 } })).unwrap();
+        let out = crate::Engine::default()
+            .with_trust_store(trust)
+            .decide_set(
+                &input,
+                &set,
+                "blake3:test",
+                "blake3:test",
+                &sk,
+                "2024-01-01T00:00:00Z",
+            )
+            .unwrap();
+        assert_eq!(out.decision.actions[0].max_delay_s, 20);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,197 @@
+//! Test vectors embedded in a policy and the runner behind `policy test`.
+//!
+//! Each vector is an `Input` plus the outcome the author expects. Vectors are
+//! serialized with the policy, so they are covered by its `policy_hash`: a
+//! policy shipped with tests hashes differently from the same rules without.
+
+use crate::{parse_policy_time, BerlinCategory, DecideOutput, Engine, Input, Policy, Severity};
+use anyhow::Result;
+use ed25519_dalek::SigningKey;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use time::format_description::well_known::Rfc3339;
+
+/// Fixed clock for test runs of policies without `effective_from`; vectors
+/// must not depend on wall time.
+pub const POLICY_TEST_CLOCK: &str = "1970-01-01T00:00:00Z";
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PolicyTest {
+    pub name: String,
+    /// Decision time (RFC 3339); defaults to [`offline_clock`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub at: Option<String>,
+    pub input: Input,
+    pub expect: PolicyExpectation,
+}
+
+/// Expected outcome; fields left out are not checked.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct PolicyExpectation {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub severity: Option<Severity>,
+    /// Names of the decision's actions (order-insensitive, exact set).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub actions: Option<Vec<String>>,
+    /// Decision hazards (order-insensitive, exact set).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hazards: Option<Vec<String>>,
+    /// Threshold profile selected for the patient.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub profile: Option<String>,
+    /// NEWS2 aggregate score.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub news2: Option<u8>,
+    /// SOFA total.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sofa: Option<u8>,
+    /// Berlin oxygenation category.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub berlin: Option<BerlinCategory>,
+    /// KDIGO stage, 0 when no stage was reached.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub aki_stage: Option<u8>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PolicyTestResult {
+    pub name: String,
+    pub passed: bool,
+    /// One line per field that differs from the expectation.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub mismatches: Vec<String>,
+    /// Set when `decide` itself failed (e.g. channel divergence).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+fn set_mismatch(field: &str, expected: &[String], got: &[String]) -> Option<String> {
+    let e: BTreeSet<&String> = expected.iter().collect();
+    let g: BTreeSet<&String> = got.iter().collect();
+    (e != g).then(|| format!("{field}: expected {e:?}, got {g:?}"))
+}
+
+/// Decision time for offline runs: the start of the policy's validity window,
+/// so lifecycle checks pass, or [`POLICY_TEST_CLOCK`].
+pub fn offline_clock(policy: &Policy) -> String {
+    match policy
+        .effective_from
+        .as_deref()
+        .and_then(|s| parse_policy_time("effective_from", s).ok())
+    {
+        Some(t) => t
+            .format(&Rfc3339)
+            .unwrap_or_else(|_| POLICY_TEST_CLOCK.into()),
+        None => POLICY_TEST_CLOCK.to_string(),
+    }
+}
+
+/// [`Engine::decide`] for offline tooling (tests, diffs): a throwaway key,
+/// since these outputs are compared, never persisted.
+pub(crate) fn decide_offline(
+    engine: &Engine,
+    input: &Input,
+    policy: &Policy,
+    now_rfc3339: &str,
+) -> Result<DecideOutput> {
+    let sk = SigningKey::from_bytes(&[0u8; 32]);
+    engine.decide(
+        input,
+        policy,
+        "blake3:offline",
+        "blake3:offline",
+        &sk,
+        now_rfc3339,
+    )
+}
+
+/// Run every test vector of `policy` through [`Engine::decide`].
+pub fn run_policy_tests(engine: &Engine, policy: &Policy) -> Vec<PolicyTestResult> {
+    let mut results = Vec::with_capacity(policy.tests.len());
+    let default_clock = offline_clock(policy);
+    for t in &policy.tests {
+        let now = t.at.as_deref().unwrap_or(&default_clock);
+        let out = match decide_offline(engine, &t.input, policy, now) {
+            Ok(out) => out,
+            Err(e) => {
+                results.push(PolicyTestResult {
+                    name: t.name.clone(),
+                    passed: false,
+                    mismatches: vec![],
+                    error: Some(e.to_string()),
+                });
+                continue;
+            }
+        };
+
+        let mut mismatches = Vec::new();
+        if let Some(sev) = &t.expect.severity {
+            if *sev != out.ast.severity {
+                mismatches.push(format!(
+                    "severity: expected {sev:?}, got {:?}",
+                    out.ast.severity
+                ));
+            }
+        }
+        if let Some(expected) = &t.expect.actions {
+            let got: Vec<String> = out
+                .decision
+                .actions
+                .iter()
+                .map(|a| a.name.clone())
+                .collect();
+            mismatches.extend(set_mismatch("actions", expected, &got));
+        }
+        if let Some(expected) = &t.expect.hazards {
+            mismatches.extend(set_mismatch("hazards", expected, &out.decision.hazards));
+        }
+        if let Some(expected) = &t.expect.profile {
+            if out.ast.profile.as_ref() != Some(expected) {
+                mismatches.push(format!(
+                    "profile: expected {expected}, got {}",
+                    out.ast.profile.as_deref().unwrap_or("none")
+                ));
+            }
+        }
+        if let Some(expected) = t.expect.news2 {
+            let got = out.news2.as_ref().map(|n| n.score);
+            if got != Some(expected) {
+                mismatches.push(format!(
+                    "news2: expected {expected}, got {}",
+                    got.map_or("none".to_string(), |s| s.to_string())
+                ));
+            }
+        }
+        if let Some(expected) = t.expect.sofa {
+            let got = out.ast.sofa.as_ref().map(|s| s.total);
+            if got != Some(expected) {
+                mismatches.push(format!(
+                    "sofa: expected {expected}, got {}",
+                    got.map_or("none".to_string(), |s| s.to_string())
+                ));
+            }
+        }
+        if let Some(expected) = t.expect.berlin {
+            let got = out.ast.berlin.as_ref().map(|b| b.category);
+            if got != Some(expected) {
+                mismatches.push(format!("berlin: expected {expected:?}, got {got:?}"));
+            }
+        }
+        if let Some(expected) = t.expect.aki_stage {
+            let got = out.ast.aki.as_ref().map(|a| a.stage);
+            if got != Some(expected) {
+                mismatches.push(format!(
+                    "aki_stage: expected {expected}, got {}",
+                    got.map_or("none".to_string(), |s| s.to_string())
+                ));
+            }
+        }
+        results.push(PolicyTestResult {
+            name: t.name.clone(),
+            passed: mismatches.is_empty(),
+            mismatches,
+            error: None,
+        });
+    }
+    results
+}
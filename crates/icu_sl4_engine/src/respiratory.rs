@@ -0,0 +1,117 @@
+//! Oxygenation category of the Berlin definition of ARDS (ARDS Definition
+//! Task Force, 2012).
+//!
+//! With PEEP or CPAP of at least [`BERLIN_MIN_PEEP_CMH2O`], a P/F ratio
+//! (`pf_ratio`, see [`crate::derived`]) above 200 and up to 300 mmHg is mild,
+//! above 100 and up to 200 moderate, and up to 100 severe. Without a PaO2
+//! the S/F ratio stands in, through the equivalents of Rice et al. (2007):
+//! 315, 235 and 148.
+//!
+//! Only the oxygenation criterion is assessed: timing, bilateral opacities
+//! and the exclusion of cardiac failure stay with the clinician, so the
+//! category is recorded in `Ast.berlin` as a grading of the respiratory
+//! failure, not an ARDS diagnosis. A measured PEEP below the minimum leaves
+//! the patient unclassified; an unmeasured one is recorded as such.
+
+use crate::Input;
+use serde::{Deserialize, Serialize};
+
+/// Lowest PEEP (cmH2O) the Berlin categories are defined at.
+pub const BERLIN_MIN_PEEP_CMH2O: f64 = 5.0;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum BerlinCategory {
+    Mild,
+    Moderate,
+    Severe,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct BerlinOxygenation {
+    pub category: BerlinCategory,
+    /// `pf_ratio`, or `sf_ratio` when no PaO2 was measured.
+    pub ratio_feature: String,
+    pub ratio: f64,
+    /// `None` when the PEEP criterion could not be checked.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub peep_cmh2o: Option<f64>,
+}
+
+/// Upper bounds of the severe, moderate and mild categories, per ratio.
+const BOUNDS: &[(&str, [f64; 3])] = &[
+    ("pf_ratio", [100.0, 200.0, 300.0]),
+    ("sf_ratio", [148.0, 235.0, 315.0]),
+];
+
+impl BerlinOxygenation {
+    /// Category of the effective `input`; `None` without a ratio, above the
+    /// mild bound or below the minimum PEEP.
+    pub fn from_input(input: &Input) -> Option<BerlinOxygenation> {
+        let m = &input.measured;
+        let peep_cmh2o = m.get("peep_cmh2o").copied();
+        if peep_cmh2o.is_some_and(|p| p < BERLIN_MIN_PEEP_CMH2O) {
+            return None;
+        }
+        let (feature, [severe, moderate, mild], ratio) = BOUNDS
+            .iter()
+            .find_map(|(f, b)| m.get(*f).map(|r| (*f, *b, *r)))?;
+        let category = match ratio {
+            r if r <= severe => BerlinCategory::Severe,
+            r if r <= moderate => BerlinCategory::Moderate,
+            r if r <= mild => BerlinCategory::Mild,
+            _ => return None,
+        };
+        Some(BerlinOxygenation {
+            category,
+            ratio_feature: feature.to_string(),
+            ratio,
+            peep_cmh2o,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{json, Value};
+
+    fn category(measured: Value) -> Option<BerlinCategory> {
+        let input: Input =
+            serde_json::from_value(json!({ "text": "", "measured": measured })).unwrap();
+        BerlinOxygenation::from_input(&input).map(|b| b.category)
+    }
+
+    #[test]
+    fn pf_ratio_bounds_are_inclusive() {
+        let pf = |r: f64| category(json!({ "pf_ratio": r, "peep_cmh2o": 5 }));
+        assert_eq!(pf(100.0), Some(BerlinCategory::Severe));
+        assert_eq!(pf(100.1), Some(BerlinCategory::Moderate));
+        assert_eq!(pf(200.0), Some(BerlinCategory::Moderate));
+        assert_eq!(pf(200.1), Some(BerlinCategory::Mild));
+        assert_eq!(pf(300.0), Some(BerlinCategory::Mild));
+        assert_eq!(pf(300.1), None);
+    }
+
+    #[test]
+    fn sf_ratio_stands_in_without_a_pao2() {
+        let sf = |r: f64| category(json!({ "sf_ratio": r }));
+        assert_eq!(sf(148.0), Some(BerlinCategory::Severe));
+        assert_eq!(sf(235.0), Some(BerlinCategory::Moderate));
+        assert_eq!(sf(315.0), Some(BerlinCategory::Mild));
+        assert_eq!(sf(316.0), None);
+        assert_eq!(
+            category(json!({ "pf_ratio": 250, "sf_ratio": 140 })),
+            Some(BerlinCategory::Mild)
+        );
+    }
+
+    #[test]
+    fn peep_below_the_minimum_leaves_the_patient_unclassified() {
+        assert_eq!(category(json!({ "pf_ratio": 90, "peep_cmh2o": 4.9 })), None);
+        assert_eq!(
+            category(json!({ "pf_ratio": 90 })),
+            Some(BerlinCategory::Severe)
+        );
+    }
+}
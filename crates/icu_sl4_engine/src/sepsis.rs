@@ -0,0 +1,176 @@
+//! Bedside sepsis screening: qSOFA (Sepsis-3, 2016) and SIRS (ACCP/SCCM,
+//! 1992).
+//!
+//! Both count criteria met on the effective `Input.measured`:
+//!
+//! | score | criterion          | met when                                 |
+//! |-------|--------------------|------------------------------------------|
+//! | qSOFA | `respiratory_rate` | `rr_rpm` ≥ 22                            |
+//! | qSOFA | `systolic_bp`      | `sbp_mmhg` ≤ 100                         |
+//! | qSOFA | `mentation`        | `gcs` < 15 or `acvpu` ≥ 1                |
+//! | SIRS  | `temperature`      | `temp_c` > 38 or < 36                    |
+//! | SIRS  | `heart_rate`       | `hr_bpm` > 90                            |
+//! | SIRS  | `respiration`      | `rr_rpm` > 20 or `paco2_mmhg` < 32       |
+//! | SIRS  | `white_cells`      | `wbc_k_ul` > 12 or < 4                   |
+//!
+//! A criterion whose features are all missing is listed in `missing` and
+//! counts as not met, so a count is a lower bound. Policies see the counts
+//! as `qsofa` and `sirs`; neither is a diagnosis of sepsis on its own, which
+//! also needs a suspected infection.
+
+use crate::Input;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Trigger identifier of the qSOFA count.
+pub const QSOFA_SCORE: &str = "qsofa";
+/// Trigger identifier of the SIRS count.
+pub const SIRS_SCORE: &str = "sirs";
+
+/// Criteria of one screening score.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CriteriaCount {
+    pub score: u8,
+    /// Criteria met, in table order.
+    pub met: Vec<String>,
+    /// Criteria without any of their features measured.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub missing: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SepsisScreen {
+    pub qsofa: CriteriaCount,
+    pub sirs: CriteriaCount,
+}
+
+/// A criterion: its name and, per feature, whether the value meets it.
+type Criterion = (&'static str, &'static [(&'static str, fn(f64) -> bool)]);
+
+const QSOFA: &[Criterion] = &[
+    ("respiratory_rate", &[("rr_rpm", |v| v >= 22.0)]),
+    ("systolic_bp", &[("sbp_mmhg", |v| v <= 100.0)]),
+    (
+        "mentation",
+        &[("gcs", |v| v < 15.0), ("acvpu", |v| v >= 1.0)],
+    ),
+];
+
+const SIRS: &[Criterion] = &[
+    (
+        "temperature",
+        &[("temp_c", |v| !(36.0..=38.0).contains(&v))],
+    ),
+    ("heart_rate", &[("hr_bpm", |v| v > 90.0)]),
+    (
+        "respiration",
+        &[("rr_rpm", |v| v > 20.0), ("paco2_mmhg", |v| v < 32.0)],
+    ),
+    (
+        "white_cells",
+        &[("wbc_k_ul", |v| !(4.0..=12.0).contains(&v))],
+    ),
+];
+
+fn count(m: &BTreeMap<String, f64>, criteria: &[Criterion]) -> CriteriaCount {
+    let mut met = Vec::new();
+    let mut missing = Vec::new();
+    for (name, tests) in criteria {
+        let values: Vec<bool> = tests
+            .iter()
+            .filter_map(|(feature, test)| m.get(*feature).map(|v| test(*v)))
+            .collect();
+        if values.is_empty() {
+            missing.push(name.to_string());
+        } else if values.contains(&true) {
+            met.push(name.to_string());
+        }
+    }
+    CriteriaCount {
+        score: met.len() as u8,
+        met,
+        missing,
+    }
+}
+
+/// Whether any of the `total` criteria of `c` could be assessed.
+fn assessed(c: &CriteriaCount, total: usize) -> bool {
+    c.missing.len() < total
+}
+
+impl SepsisScreen {
+    /// qSOFA and SIRS of `input`; `None` when neither has a criterion
+    /// measured.
+    pub fn from_input(input: &Input) -> Option<SepsisScreen> {
+        let screen = SepsisScreen {
+            qsofa: count(&input.measured, QSOFA),
+            sirs: count(&input.measured, SIRS),
+        };
+        (assessed(&screen.qsofa, QSOFA.len()) || assessed(&screen.sirs, SIRS.len()))
+            .then_some(screen)
+    }
+
+    /// The counts with at least one criterion measured, by score name.
+    pub fn scores(&self) -> impl Iterator<Item = (&'static str, u8)> + '_ {
+        [
+            (QSOFA_SCORE, &self.qsofa, QSOFA.len()),
+            (SIRS_SCORE, &self.sirs, SIRS.len()),
+        ]
+        .into_iter()
+        .filter(|(_, c, total)| assessed(c, *total))
+        .map(|(name, c, _)| (name, c.score))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{json, Value};
+
+    fn screen(measured: Value) -> Option<SepsisScreen> {
+        let input: Input =
+            serde_json::from_value(json!({ "text": "", "measured": measured })).unwrap();
+        SepsisScreen::from_input(&input)
+    }
+
+    #[test]
+    fn qsofa_criterion_edges() {
+        let q = |m: Value| screen(m).unwrap().qsofa.score;
+        assert_eq!(q(json!({ "rr_rpm": 21 })), 0);
+        assert_eq!(q(json!({ "rr_rpm": 22 })), 1);
+        assert_eq!(q(json!({ "sbp_mmhg": 101 })), 0);
+        assert_eq!(q(json!({ "sbp_mmhg": 100 })), 1);
+        assert_eq!(q(json!({ "gcs": 15 })), 0);
+        assert_eq!(q(json!({ "gcs": 14 })), 1);
+        assert_eq!(q(json!({ "gcs": 15, "acvpu": 1 })), 1);
+    }
+
+    #[test]
+    fn sirs_criterion_edges() {
+        let s = |m: Value| screen(m).unwrap().sirs.score;
+        assert_eq!(s(json!({ "temp_c": 36.0 })), 0);
+        assert_eq!(s(json!({ "temp_c": 38.0 })), 0);
+        assert_eq!(s(json!({ "temp_c": 35.9 })), 1);
+        assert_eq!(s(json!({ "temp_c": 38.1 })), 1);
+        assert_eq!(s(json!({ "hr_bpm": 90 })), 0);
+        assert_eq!(s(json!({ "hr_bpm": 91 })), 1);
+        assert_eq!(s(json!({ "rr_rpm": 20, "paco2_mmhg": 32 })), 0);
+        assert_eq!(s(json!({ "rr_rpm": 20, "paco2_mmhg": 31 })), 1);
+        assert_eq!(s(json!({ "wbc_k_ul": 4 })), 0);
+        assert_eq!(s(json!({ "wbc_k_ul": 12.1 })), 1);
+    }
+
+    #[test]
+    fn missing_criteria_are_listed_and_scores_need_one_measured() {
+        let sc = screen(json!({ "hr_bpm": 120 })).unwrap();
+        assert_eq!(sc.sirs.met, ["heart_rate"]);
+        assert_eq!(
+            sc.sirs.missing,
+            ["temperature", "respiration", "white_cells"]
+        );
+        assert_eq!(sc.qsofa.missing.len(), 3);
+        let scores: Vec<_> = sc.scores().collect();
+        assert_eq!(scores, [(SIRS_SCORE, 1)]);
+        assert!(screen(json!({ "spo2_pct": 90 })).is_none());
+    }
+}
@@ -0,0 +1,394 @@
+//! Short measurement time series and the trend functions over them.
+//!
+//! `Input.series` maps a feature to its recent samples (`at` in RFC 3339).
+//! Trend functions never look at raw samples directly; they read a window
+//! resampled onto a fixed grid, so every evaluation (either TDLN channel, a
+//! trigger, a catalog signal) does the same arithmetic in the same order:
+//!
+//! 1. Samples are ordered by time; of samples at the same second the last
+//!    one given wins.
+//! 2. The grid ends at the anchor, the newest sample of any feature in the
+//!    input, and steps back every [`RESAMPLE_STEP_S`] seconds to the start
+//!    of the window. `window_s` must be a positive multiple of the step, at
+//!    most [`MAX_WINDOW_S`].
+//! 3. Each grid point takes the last sample at or before it, if that sample
+//!    is at most [`RESAMPLE_HOLD_S`] old; otherwise the point is empty.
+//! 4. A window with any empty point is undefined, and so is every function
+//!    over it: comparisons against it are false, like a missing feature.
+//!
+//! Over the resampled values (oldest first), `delta` is last minus first,
+//! `slope` the least-squares slope per minute, and `min`/`max`/`mean` what
+//! they say.
+
+use crate::Input;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use thiserror::Error;
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+
+/// Spacing of the resampling grid.
+pub const RESAMPLE_STEP_S: u32 = 60;
+/// Longest a sample is carried forward onto later grid points.
+pub const RESAMPLE_HOLD_S: i64 = 300;
+/// Longest window a trend function may span.
+pub const MAX_WINDOW_S: u32 = 6 * 3600;
+
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum SeriesError {
+    #[error("series `{feature}`: `{at}` is not an RFC 3339 timestamp")]
+    BadTime { feature: String, at: String },
+    #[error("series `{feature}`: sample at `{at}` is not a finite number")]
+    NonFinite { feature: String, at: String },
+}
+
+/// One timestamped value of a feature.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Sample {
+    /// Observation time (RFC 3339).
+    pub at: String,
+    pub value: f64,
+}
+
+/// A function over a resampled window.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SeriesFn {
+    Delta,
+    Slope,
+    Min,
+    Max,
+    Mean,
+}
+
+impl SeriesFn {
+    pub fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "delta" => SeriesFn::Delta,
+            "slope" => SeriesFn::Slope,
+            "min" => SeriesFn::Min,
+            "max" => SeriesFn::Max,
+            "mean" => SeriesFn::Mean,
+            _ => return None,
+        })
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SeriesFn::Delta => "delta",
+            SeriesFn::Slope => "slope",
+            SeriesFn::Min => "min",
+            SeriesFn::Max => "max",
+            SeriesFn::Mean => "mean",
+        }
+    }
+
+    fn apply(&self, values: &[f64]) -> f64 {
+        let n = values.len() as f64;
+        match self {
+            SeriesFn::Delta => values[values.len() - 1] - values[0],
+            SeriesFn::Min => values.iter().copied().fold(f64::INFINITY, f64::min),
+            SeriesFn::Max => values.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+            SeriesFn::Mean => values.iter().sum::<f64>() / n,
+            SeriesFn::Slope => {
+                let step_min = RESAMPLE_STEP_S as f64 / 60.0;
+                let x_mean = (n - 1.0) * step_min / 2.0;
+                let y_mean = values.iter().sum::<f64>() / n;
+                let (mut num, mut den) = (0.0, 0.0);
+                for (i, y) in values.iter().enumerate() {
+                    let dx = i as f64 * step_min - x_mean;
+                    num += dx * (y - y_mean);
+                    den += dx * dx;
+                }
+                num / den
+            }
+        }
+    }
+}
+
+/// Why `window_s` is not a valid trend window, if it is not.
+pub fn window_error(window_s: u32) -> Option<String> {
+    if window_s == 0 || window_s % RESAMPLE_STEP_S != 0 {
+        Some(format!(
+            "window must be a positive multiple of {RESAMPLE_STEP_S} s, got {window_s}"
+        ))
+    } else if window_s > MAX_WINDOW_S {
+        Some(format!(
+            "window must be at most {MAX_WINDOW_S} s, got {window_s}"
+        ))
+    } else {
+        None
+    }
+}
+
+/// A sample as Unix seconds and value.
+type Point = (i64, f64);
+
+/// The series of an input, parsed and ordered, ready for windowed functions.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Series {
+    /// Newest sample time of any feature (Unix seconds).
+    anchor: Option<i64>,
+    samples: BTreeMap<String, Vec<Point>>,
+}
+
+fn unix_seconds(feature: &str, at: &str) -> Result<i64, SeriesError> {
+    OffsetDateTime::parse(at, &Rfc3339)
+        .map(|t| t.unix_timestamp())
+        .map_err(|_| SeriesError::BadTime {
+            feature: feature.to_string(),
+            at: at.to_string(),
+        })
+}
+
+impl Series {
+    pub fn from_input(input: &Input) -> Result<Self, SeriesError> {
+        let mut samples = BTreeMap::new();
+        let mut anchor = None;
+        for (feature, raw) in &input.series {
+            let mut parsed = Vec::with_capacity(raw.len());
+            for s in raw {
+                if !s.value.is_finite() {
+                    return Err(SeriesError::NonFinite {
+                        feature: feature.clone(),
+                        at: s.at.clone(),
+                    });
+                }
+                parsed.push((unix_seconds(feature, &s.at)?, s.value));
+            }
+            // Stable sort, then keep the last sample of each second.
+            parsed.sort_by_key(|(t, _)| *t);
+            let mut deduped: Vec<Point> = Vec::with_capacity(parsed.len());
+            for (t, v) in parsed {
+                match deduped.last_mut() {
+                    Some(last) if last.0 == t => last.1 = v,
+                    _ => deduped.push((t, v)),
+                }
+            }
+            if let Some((t, _)) = deduped.last() {
+                anchor = anchor.max(Some(*t));
+            }
+            samples.insert(feature.clone(), deduped);
+        }
+        Ok(Series { anchor, samples })
+    }
+
+    /// Newest and, if any, second-newest sample of `feature`.
+    pub fn latest(&self, feature: &str) -> Option<(Point, Option<Point>)> {
+        let s = self.samples.get(feature)?;
+        let last = *s.last()?;
+        Some((last, s.len().checked_sub(2).map(|i| s[i])))
+    }
+
+    /// `feature` resampled over the last `window_s` seconds, oldest first;
+    /// `None` when the window is invalid or not fully covered.
+    pub fn window(&self, feature: &str, window_s: u32) -> Option<Vec<f64>> {
+        if window_error(window_s).is_some() {
+            return None;
+        }
+        let anchor = self.anchor?;
+        let samples = self.samples.get(feature)?;
+        let points = (window_s / RESAMPLE_STEP_S) as i64;
+        let mut out = Vec::with_capacity(points as usize + 1);
+        for k in (0..=points).rev() {
+            let t = anchor - k * RESAMPLE_STEP_S as i64;
+            let idx = samples.partition_point(|(at, _)| *at <= t);
+            let (at, v) = *samples.get(idx.checked_sub(1)?)?;
+            if t - at > RESAMPLE_HOLD_S {
+                return None;
+            }
+            out.push(v);
+        }
+        Some(out)
+    }
+
+    /// `function` of `feature` over the last `window_s` seconds.
+    pub fn eval(&self, function: SeriesFn, feature: &str, window_s: u32) -> Option<f64> {
+        let v = function.apply(&self.window(feature, window_s)?);
+        v.is_finite().then_some(v)
+    }
+}
+
+/// Format Unix seconds as RFC 3339 (UTC).
+fn rfc3339(t: i64) -> Option<String> {
+    OffsetDateTime::from_unix_timestamp(t)
+        .ok()?
+        .format(&Rfc3339)
+        .ok()
+}
+
+/// `input` with the newest sample of each series standing in for a missing
+/// `measured` value (observed at the sample's time), and the sample before
+/// the measured value standing in for a missing `previous` one.
+pub fn fill_from_series(input: &Input) -> Result<Input, SeriesError> {
+    let series = Series::from_input(input)?;
+    let mut out = input.clone();
+    for feature in input.series.keys() {
+        let Some(((at, value), before)) = series.latest(feature) else {
+            continue;
+        };
+        let mut before = before;
+        if out.measured.contains_key(feature) {
+            before = Some((at, value));
+        } else {
+            out.measured.insert(feature.clone(), value);
+            let obs = out.observations.entry(feature.clone()).or_default();
+            if obs.at.is_none() {
+                obs.at = rfc3339(at);
+            }
+            if obs.source.is_none() {
+                obs.source = Some("series".into());
+            }
+        }
+        if let Some((_, prev)) = before {
+            out.previous.entry(feature.clone()).or_insert(prev);
+        }
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{json, Value};
+
+    fn input(series: Value) -> Input {
+        serde_json::from_value(json!({ "text": "", "series": series })).unwrap()
+    }
+
+    fn series(series: Value) -> Series {
+        Series::from_input(&input(series)).unwrap()
+    }
+
+    fn at(minute: u32) -> String {
+        format!("2024-01-01T10:{minute:02}:00Z")
+    }
+
+    /// One sample a minute from 10:00 to 10:10, value `f(minute)`.
+    fn grid(f: impl Fn(u32) -> f64) -> Value {
+        (0..=10)
+            .map(|m| json!({ "at": at(m), "value": f(m) }))
+            .collect()
+    }
+
+    #[test]
+    fn same_second_keeps_the_last_sample_given() {
+        let s = series(json!({ "hr_bpm": [
+            { "at": "2024-01-01T10:01:00Z", "value": 100 },
+            { "at": "2024-01-01T10:00:00Z", "value": 90 },
+            { "at": "2024-01-01T11:01:00+01:00", "value": 110 },
+        ]}));
+        assert_eq!(
+            s.latest("hr_bpm"),
+            Some(((1704103260, 110.0), Some((1704103200, 90.0))))
+        );
+    }
+
+    #[test]
+    fn bad_samples_are_errors() {
+        let bad_time = json!({ "hr_bpm": [{ "at": "10:00", "value": 90 }] });
+        assert_eq!(
+            Series::from_input(&input(bad_time)),
+            Err(SeriesError::BadTime {
+                feature: "hr_bpm".into(),
+                at: "10:00".into(),
+            })
+        );
+        let mut i = input(json!({ "hr_bpm": [{ "at": at(0), "value": 90 }] }));
+        i.series.get_mut("hr_bpm").unwrap()[0].value = f64::NAN;
+        assert!(matches!(
+            Series::from_input(&i),
+            Err(SeriesError::NonFinite { .. })
+        ));
+    }
+
+    #[test]
+    fn window_error_bounds() {
+        assert!(window_error(0).is_some());
+        assert!(window_error(90).is_some());
+        assert_eq!(window_error(RESAMPLE_STEP_S), None);
+        assert_eq!(window_error(MAX_WINDOW_S), None);
+        assert!(window_error(MAX_WINDOW_S + RESAMPLE_STEP_S).is_some());
+        let s = series(json!({ "hr_bpm": grid(|_| 80.0) }));
+        assert_eq!(s.eval(SeriesFn::Mean, "hr_bpm", 90), None);
+    }
+
+    #[test]
+    fn functions_over_a_known_grid() {
+        // 10:00..10:10, rising 2 per minute from 80 to 100.
+        let s = series(json!({ "hr_bpm": grid(|m| 80.0 + 2.0 * m as f64) }));
+        assert_eq!(
+            s.window("hr_bpm", 300),
+            Some(vec![90.0, 92.0, 94.0, 96.0, 98.0, 100.0])
+        );
+        assert_eq!(s.eval(SeriesFn::Delta, "hr_bpm", 600), Some(20.0));
+        assert_eq!(s.eval(SeriesFn::Slope, "hr_bpm", 600), Some(2.0));
+        assert_eq!(s.eval(SeriesFn::Min, "hr_bpm", 300), Some(90.0));
+        assert_eq!(s.eval(SeriesFn::Max, "hr_bpm", 300), Some(100.0));
+        assert_eq!(s.eval(SeriesFn::Mean, "hr_bpm", 300), Some(95.0));
+        // Reaching back before 10:00 leaves the oldest point empty.
+        assert_eq!(s.eval(SeriesFn::Delta, "hr_bpm", 660), None);
+        assert_eq!(SeriesFn::from_name("slope"), Some(SeriesFn::Slope));
+        assert_eq!(SeriesFn::from_name("median"), None);
+    }
+
+    #[test]
+    fn samples_are_held_for_at_most_the_hold_time() {
+        // Samples at 10:00 and 10:10 only: 10:05 holds 10:00 (5 min old),
+        // 10:06..10:09 have nothing recent enough.
+        let gap = json!({ "hr_bpm": [
+            { "at": at(0), "value": 80 },
+            { "at": at(10), "value": 90 },
+        ]});
+        let s = series(gap);
+        assert_eq!(s.window("hr_bpm", 60), None);
+        let held = series(json!({ "hr_bpm": [
+            { "at": at(0), "value": 80 },
+            { "at": at(5), "value": 85 },
+            { "at": at(10), "value": 90 },
+        ]}));
+        assert_eq!(
+            held.window("hr_bpm", 600),
+            Some(vec![
+                80.0, 80.0, 80.0, 80.0, 80.0, 85.0, 85.0, 85.0, 85.0, 85.0, 90.0
+            ])
+        );
+    }
+
+    #[test]
+    fn the_anchor_is_the_newest_sample_of_any_feature() {
+        // Heart rate stops at 10:10 but SpO2 runs on: the heart-rate window
+        // ends at the SpO2 sample, holding 10:10 for at most five minutes.
+        let until = |minute: u32| {
+            series(json!({
+                "hr_bpm": grid(|_| 80.0),
+                "spo2_pct": [{ "at": at(minute), "value": 95 }],
+            }))
+        };
+        assert_eq!(until(15).window("hr_bpm", 300), Some(vec![80.0; 6]));
+        assert_eq!(until(16).window("hr_bpm", 300), None);
+        assert_eq!(until(16).window("spo2_pct", 60), None);
+    }
+
+    #[test]
+    fn fill_from_series_records_where_values_came_from() {
+        let mut i = input(json!({
+            "hr_bpm": [{ "at": at(0), "value": 80 }, { "at": at(5), "value": 85 }],
+            "spo2_pct": [{ "at": at(0), "value": 93 }, { "at": at(5), "value": 91 }],
+        }));
+        i.measured.insert("spo2_pct".into(), 89.0);
+        let out = fill_from_series(&i).unwrap();
+
+        // Missing measured value: newest sample, observed then, from the series.
+        assert_eq!(out.measured["hr_bpm"], 85.0);
+        assert_eq!(out.previous["hr_bpm"], 80.0);
+        let obs = &out.observations["hr_bpm"];
+        assert_eq!(obs.at.as_deref(), Some("2024-01-01T10:05:00Z"));
+        assert_eq!(obs.source.as_deref(), Some("series"));
+
+        // A measured value wins; the newest sample becomes its previous one.
+        assert_eq!(out.measured["spo2_pct"], 89.0);
+        assert_eq!(out.previous["spo2_pct"], 91.0);
+        assert!(!out.observations.contains_key("spo2_pct"));
+    }
+}
@@ -0,0 +1,356 @@
+//! Sequential Organ Failure Assessment (Vincent et al., 1996).
+//!
+//! SOFA scores six organ systems 0–4 from labs and vitals in
+//! `Input.measured`:
+//!
+//! | organ          | features                             | 1        | 2        | 3          | 4          |
+//! |----------------|--------------------------------------|----------|----------|------------|------------|
+//! | respiration    | `pao2_mmhg` / `fio2`, `resp_support` | < 400    | < 300    | < 200 [^v] | < 100 [^v] |
+//! | coagulation    | `platelets_k_ul` (10³/µL)            | < 150    | < 100    | < 50       | < 20       |
+//! | liver          | `bilirubin_mg_dl`                    | 1.2–1.9  | 2.0–5.9  | 6.0–11.9   | ≥ 12       |
+//! | cardiovascular | `map_mmhg`, vasopressors             | MAP < 70 | [^c]     | [^c]       | [^c]       |
+//! | CNS            | `gcs`                                | 13–14    | 10–12    | 6–9        | < 6        |
+//! | renal          | `creatinine_mg_dl`                   | 1.2–1.9  | 2.0–3.4  | 3.5–4.9    | ≥ 5.0      |
+//! | renal          | `urine_ml_24h`                       |          |          | < 500      | < 200      |
+//!
+//! [^v]: With `resp_support` (1 under mechanical or non-invasive
+//! ventilation); without it respiration scores at most 2.
+//!
+//! [^c]: In µg/kg/min: 2 for dopamine ≤ 5 or any dobutamine; 3 for dopamine
+//! > 5 or epinephrine or norepinephrine ≤ 0.1; 4 for dopamine > 15 or
+//! epinephrine or norepinephrine > 0.1.
+//!
+//! Vasopressors are `dopamine_mcg_kg_min`, `dobutamine_mcg_kg_min`,
+//! `epinephrine_mcg_kg_min` and `norepinephrine_mcg_kg_min`; absent ones are
+//! taken as not running, and an absent `resp_support` as no ventilation.
+//! Renal takes the worse of creatinine and urine output.
+//!
+//! An organ with none of its features measured is reported in `missing`
+//! and adds nothing to the total, so an incomplete total is a lower bound.
+//! `delta_24h` compares against the same score over `Input.measured_24h`,
+//! counting only organs scored at both times; policies see the total as
+//! `sofa` and the delta as `sofa_delta`.
+
+use crate::Input;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Trigger identifier of the total.
+pub const SOFA_SCORE: &str = "sofa";
+/// Trigger identifier of the 24-hour change of the total.
+pub const SOFA_DELTA_SCORE: &str = "sofa_delta";
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum SofaOrgan {
+    Respiration,
+    Coagulation,
+    Liver,
+    Cardiovascular,
+    Cns,
+    Renal,
+}
+
+/// Sub-score of one organ system and the value that decided it.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SofaComponent {
+    pub organ: SofaOrgan,
+    pub points: u8,
+    /// Deciding feature, or `pao2_fio2` for the respiratory ratio.
+    pub feature: String,
+    pub value: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Sofa {
+    pub total: u8,
+    /// Sub-scores of the organs scored, in SOFA order.
+    pub components: Vec<SofaComponent>,
+    /// Organs without any of their features measured.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub missing: Vec<SofaOrgan>,
+    /// Change of the total since `Input.measured_24h`, over the organs
+    /// scored at both times.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub delta_24h: Option<i16>,
+}
+
+/// Points of `v` for descending lower bounds: the first bound `v` is
+/// below scores its points.
+fn below(v: f64, bounds: &[(f64, u8)]) -> u8 {
+    bounds
+        .iter()
+        .rev()
+        .find(|(b, _)| v < *b)
+        .map_or(0, |(_, p)| *p)
+}
+
+/// Points of `v` for ascending lower bounds: the last bound `v` reaches
+/// scores its points.
+fn at_least(v: f64, bounds: &[(f64, u8)]) -> u8 {
+    bounds
+        .iter()
+        .rev()
+        .find(|(b, _)| v >= *b)
+        .map_or(0, |(_, p)| *p)
+}
+
+fn component(organ: SofaOrgan, points: u8, feature: &str, value: f64) -> SofaComponent {
+    SofaComponent {
+        organ,
+        points,
+        feature: feature.to_string(),
+        value,
+    }
+}
+
+fn respiration(m: &BTreeMap<String, f64>) -> Option<SofaComponent> {
+    let (pao2, fio2) = (*m.get("pao2_mmhg")?, *m.get("fio2")?);
+    let ratio = pao2 / fio2;
+    if !ratio.is_finite() {
+        return None;
+    }
+    let supported = m.get("resp_support").is_some_and(|s| *s >= 1.0);
+    let mut points = below(ratio, &[(400.0, 1), (300.0, 2), (200.0, 3), (100.0, 4)]);
+    if !supported {
+        points = points.min(2);
+    }
+    Some(component(
+        SofaOrgan::Respiration,
+        points,
+        "pao2_fio2",
+        ratio,
+    ))
+}
+
+fn cardiovascular(m: &BTreeMap<String, f64>) -> Option<SofaComponent> {
+    let dose = |f: &str| m.get(f).copied().filter(|d| *d > 0.0);
+    let mut best: Option<SofaComponent> = m.get("map_mmhg").map(|v| {
+        component(
+            SofaOrgan::Cardiovascular,
+            u8::from(*v < 70.0),
+            "map_mmhg",
+            *v,
+        )
+    });
+    let mut consider = |points: u8, feature: &str, value: f64| {
+        if best.as_ref().map_or(true, |b| points > b.points) {
+            best = Some(component(SofaOrgan::Cardiovascular, points, feature, value));
+        }
+    };
+    if let Some(d) = dose("dopamine_mcg_kg_min") {
+        let points = match d {
+            d if d > 15.0 => 4,
+            d if d > 5.0 => 3,
+            _ => 2,
+        };
+        consider(points, "dopamine_mcg_kg_min", d);
+    }
+    if let Some(d) = dose("dobutamine_mcg_kg_min") {
+        consider(2, "dobutamine_mcg_kg_min", d);
+    }
+    for f in ["epinephrine_mcg_kg_min", "norepinephrine_mcg_kg_min"] {
+        if let Some(d) = dose(f) {
+            consider(if d > 0.1 { 4 } else { 3 }, f, d);
+        }
+    }
+    best
+}
+
+fn renal(m: &BTreeMap<String, f64>) -> Option<SofaComponent> {
+    let by_creatinine = m.get("creatinine_mg_dl").map(|v| {
+        let points = at_least(*v, &[(1.2, 1), (2.0, 2), (3.5, 3), (5.0, 4)]);
+        component(SofaOrgan::Renal, points, "creatinine_mg_dl", *v)
+    });
+    let by_urine = m.get("urine_ml_24h").map(|v| {
+        let points = below(*v, &[(500.0, 3), (200.0, 4)]);
+        component(SofaOrgan::Renal, points, "urine_ml_24h", *v)
+    });
+    match (by_creatinine, by_urine) {
+        (Some(c), Some(u)) if u.points > c.points => Some(u),
+        (Some(c), _) => Some(c),
+        (None, u) => u,
+    }
+}
+
+/// Organ sub-scores of `m`, in SOFA order; `None` for organs not measured.
+fn components(m: &BTreeMap<String, f64>) -> Vec<(SofaOrgan, Option<SofaComponent>)> {
+    let simple = |organ, feature: &str, score: &dyn Fn(f64) -> u8| {
+        m.get(feature)
+            .map(|v| component(organ, score(*v), feature, *v))
+    };
+    vec![
+        (SofaOrgan::Respiration, respiration(m)),
+        (
+            SofaOrgan::Coagulation,
+            simple(SofaOrgan::Coagulation, "platelets_k_ul", &|v| {
+                below(v, &[(150.0, 1), (100.0, 2), (50.0, 3), (20.0, 4)])
+            }),
+        ),
+        (
+            SofaOrgan::Liver,
+            simple(SofaOrgan::Liver, "bilirubin_mg_dl", &|v| {
+                at_least(v, &[(1.2, 1), (2.0, 2), (6.0, 3), (12.0, 4)])
+            }),
+        ),
+        (SofaOrgan::Cardiovascular, cardiovascular(m)),
+        (
+            SofaOrgan::Cns,
+            simple(SofaOrgan::Cns, "gcs", &|v| {
+                below(v, &[(15.0, 1), (13.0, 2), (10.0, 3), (6.0, 4)])
+            }),
+        ),
+        (SofaOrgan::Renal, renal(m)),
+    ]
+}
+
+impl Sofa {
+    /// SOFA of `input`; `None` when no organ can be scored.
+    pub fn from_input(input: &Input) -> Option<Sofa> {
+        let now = components(&input.measured);
+        let mut out = Vec::new();
+        let mut missing = Vec::new();
+        for (organ, c) in &now {
+            match c {
+                Some(c) => out.push(c.clone()),
+                None => missing.push(*organ),
+            }
+        }
+        if out.is_empty() {
+            return None;
+        }
+        let delta_24h = (!input.measured_24h.is_empty())
+            .then(|| components(&input.measured_24h))
+            .and_then(|before| {
+                let pairs: Vec<(u8, u8)> = now
+                    .iter()
+                    .zip(&before)
+                    .filter_map(|((_, n), (_, b))| Some((n.as_ref()?.points, b.as_ref()?.points)))
+                    .collect();
+                (!pairs.is_empty()).then(|| pairs.iter().map(|(n, b)| *n as i16 - *b as i16).sum())
+            });
+        Some(Sofa {
+            total: out.iter().map(|c| c.points).sum(),
+            components: out,
+            missing,
+            delta_24h,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{json, Value};
+
+    fn sofa(measured: Value, measured_24h: Value) -> Option<Sofa> {
+        let input: Input = serde_json::from_value(json!({
+            "text": "",
+            "measured": measured,
+            "measured_24h": measured_24h,
+        }))
+        .unwrap();
+        Sofa::from_input(&input)
+    }
+
+    /// Total for a single `feature` at each of `values`.
+    fn points(feature: &str, values: &[f64]) -> Vec<u8> {
+        values
+            .iter()
+            .map(|v| sofa(json!({ feature: v }), json!({})).unwrap().total)
+            .collect()
+    }
+
+    #[test]
+    fn coagulation_band_edges() {
+        let plt = [150.0, 149.0, 100.0, 99.0, 50.0, 49.0, 20.0, 19.0];
+        assert_eq!(points("platelets_k_ul", &plt), [0, 1, 1, 2, 2, 3, 3, 4]);
+    }
+
+    #[test]
+    fn liver_band_edges() {
+        let bili = [1.1, 1.2, 1.9, 2.0, 5.9, 6.0, 11.9, 12.0];
+        assert_eq!(points("bilirubin_mg_dl", &bili), [0, 1, 1, 2, 2, 3, 3, 4]);
+    }
+
+    #[test]
+    fn cns_band_edges() {
+        let gcs = [15.0, 14.0, 13.0, 12.0, 10.0, 9.0, 6.0, 5.0];
+        assert_eq!(points("gcs", &gcs), [0, 1, 1, 2, 2, 3, 3, 4]);
+    }
+
+    #[test]
+    fn renal_band_edges_and_worse_of_two() {
+        let cr = [1.1, 1.2, 1.9, 2.0, 3.4, 3.5, 4.9, 5.0];
+        assert_eq!(points("creatinine_mg_dl", &cr), [0, 1, 1, 2, 2, 3, 3, 4]);
+        assert_eq!(
+            points("urine_ml_24h", &[500.0, 499.0, 200.0, 199.0]),
+            [0, 3, 3, 4]
+        );
+        let s = sofa(
+            json!({ "creatinine_mg_dl": 1.5, "urine_ml_24h": 150 }),
+            json!({}),
+        )
+        .unwrap();
+        assert_eq!(s.components[0].feature, "urine_ml_24h");
+        assert_eq!(s.total, 4);
+    }
+
+    #[test]
+    fn respiration_band_edges_and_ventilation_cap() {
+        let resp = |ratio: f64, support: f64| {
+            let m = json!({ "pao2_mmhg": ratio, "fio2": 1.0, "resp_support": support });
+            sofa(m, json!({})).unwrap().total
+        };
+        let ventilated: Vec<u8> = [400.0, 399.0, 300.0, 299.0, 200.0, 199.0, 100.0, 99.0]
+            .iter()
+            .map(|r| resp(*r, 1.0))
+            .collect();
+        assert_eq!(ventilated, [0, 1, 1, 2, 2, 3, 3, 4]);
+        assert_eq!(resp(99.0, 0.0), 2);
+        assert!(sofa(json!({ "pao2_mmhg": 80, "fio2": 0 }), json!({})).is_none());
+    }
+
+    #[test]
+    fn cardiovascular_takes_the_strongest_support() {
+        let cv = |m: Value| sofa(m, json!({})).unwrap().total;
+        assert_eq!(cv(json!({ "map_mmhg": 70 })), 0);
+        assert_eq!(cv(json!({ "map_mmhg": 69 })), 1);
+        assert_eq!(cv(json!({ "dopamine_mcg_kg_min": 5 })), 2);
+        assert_eq!(cv(json!({ "dopamine_mcg_kg_min": 5.1 })), 3);
+        assert_eq!(cv(json!({ "dopamine_mcg_kg_min": 15.1 })), 4);
+        assert_eq!(cv(json!({ "dobutamine_mcg_kg_min": 2 })), 2);
+        assert_eq!(cv(json!({ "norepinephrine_mcg_kg_min": 0.1 })), 3);
+        assert_eq!(
+            cv(json!({ "map_mmhg": 60, "norepinephrine_mcg_kg_min": 0.11 })),
+            4
+        );
+        assert_eq!(
+            cv(json!({ "map_mmhg": 75, "epinephrine_mcg_kg_min": 0 })),
+            0
+        );
+    }
+
+    #[test]
+    fn delta_counts_organs_scored_at_both_times() {
+        let s = sofa(
+            json!({ "platelets_k_ul": 40, "gcs": 9 }),
+            json!({ "platelets_k_ul": 160, "bilirubin_mg_dl": 7 }),
+        )
+        .unwrap();
+        assert_eq!(s.total, 6);
+        assert_eq!(s.delta_24h, Some(3));
+        assert_eq!(
+            s.missing,
+            [
+                SofaOrgan::Respiration,
+                SofaOrgan::Liver,
+                SofaOrgan::Cardiovascular,
+                SofaOrgan::Renal
+            ]
+        );
+        let none = sofa(json!({ "gcs": 9 }), json!({ "platelets_k_ul": 160 })).unwrap();
+        assert_eq!(none.delta_24h, None);
+        assert!(sofa(json!({}), json!({})).is_none());
+    }
+}
@@ -0,0 +1,1164 @@
+//! Trigger expression language used by `Policy.triggers`.
+//!
+//! The language is small, total and deterministic: every well-typed
+//! expression evaluates to a boolean for every `Input`, with no I/O, no
+//! loops and no clock. Missing or non-finite numbers never match: a
+//! comparison on one is unknown, `not` keeps it unknown, `and`/`or` follow
+//! three-valued logic, and a trigger that is unknown overall is false. So
+//! `not spo2_pct < 90` does not fire when SpO2 was not measured.
+//!
+//! ```text
+//! expr    := or
+//! or      := and ("or" and)*
+//! and     := unary ("and" unary)*
+//! unary   := "not" unary | primary
+//! primary := "true" | "false" | "(" expr ")"
+//!          | IDENT "matches" /REGEX/FLAGS
+//!          | IDENT "contains" "STRING"
+//!          | "severity" RELOP LEVEL
+//!          | num RELOP num
+//!          | IDENT
+//! num     := term (("+" | "-") term)*
+//! term    := factor (("*" | "/") factor)*
+//! factor  := NUMBER | IDENT | "$" IDENT | "-" factor | "(" num ")"
+//!          | FN "(" IDENT "," NUMBER ")"
+//! FN      := "delta" | "slope" | "min" | "max" | "mean"
+//! RELOP   := "<" | "<=" | ">" | ">=" | "==" | "!="
+//! LEVEL   := "ROUTINE" | "URGENT" | "CRITICAL"
+//! ```
+//!
+//! Identifiers in numeric position name `Input.measured` keys or scores the
+//! engine computes from the input ([`KNOWN_SCORES`], e.g. `news2 >= 5`); `text` names
+//! `Input.text` and is the only string subject. `$name` is a numeric policy
+//! parameter (`Policy.params`), the knob overlays use to move thresholds.
+//! The only regex flag is `i`. `matches` and `contains` only count affirmed
+//! mentions: `sem hipoxemia` does not match `/hipox/` (see [`crate::negation`]).
+//! A bare identifier in boolean position is an AST signal, true when the
+//! TDLN channels raised it. `severity` compares against the AST severity,
+//! ordered `ROUTINE < URGENT < CRITICAL`.
+//! `FN(feature, window_s)` applies a trend function to `Input.series` over
+//! the last `window_s` seconds, e.g. `delta(spo2_pct, 900) <= -6`; see
+//! [`crate::series`] for the resampling rules.
+
+use crate::negation::{is_affirmed_match, is_affirmed_substring};
+use crate::news2::NEWS2_SCORE;
+use crate::sepsis::{QSOFA_SCORE, SIRS_SCORE};
+use crate::series::{window_error, Series, SeriesFn};
+use crate::sofa::{SOFA_DELTA_SCORE, SOFA_SCORE};
+use crate::{Severity, SignalCatalog};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use thiserror::Error;
+
+/// Measured features the type checker accepts by default. `acvpu` codes
+/// consciousness: 0 alert, 1 new confusion, 2 voice, 3 pain, 4 unresponsive;
+/// `resp_support` is 1 under mechanical or non-invasive ventilation.
+/// Vasopressor doses are in µg/kg/min. `shock_index`, `pf_ratio`, `sf_ratio` and
+/// the kidney features are usually derived (see [`crate::derived`]).
+pub const KNOWN_FEATURES: &[&str] = &[
+    "spo2_pct",
+    "hr_bpm",
+    "rr_rpm",
+    "sbp_mmhg",
+    "dbp_mmhg",
+    "map_mmhg",
+    "temp_c",
+    "fio2",
+    "acvpu",
+    "pao2_mmhg",
+    "resp_support",
+    "platelets_k_ul",
+    "bilirubin_mg_dl",
+    "gcs",
+    "creatinine_mg_dl",
+    "urine_ml_24h",
+    "dopamine_mcg_kg_min",
+    "dobutamine_mcg_kg_min",
+    "epinephrine_mcg_kg_min",
+    "norepinephrine_mcg_kg_min",
+    "lactate_mmol_l",
+    "wbc_k_ul",
+    "paco2_mmhg",
+    "shock_index",
+    "pf_ratio",
+    "sf_ratio",
+    "peep_cmh2o",
+    "creatinine_ratio",
+    "creatinine_rise_mg_dl",
+    "creatinine_acute_mg_dl",
+    "urine_ml_kg_h_6h",
+    "urine_ml_kg_h_12h",
+    "urine_ml_kg_h_24h",
+];
+
+/// Scores computed from the input, usable in numeric position like features.
+pub const KNOWN_SCORES: &[&str] = &[
+    NEWS2_SCORE,
+    SOFA_SCORE,
+    SOFA_DELTA_SCORE,
+    QSOFA_SCORE,
+    SIRS_SCORE,
+];
+
+/// Deepest nesting of `not`, parentheses and unary minus a trigger may use;
+/// bounds the parser's recursion so a hostile policy cannot exhaust the stack.
+pub const MAX_TRIGGER_NESTING: usize = 64;
+
+const KEYWORDS: &[&str] = &[
+    "and", "or", "not", "true", "false", "matches", "contains", "severity",
+];
+
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum TriggerError {
+    #[error("syntax error at column {}: {msg}", pos + 1)]
+    Syntax { pos: usize, msg: String },
+    #[error("invalid regex at column {}: {msg}", pos + 1)]
+    Regex { pos: usize, msg: String },
+    #[error("type error at column {}: {msg}", pos + 1)]
+    Type { pos: usize, msg: String },
+    #[error("type error at column {}: unknown measured feature `{name}`", pos + 1)]
+    UnknownFeature { pos: usize, name: String },
+    #[error("type error at column {}: unknown signal `{name}`", pos + 1)]
+    UnknownSignal { pos: usize, name: String },
+    #[error("type error at column {}: unknown parameter `${name}`", pos + 1)]
+    UnknownParam { pos: usize, name: String },
+}
+
+impl TriggerError {
+    /// Byte offset of the error inside the trigger source.
+    pub fn pos(&self) -> usize {
+        match self {
+            TriggerError::Syntax { pos, .. }
+            | TriggerError::Regex { pos, .. }
+            | TriggerError::Type { pos, .. }
+            | TriggerError::UnknownFeature { pos, .. }
+            | TriggerError::UnknownSignal { pos, .. }
+            | TriggerError::UnknownParam { pos, .. } => *pos,
+        }
+    }
+}
+
+// -----------------------------
+// AST
+// -----------------------------
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Ident {
+    pub name: String,
+    pub pos: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RelOp {
+    #[serde(rename = "<")]
+    Lt,
+    #[serde(rename = "<=")]
+    Le,
+    #[serde(rename = ">")]
+    Gt,
+    #[serde(rename = ">=")]
+    Ge,
+    #[serde(rename = "==")]
+    Eq,
+    #[serde(rename = "!=")]
+    Ne,
+}
+
+impl RelOp {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RelOp::Lt => "<",
+            RelOp::Le => "<=",
+            RelOp::Gt => ">",
+            RelOp::Ge => ">=",
+            RelOp::Eq => "==",
+            RelOp::Ne => "!=",
+        }
+    }
+
+    pub fn holds(&self, l: f64, r: f64) -> bool {
+        match self {
+            RelOp::Lt => l < r,
+            RelOp::Le => l <= r,
+            RelOp::Gt => l > r,
+            RelOp::Ge => l >= r,
+            RelOp::Eq => l == r,
+            RelOp::Ne => l != r,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArithOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum NumExpr {
+    Const(f64),
+    Feature(Ident),
+    Param(Ident),
+    Neg(Box<NumExpr>),
+    Bin(ArithOp, Box<NumExpr>, Box<NumExpr>),
+    /// `FN(feature, window_s)` over `Input.series`.
+    Trend {
+        function: SeriesFn,
+        feature: Ident,
+        window_s: u32,
+        /// Position of the window literal.
+        window_pos: usize,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub enum Expr {
+    Bool(bool),
+    Not(Box<Expr>),
+    And(Vec<Expr>),
+    Or(Vec<Expr>),
+    Matches {
+        subject: Ident,
+        regex: Regex,
+    },
+    Contains {
+        subject: Ident,
+        needle: String,
+    },
+    Signal(Ident),
+    SeverityCmp {
+        op: RelOp,
+        level: Severity,
+    },
+    Compare {
+        lhs: NumExpr,
+        op: RelOp,
+        rhs: NumExpr,
+    },
+}
+
+// -----------------------------
+// Lexer
+// -----------------------------
+
+#[derive(Debug, Clone, PartialEq)]
+enum Tok {
+    Num(f64),
+    Ident(String),
+    Param(String),
+    Str(String),
+    Regex(String, String),
+    LParen,
+    RParen,
+    Comma,
+    Rel(RelOp),
+    Arith(ArithOp),
+}
+
+fn lex(src: &str) -> Result<Vec<(Tok, usize)>, TriggerError> {
+    let bytes = src.as_bytes();
+    let mut out: Vec<(Tok, usize)> = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        let c = bytes[i];
+        let start = i;
+        if c.is_ascii_whitespace() {
+            i += 1;
+            continue;
+        }
+        let after_matches = matches!(out.last(), Some((Tok::Ident(w), _)) if w == "matches");
+        if c == b'/' && after_matches {
+            // Regex literal: `/.../flags`, `\/` escapes a slash.
+            let mut pat = String::new();
+            let mut j = i + 1;
+            let mut closed = false;
+            while j < bytes.len() {
+                let ch = src[j..].chars().next().unwrap();
+                if ch == '\\' && src[j + 1..].starts_with('/') {
+                    pat.push('/');
+                    j += 2;
+                    continue;
+                }
+                if ch == '/' {
+                    closed = true;
+                    break;
+                }
+                pat.push(ch);
+                j += ch.len_utf8();
+            }
+            if !closed {
+                return Err(TriggerError::Syntax {
+                    pos: start,
+                    msg: "unterminated regex literal".into(),
+                });
+            }
+            j += 1;
+            let flags_start = j;
+            while j < bytes.len() && bytes[j].is_ascii_alphabetic() {
+                j += 1;
+            }
+            out.push((Tok::Regex(pat, src[flags_start..j].to_string()), start));
+            i = j;
+            continue;
+        }
+        if c == b'"' {
+            let mut s = String::new();
+            let mut j = i + 1;
+            let mut closed = false;
+            while j < bytes.len() {
+                let ch = src[j..].chars().next().unwrap();
+                if ch == '\\' && j + 1 < bytes.len() {
+                    let next = src[j + 1..].chars().next().unwrap();
+                    s.push(next);
+                    j += 1 + next.len_utf8();
+                    continue;
+                }
+                if ch == '"' {
+                    closed = true;
+                    break;
+                }
+                s.push(ch);
+                j += ch.len_utf8();
+            }
+            if !closed {
+                return Err(TriggerError::Syntax {
+                    pos: start,
+                    msg: "unterminated string literal".into(),
+                });
+            }
+            out.push((Tok::Str(s), start));
+            i = j + 1;
+            continue;
+        }
+        if c.is_ascii_digit() || (c == b'.' && i + 1 < bytes.len() && bytes[i + 1].is_ascii_digit())
+        {
+            let mut j = i;
+            while j < bytes.len() && (bytes[j].is_ascii_digit() || bytes[j] == b'.') {
+                j += 1;
+            }
+            let lit = &src[i..j];
+            let v: f64 = lit.parse().map_err(|_| TriggerError::Syntax {
+                pos: start,
+                msg: format!("invalid number `{lit}`"),
+            })?;
+            out.push((Tok::Num(v), start));
+            i = j;
+            continue;
+        }
+        if c == b'$' {
+            let mut j = i + 1;
+            while j < bytes.len() && (bytes[j].is_ascii_alphanumeric() || bytes[j] == b'_') {
+                j += 1;
+            }
+            if j == i + 1 {
+                return Err(TriggerError::Syntax {
+                    pos: start,
+                    msg: "expected a parameter name after `$`".into(),
+                });
+            }
+            out.push((Tok::Param(src[i + 1..j].to_string()), start));
+            i = j;
+            continue;
+        }
+        if c.is_ascii_alphabetic() || c == b'_' {
+            let mut j = i;
+            while j < bytes.len() && (bytes[j].is_ascii_alphanumeric() || bytes[j] == b'_') {
+                j += 1;
+            }
+            out.push((Tok::Ident(src[i..j].to_string()), start));
+            i = j;
+            continue;
+        }
+        let two = src.get(i..i + 2).unwrap_or("");
+        let (tok, len) = match two {
+            "<=" => (Tok::Rel(RelOp::Le), 2),
+            ">=" => (Tok::Rel(RelOp::Ge), 2),
+            "==" => (Tok::Rel(RelOp::Eq), 2),
+            "!=" => (Tok::Rel(RelOp::Ne), 2),
+            _ => match c {
+                b'<' => (Tok::Rel(RelOp::Lt), 1),
+                b'>' => (Tok::Rel(RelOp::Gt), 1),
+                b'(' => (Tok::LParen, 1),
+                b')' => (Tok::RParen, 1),
+                b',' => (Tok::Comma, 1),
+                b'+' => (Tok::Arith(ArithOp::Add), 1),
+                b'-' => (Tok::Arith(ArithOp::Sub), 1),
+                b'*' => (Tok::Arith(ArithOp::Mul), 1),
+                b'/' => (Tok::Arith(ArithOp::Div), 1),
+                _ => {
+                    let ch = src[i..].chars().next().unwrap();
+                    return Err(TriggerError::Syntax {
+                        pos: start,
+                        msg: format!("unexpected character `{ch}`"),
+                    });
+                }
+            },
+        };
+        out.push((tok, start));
+        i += len;
+    }
+    Ok(out)
+}
+
+// -----------------------------
+// Parser
+// -----------------------------
+
+struct Parser {
+    toks: Vec<(Tok, usize)>,
+    at: usize,
+    end: usize,
+    depth: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Tok> {
+        self.toks.get(self.at).map(|(t, _)| t)
+    }
+
+    fn pos(&self) -> usize {
+        self.toks.get(self.at).map(|(_, p)| *p).unwrap_or(self.end)
+    }
+
+    fn bump(&mut self) -> Option<Tok> {
+        let t = self.toks.get(self.at).map(|(t, _)| t.clone());
+        self.at += 1;
+        t
+    }
+
+    fn err<T>(&self, msg: impl Into<String>) -> Result<T, TriggerError> {
+        Err(TriggerError::Syntax {
+            pos: self.pos(),
+            msg: msg.into(),
+        })
+    }
+
+    /// Run `f` one nesting level deeper, refusing past [`MAX_TRIGGER_NESTING`].
+    fn nested<T>(
+        &mut self,
+        f: impl FnOnce(&mut Self) -> Result<T, TriggerError>,
+    ) -> Result<T, TriggerError> {
+        if self.depth >= MAX_TRIGGER_NESTING {
+            return self.err(format!("nesting deeper than {MAX_TRIGGER_NESTING} levels"));
+        }
+        self.depth += 1;
+        let out = f(self);
+        self.depth -= 1;
+        out
+    }
+
+    fn is_word(&self, w: &str) -> bool {
+        matches!(self.peek(), Some(Tok::Ident(s)) if s == w)
+    }
+
+    fn word_at(&self, offset: usize, w: &str) -> bool {
+        matches!(self.toks.get(self.at + offset), Some((Tok::Ident(s), _)) if s == w)
+    }
+
+    fn expr(&mut self) -> Result<Expr, TriggerError> {
+        let mut items = vec![self.and()?];
+        while self.is_word("or") {
+            self.bump();
+            items.push(self.and()?);
+        }
+        Ok(if items.len() == 1 {
+            items.pop().unwrap()
+        } else {
+            Expr::Or(items)
+        })
+    }
+
+    fn and(&mut self) -> Result<Expr, TriggerError> {
+        let mut items = vec![self.unary()?];
+        while self.is_word("and") {
+            self.bump();
+            items.push(self.unary()?);
+        }
+        Ok(if items.len() == 1 {
+            items.pop().unwrap()
+        } else {
+            Expr::And(items)
+        })
+    }
+
+    fn unary(&mut self) -> Result<Expr, TriggerError> {
+        if self.is_word("not") {
+            self.bump();
+            return Ok(Expr::Not(Box::new(self.nested(Self::unary)?)));
+        }
+        self.primary()
+    }
+
+    fn primary(&mut self) -> Result<Expr, TriggerError> {
+        if self.is_word("true") || self.is_word("false") {
+            let v = self.is_word("true");
+            self.bump();
+            return Ok(Expr::Bool(v));
+        }
+        if self.is_word("severity") {
+            return self.severity_cmp();
+        }
+        if let Some(Tok::Ident(name)) = self.peek().cloned() {
+            if self.word_at(1, "matches") || self.word_at(1, "contains") {
+                return self.string_op(name);
+            }
+            let continues_num = match self.toks.get(self.at + 1) {
+                Some((Tok::Rel(_) | Tok::Arith(_), _)) => true,
+                Some((Tok::LParen, _)) => SeriesFn::from_name(&name).is_some(),
+                _ => false,
+            };
+            if !continues_num && !KEYWORDS.contains(&name.as_str()) {
+                let pos = self.pos();
+                self.bump();
+                return Ok(Expr::Signal(Ident { name, pos }));
+            }
+        }
+        if self.peek() == Some(&Tok::LParen) {
+            // `(` opens either a boolean group or a numeric sub-expression;
+            // try the comparison first and fall back to the group.
+            let save = self.at;
+            match self.comparison() {
+                Ok(e) => return Ok(e),
+                Err(cmp_err) => {
+                    self.at = save;
+                    self.bump();
+                    let inner = self.nested(Self::expr);
+                    let grouped = inner.and_then(|e| {
+                        if self.peek() == Some(&Tok::RParen) {
+                            self.bump();
+                            Ok(e)
+                        } else {
+                            self.err("expected `)`")
+                        }
+                    });
+                    return grouped.map_err(|group_err| {
+                        if group_err.pos() >= cmp_err.pos() {
+                            group_err
+                        } else {
+                            cmp_err
+                        }
+                    });
+                }
+            }
+        }
+        self.comparison()
+    }
+
+    fn severity_cmp(&mut self) -> Result<Expr, TriggerError> {
+        self.bump();
+        let op = match self.peek() {
+            Some(Tok::Rel(op)) => *op,
+            _ => return self.err("`severity` expects a comparison operator"),
+        };
+        self.bump();
+        let level = match self.peek() {
+            Some(Tok::Ident(w)) if w == "CRITICAL" => Severity::CRITICAL,
+            Some(Tok::Ident(w)) if w == "URGENT" => Severity::URGENT,
+            Some(Tok::Ident(w)) if w == "ROUTINE" => Severity::ROUTINE,
+            _ => return self.err("expected ROUTINE, URGENT or CRITICAL"),
+        };
+        self.bump();
+        Ok(Expr::SeverityCmp { op, level })
+    }
+
+    fn string_op(&mut self, name: String) -> Result<Expr, TriggerError> {
+        let subject = Ident {
+            name,
+            pos: self.pos(),
+        };
+        self.bump();
+        let op_pos = self.pos();
+        let op = self.bump();
+        match (op, self.peek().cloned()) {
+            (Some(Tok::Ident(op)), Some(Tok::Regex(pat, flags))) if op == "matches" => {
+                let regex_pos = self.pos();
+                self.bump();
+                let mut prefix = String::new();
+                for f in flags.chars() {
+                    match f {
+                        'i' => prefix.push_str("(?i)"),
+                        other => {
+                            return Err(TriggerError::Regex {
+                                pos: regex_pos,
+                                msg: format!("unsupported flag `{other}`"),
+                            })
+                        }
+                    }
+                }
+                let regex =
+                    Regex::new(&format!("{prefix}{pat}")).map_err(|e| TriggerError::Regex {
+                        pos: regex_pos,
+                        msg: e.to_string(),
+                    })?;
+                Ok(Expr::Matches { subject, regex })
+            }
+            (Some(Tok::Ident(op)), Some(Tok::Str(needle))) if op == "contains" => {
+                self.bump();
+                Ok(Expr::Contains { subject, needle })
+            }
+            (Some(Tok::Ident(op)), _) => Err(TriggerError::Syntax {
+                pos: op_pos,
+                msg: if op == "matches" {
+                    "`matches` expects a /regex/ literal".into()
+                } else {
+                    "`contains` expects a \"string\" literal".into()
+                },
+            }),
+            _ => unreachable!("string_op is only entered on matches/contains"),
+        }
+    }
+
+    fn comparison(&mut self) -> Result<Expr, TriggerError> {
+        let lhs = self.num()?;
+        let op = match self.peek() {
+            Some(Tok::Rel(op)) => *op,
+            _ => return self.err("expected a comparison operator"),
+        };
+        self.bump();
+        let rhs = self.num()?;
+        Ok(Expr::Compare { lhs, op, rhs })
+    }
+
+    fn num(&mut self) -> Result<NumExpr, TriggerError> {
+        let mut acc = self.term()?;
+        while let Some(Tok::Arith(op @ (ArithOp::Add | ArithOp::Sub))) = self.peek().cloned() {
+            self.bump();
+            acc = NumExpr::Bin(op, Box::new(acc), Box::new(self.term()?));
+        }
+        Ok(acc)
+    }
+
+    fn term(&mut self) -> Result<NumExpr, TriggerError> {
+        let mut acc = self.factor()?;
+        while let Some(Tok::Arith(op @ (ArithOp::Mul | ArithOp::Div))) = self.peek().cloned() {
+            self.bump();
+            acc = NumExpr::Bin(op, Box::new(acc), Box::new(self.factor()?));
+        }
+        Ok(acc)
+    }
+
+    fn factor(&mut self) -> Result<NumExpr, TriggerError> {
+        let pos = self.pos();
+        match self.peek().cloned() {
+            Some(Tok::Num(v)) => {
+                self.bump();
+                Ok(NumExpr::Const(v))
+            }
+            Some(Tok::Ident(name))
+                if matches!(self.toks.get(self.at + 1), Some((Tok::LParen, _))) =>
+            {
+                match SeriesFn::from_name(&name) {
+                    Some(function) => self.trend(function),
+                    None => self.err(format!("unknown function `{name}`")),
+                }
+            }
+            Some(Tok::Ident(name)) if !KEYWORDS.contains(&name.as_str()) => {
+                self.bump();
+                Ok(NumExpr::Feature(Ident { name, pos }))
+            }
+            Some(Tok::Param(name)) => {
+                self.bump();
+                Ok(NumExpr::Param(Ident { name, pos }))
+            }
+            Some(Tok::Arith(ArithOp::Sub)) => {
+                self.bump();
+                Ok(NumExpr::Neg(Box::new(self.nested(Self::factor)?)))
+            }
+            Some(Tok::LParen) => {
+                self.bump();
+                let inner = self.nested(Self::num)?;
+                if self.peek() != Some(&Tok::RParen) {
+                    return self.err("expected `)`");
+                }
+                self.bump();
+                Ok(inner)
+            }
+            Some(_) => self.err("expected a number or a feature name"),
+            None => self.err("unexpected end of trigger"),
+        }
+    }
+
+    /// `FN ( IDENT , NUMBER )`, entered on the function name.
+    fn trend(&mut self, function: SeriesFn) -> Result<NumExpr, TriggerError> {
+        self.bump();
+        self.bump();
+        let feature = match self.peek().cloned() {
+            Some(Tok::Ident(name)) if !KEYWORDS.contains(&name.as_str()) => {
+                let pos = self.pos();
+                self.bump();
+                Ident { name, pos }
+            }
+            _ => return self.err(format!("`{}` expects a feature name", function.as_str())),
+        };
+        if self.peek() != Some(&Tok::Comma) {
+            return self.err("expected `,` before the window in seconds");
+        }
+        self.bump();
+        let window_pos = self.pos();
+        let window_s = match self.peek() {
+            Some(Tok::Num(v)) if v.fract() == 0.0 && *v >= 1.0 && *v <= u32::MAX as f64 => {
+                *v as u32
+            }
+            _ => return self.err("expected the window as a whole number of seconds"),
+        };
+        self.bump();
+        if self.peek() != Some(&Tok::RParen) {
+            return self.err("expected `)`");
+        }
+        self.bump();
+        Ok(NumExpr::Trend {
+            function,
+            feature,
+            window_s,
+            window_pos,
+        })
+    }
+}
+
+/// Parse a trigger into an untyped AST.
+pub fn parse_trigger(src: &str) -> Result<Expr, TriggerError> {
+    let toks = lex(src)?;
+    let mut p = Parser {
+        toks,
+        at: 0,
+        end: src.len(),
+        depth: 0,
+    };
+    if p.peek().is_none() {
+        return p.err("empty trigger");
+    }
+    let e = p.expr()?;
+    if p.peek().is_some() {
+        return p.err("unexpected trailing input");
+    }
+    Ok(e)
+}
+
+// -----------------------------
+// Type checker
+// -----------------------------
+
+/// Names the type checker resolves identifiers against.
+#[derive(Debug, Clone)]
+pub struct TypeEnv {
+    pub features: BTreeSet<String>,
+    pub signals: BTreeSet<String>,
+    pub params: BTreeSet<String>,
+    pub scores: BTreeSet<String>,
+    /// Threshold profiles a policy may refine its parameters for.
+    pub profiles: BTreeSet<String>,
+}
+
+impl TypeEnv {
+    /// Known features and scores plus the signals and profiles declared by
+    /// `catalog`; no parameters.
+    pub fn for_catalog(catalog: &SignalCatalog) -> Self {
+        TypeEnv {
+            features: KNOWN_FEATURES.iter().map(|s| s.to_string()).collect(),
+            signals: catalog.signal_ids().map(|s| s.to_string()).collect(),
+            params: BTreeSet::new(),
+            scores: KNOWN_SCORES.iter().map(|s| s.to_string()).collect(),
+            profiles: catalog.profile_ids().map(|s| s.to_string()).collect(),
+        }
+    }
+
+    /// This environment plus the given parameter names.
+    pub fn with_params<'a>(&self, params: impl IntoIterator<Item = &'a String>) -> Self {
+        let mut env = self.clone();
+        env.params.extend(params.into_iter().cloned());
+        env
+    }
+}
+
+impl Default for TypeEnv {
+    fn default() -> Self {
+        TypeEnv::for_catalog(SignalCatalog::builtin())
+    }
+}
+
+fn check_num(e: &NumExpr, env: &TypeEnv) -> Result<(), TriggerError> {
+    match e {
+        NumExpr::Const(_) => Ok(()),
+        NumExpr::Feature(id) if id.name == "text" => Err(TriggerError::Type {
+            pos: id.pos,
+            msg: "`text` is a string and cannot be compared numerically".into(),
+        }),
+        NumExpr::Feature(id)
+            if !env.features.contains(&id.name) && !env.scores.contains(&id.name) =>
+        {
+            Err(TriggerError::UnknownFeature {
+                pos: id.pos,
+                name: id.name.clone(),
+            })
+        }
+        NumExpr::Feature(_) => Ok(()),
+        NumExpr::Param(id) if !env.params.contains(&id.name) => Err(TriggerError::UnknownParam {
+            pos: id.pos,
+            name: id.name.clone(),
+        }),
+        NumExpr::Param(_) => Ok(()),
+        NumExpr::Neg(x) => check_num(x, env),
+        NumExpr::Bin(_, l, r) => {
+            check_num(l, env)?;
+            check_num(r, env)
+        }
+        NumExpr::Trend {
+            feature,
+            window_s,
+            window_pos,
+            ..
+        } => {
+            if env.scores.contains(&feature.name) {
+                return Err(TriggerError::Type {
+                    pos: feature.pos,
+                    msg: format!("`{}` is a score and has no series", feature.name),
+                });
+            }
+            check_num(&NumExpr::Feature(feature.clone()), env)?;
+            match window_error(*window_s) {
+                Some(msg) => Err(TriggerError::Type {
+                    pos: *window_pos,
+                    msg,
+                }),
+                None => Ok(()),
+            }
+        }
+    }
+}
+
+/// Check that every identifier resolves and every operator gets operands of
+/// the right type.
+pub fn check_trigger(e: &Expr, env: &TypeEnv) -> Result<(), TriggerError> {
+    match e {
+        Expr::Bool(_) => Ok(()),
+        Expr::Not(x) => check_trigger(x, env),
+        Expr::And(xs) | Expr::Or(xs) => xs.iter().try_for_each(|x| check_trigger(x, env)),
+        Expr::Matches { subject, .. } | Expr::Contains { subject, .. } => {
+            if subject.name == "text" {
+                Ok(())
+            } else {
+                Err(TriggerError::Type {
+                    pos: subject.pos,
+                    msg: format!("`{}` is numeric; only `text` is a string", subject.name),
+                })
+            }
+        }
+        Expr::Signal(id) if env.signals.contains(&id.name) => Ok(()),
+        Expr::Signal(id) if env.features.contains(&id.name) => Err(TriggerError::Type {
+            pos: id.pos,
+            msg: format!("`{}` is numeric and must be compared", id.name),
+        }),
+        Expr::Signal(id) => Err(TriggerError::UnknownSignal {
+            pos: id.pos,
+            name: id.name.clone(),
+        }),
+        Expr::SeverityCmp { .. } => Ok(()),
+        Expr::Compare { lhs, rhs, .. } => {
+            check_num(lhs, env)?;
+            check_num(rhs, env)
+        }
+    }
+}
+
+/// Parse and type-check a trigger in one step.
+pub fn compile_trigger(src: &str, env: &TypeEnv) -> Result<Expr, TriggerError> {
+    let e = parse_trigger(src)?;
+    check_trigger(&e, env)?;
+    Ok(e)
+}
+
+/// What a trigger looks at, as far as picking a policy's hazard goes.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TriggerReads {
+    /// Signals named in boolean position.
+    pub signals: BTreeSet<String>,
+    /// Measured features compared, directly or through a trend function.
+    pub features: BTreeSet<String>,
+    /// The trigger reads the text, a score or the AST severity, which no
+    /// single signal accounts for.
+    pub global: bool,
+}
+
+/// Signals, features and global inputs `e` reads.
+pub fn trigger_reads(e: &Expr) -> TriggerReads {
+    fn num(e: &NumExpr, out: &mut TriggerReads) {
+        match e {
+            NumExpr::Const(_) | NumExpr::Param(_) => {}
+            NumExpr::Feature(id) if KNOWN_SCORES.contains(&id.name.as_str()) => out.global = true,
+            NumExpr::Feature(id) | NumExpr::Trend { feature: id, .. } => {
+                out.features.insert(id.name.clone());
+            }
+            NumExpr::Neg(x) => num(x, out),
+            NumExpr::Bin(_, l, r) => {
+                num(l, out);
+                num(r, out);
+            }
+        }
+    }
+    fn walk(e: &Expr, out: &mut TriggerReads) {
+        match e {
+            Expr::Bool(_) => {}
+            Expr::Not(x) => walk(x, out),
+            Expr::And(xs) | Expr::Or(xs) => xs.iter().for_each(|x| walk(x, out)),
+            Expr::Matches { .. } | Expr::Contains { .. } | Expr::SeverityCmp { .. } => {
+                out.global = true
+            }
+            Expr::Signal(id) => {
+                out.signals.insert(id.name.clone());
+            }
+            Expr::Compare { lhs, rhs, .. } => {
+                num(lhs, out);
+                num(rhs, out);
+            }
+        }
+    }
+    let mut out = TriggerReads::default();
+    walk(e, &mut out);
+    out
+}
+
+// -----------------------------
+// Evaluator
+// -----------------------------
+
+/// Everything a trigger may observe.
+#[derive(Debug, Clone, Copy)]
+pub struct EvalCtx<'a> {
+    pub text: &'a str,
+    pub measured: &'a BTreeMap<String, f64>,
+    pub signals: &'a [String],
+    pub severity: &'a Severity,
+    /// Policy parameters referenced as `$name`.
+    pub params: &'a BTreeMap<String, f64>,
+    /// `Input.series`, resampled on demand by trend functions.
+    pub series: &'a Series,
+    /// Scores computed from the input, by [`KNOWN_SCORES`] name.
+    pub scores: &'a BTreeMap<String, f64>,
+}
+
+fn eval_num(e: &NumExpr, ctx: &EvalCtx) -> Option<f64> {
+    let v = match e {
+        NumExpr::Const(v) => *v,
+        NumExpr::Feature(id) if KNOWN_SCORES.contains(&id.name.as_str()) => {
+            *ctx.scores.get(&id.name)?
+        }
+        NumExpr::Feature(id) => *ctx.measured.get(&id.name)?,
+        NumExpr::Param(id) => *ctx.params.get(&id.name)?,
+        NumExpr::Trend {
+            function,
+            feature,
+            window_s,
+            ..
+        } => ctx.series.eval(*function, &feature.name, *window_s)?,
+        NumExpr::Neg(x) => -eval_num(x, ctx)?,
+        NumExpr::Bin(op, l, r) => {
+            let (l, r) = (eval_num(l, ctx)?, eval_num(r, ctx)?);
+            match op {
+                ArithOp::Add => l + r,
+                ArithOp::Sub => l - r,
+                ArithOp::Mul => l * r,
+                ArithOp::Div => l / r,
+            }
+        }
+    };
+    v.is_finite().then_some(v)
+}
+
+/// Three-valued evaluation: `None` when the outcome depends on a missing or
+/// non-finite value.
+fn eval_kleene(e: &Expr, ctx: &EvalCtx) -> Option<bool> {
+    match e {
+        Expr::Bool(v) => Some(*v),
+        Expr::Not(x) => eval_kleene(x, ctx).map(|v| !v),
+        Expr::And(xs) => {
+            let vs: Vec<Option<bool>> = xs.iter().map(|x| eval_kleene(x, ctx)).collect();
+            if vs.contains(&Some(false)) {
+                Some(false)
+            } else if vs.contains(&None) {
+                None
+            } else {
+                Some(true)
+            }
+        }
+        Expr::Or(xs) => {
+            let vs: Vec<Option<bool>> = xs.iter().map(|x| eval_kleene(x, ctx)).collect();
+            if vs.contains(&Some(true)) {
+                Some(true)
+            } else if vs.contains(&None) {
+                None
+            } else {
+                Some(false)
+            }
+        }
+        Expr::Matches { regex, .. } => Some(is_affirmed_match(ctx.text, regex)),
+        Expr::Contains { needle, .. } => Some(is_affirmed_substring(ctx.text, needle)),
+        Expr::Signal(id) => Some(ctx.signals.iter().any(|s| *s == id.name)),
+        Expr::SeverityCmp { op, level } => {
+            Some(op.holds(ctx.severity.rank() as f64, level.rank() as f64))
+        }
+        Expr::Compare { lhs, op, rhs } => {
+            let (l, r) = (eval_num(lhs, ctx)?, eval_num(rhs, ctx)?);
+            Some(op.holds(l, r))
+        }
+    }
+}
+
+/// Evaluate a type-checked trigger. Comparisons involving a missing or
+/// non-finite value are unknown, and an unknown trigger is false.
+pub fn eval_trigger(e: &Expr, ctx: &EvalCtx) -> bool {
+    eval_kleene(e, ctx).unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn eval(src: &str, measured: &[(&str, f64)], signals: &[&str]) -> bool {
+        let measured: BTreeMap<String, f64> =
+            measured.iter().map(|(k, v)| (k.to_string(), *v)).collect();
+        let signals: Vec<String> = signals.iter().map(|s| s.to_string()).collect();
+        let params = BTreeMap::from([("low".to_string(), 90.0)]);
+        let ctx = EvalCtx {
+            text: "",
+            measured: &measured,
+            signals: &signals,
+            severity: &Severity::ROUTINE,
+            params: &params,
+            series: &Series::default(),
+            scores: &BTreeMap::new(),
+        };
+        let e = compile_trigger(src, &TypeEnv::default().with_params(params.keys()))
+            .unwrap_or_else(|e| panic!("{src}: {e}"));
+        eval_trigger(&e, &ctx)
+    }
+
+    fn syntax_pos(src: &str) -> usize {
+        match parse_trigger(src) {
+            Err(e @ TriggerError::Syntax { .. }) => e.pos(),
+            other => panic!("{src}: expected a syntax error, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn parse_errors_point_at_the_offending_token() {
+        assert_eq!(syntax_pos(""), 0);
+        assert_eq!(syntax_pos("spo2_pct <"), 10);
+        assert_eq!(syntax_pos("spo2_pct < 90 hypoxemia"), 14);
+        assert_eq!(syntax_pos("(hypoxemia or tachycardia"), 25);
+        assert_eq!(syntax_pos("text contains hipox"), 5);
+        assert_eq!(syntax_pos("severity > BAD"), 11);
+        assert_eq!(syntax_pos("spo2_pct @ 90"), 9);
+        assert_eq!(syntax_pos("text matches /hipox"), 13);
+        assert_eq!(syntax_pos("delta(spo2_pct, 1.5) < 0"), 16);
+    }
+
+    #[test]
+    fn type_errors_name_the_identifier() {
+        let env = TypeEnv::default();
+        assert!(matches!(
+            compile_trigger("spo2 < 90", &env),
+            Err(TriggerError::UnknownFeature { pos: 0, .. })
+        ));
+        assert!(matches!(
+            compile_trigger("hypoxaemia", &env),
+            Err(TriggerError::UnknownSignal { pos: 0, .. })
+        ));
+        assert!(matches!(
+            compile_trigger("spo2_pct < $low", &env),
+            Err(TriggerError::UnknownParam { pos: 11, .. })
+        ));
+        assert!(matches!(
+            compile_trigger("spo2_pct", &env),
+            Err(TriggerError::Type { pos: 0, .. })
+        ));
+        assert!(matches!(
+            compile_trigger("delta(news2, 900) > 1", &env),
+            Err(TriggerError::Type { pos: 6, .. })
+        ));
+    }
+
+    #[test]
+    fn and_binds_tighter_than_or() {
+        assert!(eval(
+            "hypoxemia or tachycardia and fever",
+            &[],
+            &["hypoxemia"]
+        ));
+        assert!(!eval(
+            "(hypoxemia or tachycardia) and fever",
+            &[],
+            &["hypoxemia"]
+        ));
+    }
+
+    #[test]
+    fn not_binds_tighter_than_and() {
+        assert!(!eval("not hypoxemia and tachycardia", &[], &["hypoxemia"]));
+        assert!(eval("not (hypoxemia and tachycardia)", &[], &["hypoxemia"]));
+    }
+
+    #[test]
+    fn arithmetic_follows_the_usual_precedence() {
+        let m = [("hr_bpm", 120.0), ("sbp_mmhg", 100.0)];
+        assert!(eval("hr_bpm - 10 * 2 == 100", &m, &[]));
+        assert!(eval("(hr_bpm - 10) * 2 == 220", &m, &[]));
+        assert!(eval("hr_bpm / sbp_mmhg > 1", &m, &[]));
+        assert!(eval("-hr_bpm < -100", &m, &[]));
+        assert!(eval("(hr_bpm > 100)", &m, &[]));
+    }
+
+    #[test]
+    fn missing_values_never_fire_even_under_not() {
+        assert!(!eval("spo2_pct < 90", &[], &[]));
+        assert!(!eval("not spo2_pct < 90", &[], &[]));
+        assert!(!eval("not not spo2_pct < 90", &[], &[]));
+        assert!(!eval(
+            "not (spo2_pct < 90 and hr_bpm > 100)",
+            &[("hr_bpm", 120.0)],
+            &[]
+        ));
+        assert!(eval("not spo2_pct < $low", &[("spo2_pct", 95.0)], &[]));
+    }
+
+    #[test]
+    fn three_valued_and_or_settle_when_one_side_decides() {
+        assert!(eval("spo2_pct < 90 or tachycardia", &[], &["tachycardia"]));
+        assert!(eval("not (spo2_pct < 90 and tachycardia)", &[], &[]));
+        assert!(!eval("spo2_pct < 90 or tachycardia", &[], &[]));
+    }
+
+    #[test]
+    fn division_by_zero_is_unknown() {
+        let m = [("hr_bpm", 120.0), ("sbp_mmhg", 0.0)];
+        assert!(!eval("hr_bpm / sbp_mmhg > 1", &m, &[]));
+        assert!(!eval("not hr_bpm / sbp_mmhg > 1", &m, &[]));
+    }
+
+    #[test]
+    fn reads_separate_signals_features_and_global_inputs() {
+        let reads =
+            trigger_reads(&parse_trigger("hypoxemia and delta(spo2_pct, 900) < -6").unwrap());
+        assert_eq!(reads.signals, BTreeSet::from(["hypoxemia".to_string()]));
+        assert_eq!(reads.features, BTreeSet::from(["spo2_pct".to_string()]));
+        assert!(!reads.global);
+        assert!(trigger_reads(&parse_trigger("news2 >= 5").unwrap()).global);
+        assert!(trigger_reads(&parse_trigger("text contains \"x\"").unwrap()).global);
+    }
+
+    #[test]
+    fn nesting_is_bounded() {
+        let nots = |n: usize| format!("{}hypoxemia", "not ".repeat(n));
+        assert!(parse_trigger(&nots(MAX_TRIGGER_NESTING)).is_ok());
+        syntax_pos(&nots(MAX_TRIGGER_NESTING + 1));
+        // Deep enough to overflow the stack without the limit.
+        syntax_pos(&nots(30_000));
+        let parens = |n: usize| format!("{}spo2_pct{} < 90", "(".repeat(n), ")".repeat(n));
+        assert!(parse_trigger(&parens(MAX_TRIGGER_NESTING)).is_ok());
+        syntax_pos(&parens(MAX_TRIGGER_NESTING + 1));
+        syntax_pos(&format!("{}hypoxemia", "(".repeat(30_000)));
+        syntax_pos(&format!("spo2_pct < {}90", "-".repeat(30_000)));
+    }
+}
@@ -0,0 +1,331 @@
+//! Policy authenticity: detached Ed25519 signatures and the trust store.
+//!
+//! A policy signature covers the policy hash (blake3 of the canonical policy
+//! JSON), so it survives YAML reformatting but not any semantic change. An
+//! [`Engine`](crate::Engine) configured with a [`TrustStore`] refuses to apply
+//! a policy that is unsigned or signed by a key outside the store, and records
+//! the accepted signer of every policy in the `ProofPack`.
+
+use crate::{
+    blake3_hash_json, json_canonical, policy_hash, sign_bytes, verify_bytes, verifying_key_to_hex,
+    Policy,
+};
+use ed25519_dalek::{Signature, SigningKey, VerifyingKey};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::fs;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum TrustError {
+    #[error("trust store: {0}")]
+    Yaml(#[from] serde_yaml::Error),
+    #[error("trust store I/O: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("trust store: duplicate key_id `{0}`")]
+    DuplicateKey(String),
+    #[error("key `{key_id}`: {msg}")]
+    BadKey { key_id: String, msg: String },
+    #[error("policy `{protocol_id}` is unsigned")]
+    Unsigned { protocol_id: String },
+    #[error("policy `{protocol_id}` is signed by untrusted key `{key_id}`")]
+    UntrustedKey { protocol_id: String, key_id: String },
+    #[error("policy `{protocol_id}`: signature does not match the policy content")]
+    BadSignature { protocol_id: String },
+    #[error("policy signature: unsupported algorithm `{0}`")]
+    Algorithm(String),
+}
+
+/// Detached signature over a policy, stored next to it as `<policy>.sig`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PolicySignature {
+    pub alg: String,
+    /// Identifier of the signing key in the trust store.
+    pub key_id: String,
+    pub sig: String,
+}
+
+/// A governance key allowed to sign policies.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TrustedKey {
+    pub key_id: String,
+    /// Person or body the key belongs to (e.g. a clinical governance board).
+    pub owner: String,
+    pub pubkey: String,
+}
+
+/// Accepted signer of one policy, as recorded in the `ProofPack`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PolicySigner {
+    pub protocol_id: String,
+    pub policy_hash: String,
+    pub key_id: String,
+    pub owner: String,
+    pub pubkey: String,
+    pub sig: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct TrustStore {
+    pub keys: Vec<TrustedKey>,
+}
+
+/// Bytes covered by a policy signature, domain-separated from proof packs.
+pub fn policy_signing_bytes(policy_hash: &str) -> anyhow::Result<String> {
+    json_canonical(&json!({ "icu_sl4_policy": policy_hash }))
+}
+
+pub fn sign_policy(p: &Policy, key_id: &str, sk: &SigningKey) -> anyhow::Result<PolicySignature> {
+    let msg = policy_signing_bytes(&policy_hash(p)?)?;
+    Ok(PolicySignature {
+        alg: "Ed25519".into(),
+        key_id: key_id.to_string(),
+        sig: hex::encode(sign_bytes(sk, msg.as_bytes()).to_bytes()),
+    })
+}
+
+/// Conventional location of the detached signature of `policy_path`.
+pub fn policy_sig_path(policy_path: &Path) -> PathBuf {
+    let mut s = policy_path.as_os_str().to_owned();
+    s.push(".sig");
+    PathBuf::from(s)
+}
+
+/// Conventional location of the signature of `effective`, the policy at
+/// `policy_path` (`base`) resolved with overlays: `<policy>.sig` when no
+/// overlay was applied, else `<policy>.<chain>.sig`, `<chain>` being the first
+/// 16 hex digits of the hash of the applied overlay chain. Each chain thus has
+/// its own signature next to the base's.
+pub fn effective_sig_path(
+    policy_path: &Path,
+    base: &Policy,
+    effective: &Policy,
+) -> anyhow::Result<PathBuf> {
+    let applied = effective.overlays.get(base.overlays.len()..).unwrap_or(&[]);
+    if applied.is_empty() {
+        return Ok(policy_sig_path(policy_path));
+    }
+    let hash = blake3_hash_json(&serde_json::to_value(applied)?)?;
+    let chain = hash.trim_start_matches("blake3:");
+    let mut s = policy_path.as_os_str().to_owned();
+    s.push(format!(".{}.sig", &chain[..16.min(chain.len())]));
+    Ok(PathBuf::from(s))
+}
+
+pub fn load_policy_signature(path: &Path) -> anyhow::Result<PolicySignature> {
+    Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+}
+
+fn decode_pubkey(key_id: &str, hex_key: &str) -> Result<VerifyingKey, TrustError> {
+    let bad = |msg: String| TrustError::BadKey {
+        key_id: key_id.to_string(),
+        msg,
+    };
+    let bytes: [u8; 32] = hex::decode(hex_key)
+        .map_err(|e| bad(format!("pubkey hex: {e}")))?
+        .try_into()
+        .map_err(|_| bad("pubkey must be 32 bytes".into()))?;
+    VerifyingKey::from_bytes(&bytes).map_err(|e| bad(e.to_string()))
+}
+
+/// Check `sig` against `pubkey` over `policy_hash`.
+pub(crate) fn verify_policy_hash_sig(
+    policy_hash: &str,
+    pubkey: &VerifyingKey,
+    sig_hex: &str,
+) -> anyhow::Result<()> {
+    let sig: [u8; 64] = hex::decode(sig_hex)?
+        .try_into()
+        .map_err(|_| anyhow::anyhow!("bad sig length"))?;
+    let msg = policy_signing_bytes(policy_hash)?;
+    verify_bytes(pubkey, msg.as_bytes(), &Signature::from_bytes(&sig))
+}
+
+impl TrustStore {
+    pub fn from_yaml(s: &str) -> Result<Self, TrustError> {
+        let store: TrustStore = serde_yaml::from_str(s)?;
+        let mut seen = std::collections::BTreeSet::new();
+        for k in &store.keys {
+            if !seen.insert(k.key_id.as_str()) {
+                return Err(TrustError::DuplicateKey(k.key_id.clone()));
+            }
+            decode_pubkey(&k.key_id, &k.pubkey)?;
+        }
+        Ok(store)
+    }
+
+    /// Load a trust store file (YAML or JSON).
+    pub fn load(path: &Path) -> Result<Self, TrustError> {
+        Self::from_yaml(&fs::read_to_string(path)?)
+    }
+
+    pub fn key(&self, key_id: &str) -> Option<&TrustedKey> {
+        self.keys.iter().find(|k| k.key_id == key_id)
+    }
+
+    /// Accept `p` only if `sig` is a valid signature by a trusted key.
+    pub fn verify(
+        &self,
+        p: &Policy,
+        sig: Option<&PolicySignature>,
+    ) -> Result<PolicySigner, TrustError> {
+        let protocol_id = p.protocol_id.clone();
+        let sig = sig.ok_or_else(|| TrustError::Unsigned {
+            protocol_id: protocol_id.clone(),
+        })?;
+        if sig.alg != "Ed25519" {
+            return Err(TrustError::Algorithm(sig.alg.clone()));
+        }
+        let key = self
+            .key(&sig.key_id)
+            .ok_or_else(|| TrustError::UntrustedKey {
+                protocol_id: protocol_id.clone(),
+                key_id: sig.key_id.clone(),
+            })?;
+        let vk = decode_pubkey(&key.key_id, &key.pubkey)?;
+        let hash = policy_hash(p).map_err(|_| TrustError::BadSignature {
+            protocol_id: protocol_id.clone(),
+        })?;
+        verify_policy_hash_sig(&hash, &vk, &sig.sig).map_err(|_| TrustError::BadSignature {
+            protocol_id: protocol_id.clone(),
+        })?;
+        Ok(PolicySigner {
+            protocol_id,
+            policy_hash: hash,
+            key_id: key.key_id.clone(),
+            owner: key.owner.clone(),
+            pubkey: verifying_key_to_hex(&vk),
+            sig: sig.sig.clone(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_policy_yaml;
+
+    const POLICY: &str = "\
+protocol_id: hypox
+version: 1.0.0
+jurisdiction: PT
+source: test
+triggers:
+  - spo2_pct < 90
+severity: CRITICAL
+hazards:
+  CRITICAL: HYPOXEMIA_CRITICAL
+  URGENT: HYPOXEMIA_MODERATE
+actions:
+  - name: call_attending
+    max_delay_s: 30
+";
+
+    fn key(seed: u8) -> SigningKey {
+        SigningKey::from_bytes(&[seed; 32])
+    }
+
+    fn store() -> TrustStore {
+        TrustStore {
+            keys: vec![TrustedKey {
+                key_id: "cg-2026".into(),
+                owner: "Clinical governance".into(),
+                pubkey: verifying_key_to_hex(&key(1).verifying_key()),
+            }],
+        }
+    }
+
+    #[test]
+    fn trusted_signature_is_accepted_and_recorded() {
+        let p = parse_policy_yaml(POLICY).unwrap();
+        let sig = sign_policy(&p, "cg-2026", &key(1)).unwrap();
+        let signer = store().verify(&p, Some(&sig)).unwrap();
+        assert_eq!(signer.key_id, "cg-2026");
+        assert_eq!(signer.policy_hash, policy_hash(&p).unwrap());
+        assert_eq!(signer.sig, sig.sig);
+    }
+
+    #[test]
+    fn unsigned_policy_is_refused() {
+        let p = parse_policy_yaml(POLICY).unwrap();
+        assert!(matches!(
+            store().verify(&p, None),
+            Err(TrustError::Unsigned { protocol_id }) if protocol_id == "hypox"
+        ));
+    }
+
+    #[test]
+    fn key_outside_the_store_is_refused() {
+        let p = parse_policy_yaml(POLICY).unwrap();
+        let sig = sign_policy(&p, "rogue", &key(2)).unwrap();
+        assert!(matches!(
+            store().verify(&p, Some(&sig)),
+            Err(TrustError::UntrustedKey { key_id, .. }) if key_id == "rogue"
+        ));
+        // A trusted key_id does not make another key's signature valid.
+        let forged = sign_policy(&p, "cg-2026", &key(2)).unwrap();
+        assert!(matches!(
+            store().verify(&p, Some(&forged)),
+            Err(TrustError::BadSignature { .. })
+        ));
+    }
+
+    #[test]
+    fn tampered_policy_is_refused() {
+        let p = parse_policy_yaml(POLICY).unwrap();
+        let sig = sign_policy(&p, "cg-2026", &key(1)).unwrap();
+        let mut tampered = p.clone();
+        tampered.actions[0].max_delay_s = 3600;
+        assert!(matches!(
+            store().verify(&tampered, Some(&sig)),
+            Err(TrustError::BadSignature { .. })
+        ));
+        // Reformatting the YAML keeps the signature valid.
+        let reformatted =
+            parse_policy_yaml(&POLICY.replace("  - spo2_pct < 90", "  - 'spo2_pct < 90'")).unwrap();
+        assert!(store().verify(&reformatted, Some(&sig)).is_ok());
+    }
+
+    #[test]
+    fn signatures_are_domain_separated() {
+        let p = parse_policy_yaml(POLICY).unwrap();
+        let hash = policy_hash(&p).unwrap();
+        // A signature over the bare hash, as another protocol might produce,
+        // is not a policy signature.
+        let bare = PolicySignature {
+            alg: "Ed25519".into(),
+            key_id: "cg-2026".into(),
+            sig: hex::encode(sign_bytes(&key(1), hash.as_bytes()).to_bytes()),
+        };
+        assert!(matches!(
+            store().verify(&p, Some(&bare)),
+            Err(TrustError::BadSignature { .. })
+        ));
+        assert_ne!(policy_signing_bytes(&hash).unwrap(), hash);
+        let other_alg = PolicySignature {
+            alg: "RSA".into(),
+            ..sign_policy(&p, "cg-2026", &key(1)).unwrap()
+        };
+        assert!(matches!(
+            store().verify(&p, Some(&other_alg)),
+            Err(TrustError::Algorithm(a)) if a == "RSA"
+        ));
+    }
+
+    #[test]
+    fn store_rejects_duplicate_and_malformed_keys() {
+        let pk = verifying_key_to_hex(&key(1).verifying_key());
+        let dup = format!(
+            "keys:\n  - {{key_id: a, owner: x, pubkey: {pk}}}\n  - {{key_id: a, owner: y, pubkey: {pk}}}\n"
+        );
+        assert!(matches!(
+            TrustStore::from_yaml(&dup),
+            Err(TrustError::DuplicateKey(k)) if k == "a"
+        ));
+        assert!(matches!(
+            TrustStore::from_yaml("keys:\n  - {key_id: a, owner: x, pubkey: abcd}\n"),
+            Err(TrustError::BadKey { .. })
+        ));
+    }
+}
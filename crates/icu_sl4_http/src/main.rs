@@ -17,6 +17,42 @@ pub struct InputSchema {
     #[schema(example = r#"{"spo2_pct": 85, "hr_bpm": 125}"#)]
     #[serde(default)]
     pub measured: std::collections::BTreeMap<String, f64>,
+    /// Amostra anterior de cada parâmetro, para detetar quedas súbitas (artefacto)
+    #[schema(example = r#"{"spo2_pct": 97}"#)]
+    #[serde(default)]
+    pub previous: std::collections::BTreeMap<String, f64>,
+    /// Hora (RFC 3339) e origem de cada valor medido
+    #[schema(value_type = Object, example = r#"{"spo2_pct": {"at": "2025-01-01T10:00:00Z", "source": "monitor:cama-12"}}"#)]
+    #[serde(default)]
+    pub observations: std::collections::BTreeMap<String, serde_json::Value>,
+    /// Amostras recentes (RFC 3339) de cada parâmetro, para funções de tendência
+    #[schema(value_type = Object, example = r#"{"spo2_pct": [{"at": "2025-01-01T09:45:00Z", "value": 97}, {"at": "2025-01-01T10:00:00Z", "value": 90}]}"#)]
+    #[serde(default)]
+    pub series: std::collections::BTreeMap<String, serde_json::Value>,
+    /// Contexto do doente, para perfis de limiares (DPOC, pediatria, basal crónica)
+    pub patient: Option<PatientSchema>,
+    /// Valores medidos cerca de 24 horas antes, para o delta do SOFA
+    #[schema(example = r#"{"platelets_k_ul": 180, "creatinine_mg_dl": 1.0}"#)]
+    #[serde(default)]
+    pub measured_24h: std::collections::BTreeMap<String, f64>,
+    /// Diurese horária (mL), `at` no fim de cada hora, para o estadiamento KDIGO
+    #[schema(value_type = Object, example = r#"[{"at": "2025-01-01T09:00:00Z", "value": 20}, {"at": "2025-01-01T10:00:00Z", "value": 15}]"#)]
+    #[serde(default)]
+    pub urine_output: Vec<serde_json::Value>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct PatientSchema {
+    #[schema(example = 72)]
+    pub age_years: Option<f64>,
+    #[schema(example = 68.5)]
+    pub weight_kg: Option<f64>,
+    /// Condições conhecidas, em minúsculas
+    #[schema(example = r#"["copd"]"#)]
+    pub conditions: Option<Vec<String>>,
+    /// Valor basal documentado de cada parâmetro
+    #[schema(example = r#"{"spo2_pct": 88}"#)]
+    pub baseline: Option<std::collections::BTreeMap<String, f64>>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
@@ -27,6 +63,39 @@ pub struct ActionSchema {
     pub max_delay_s: u64,
     #[schema(example = 0)]
     pub deadline_s: Option<u64>,
+    #[schema(example = "severe_hypoxemia")]
+    pub when: Option<String>,
+    /// Ações que não podem ser emitidas junto com esta (fusão do PolicySet)
+    #[schema(example = r#"["fluid_challenge"]"#)]
+    pub conflicts_with: Option<Vec<String>>,
+    /// Ids das referências normativas que justificam a ação
+    #[schema(example = r#"["ATS-2020"]"#)]
+    pub cites: Option<Vec<String>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct CitationSchema {
+    #[schema(example = "ATS-2020")]
+    pub id: String,
+    #[schema(example = "ATS Clinical Practice Guideline: Oxygen Therapy")]
+    pub title: String,
+    #[schema(example = "3.2")]
+    pub section: Option<String>,
+    #[schema(example = 2020)]
+    pub year: Option<u16>,
+    pub uri: Option<String>,
+    pub doi: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct NormativeLinksSchema {
+    pub references: Vec<CitationSchema>,
+    /// Ação da decisão -> ids das referências
+    #[schema(example = r#"{"increase_O2_100": ["ATS-2020"]}"#)]
+    pub actions: Option<std::collections::BTreeMap<String, Vec<String>>>,
+    /// Sinal do AST -> ids das referências
+    #[schema(example = r#"{"hypoxemia": ["ATS-2020"]}"#)]
+    pub signals: Option<std::collections::BTreeMap<String, Vec<String>>>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
@@ -45,7 +114,175 @@ pub struct AstSchema {
     #[schema(example = r#"["hypoxemia_acute"]"#)]
     pub protocols: Vec<String>,
     pub actions: Vec<ActionSchema>,
-    pub normative: Option<std::collections::BTreeMap<String, String>>,
+    pub normative: Option<NormativeLinksSchema>,
+    /// Menções no texto negadas, hipotéticas ou históricas (não dispararam)
+    pub negated_findings: Option<Vec<NegatedFindingSchema>>,
+    /// Sinais vitais lidos do texto; só entram na decisão quando não medidos
+    pub text_values: Option<Vec<TextValueSchema>>,
+    /// Valores medidos descartados como artefacto de sensor
+    pub artifacts: Option<Vec<SensorArtifactSchema>>,
+    /// Valores medidos descartados por serem mais antigos que `max_age_s`
+    pub stale: Option<Vec<StaleValueSchema>>,
+    /// Perfil de limiares do catálogo escolhido a partir do contexto do doente
+    #[schema(example = "copd")]
+    pub profile: Option<String>,
+    /// Faixa etária pediátrica cujos limites de sinais vitais foram usados
+    #[schema(example = "infant")]
+    pub age_band: Option<String>,
+    /// Valores calculados a partir dos medidos, como o índice de choque
+    pub derived: Option<Vec<DerivedValueSchema>>,
+    /// Categoria de oxigenação de Berlim (não é um diagnóstico de SDRA)
+    pub berlin: Option<BerlinSchema>,
+    /// Estádio KDIGO de lesão renal aguda e os limiares que o justificam
+    pub aki: Option<AkiStagingSchema>,
+    /// Pontuação SOFA por órgão, quando algum órgão foi avaliado
+    pub sofa: Option<SofaSchema>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct AkiCriterionSchema {
+    #[schema(example = "aki_stage_2")]
+    pub signal: String,
+    #[schema(example = "creatinine_ratio")]
+    pub feature: String,
+    #[schema(example = 2.3)]
+    pub value: f64,
+    #[schema(example = ">=")]
+    pub relation: String,
+    #[schema(example = 2.0)]
+    pub threshold: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct AkiStagingSchema {
+    /// Estádio mais alto atingido (0 a 3)
+    #[schema(example = 2)]
+    pub stage: u8,
+    pub criteria: Option<Vec<AkiCriterionSchema>>,
+    /// Parâmetros do estadiamento que não puderam ser calculados
+    #[schema(example = r#"["urine_ml_kg_h_24h"]"#)]
+    pub missing: Option<Vec<String>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct BerlinSchema {
+    /// `mild`, `moderate` ou `severe`
+    #[schema(example = "moderate")]
+    pub category: String,
+    /// `pf_ratio`, ou `sf_ratio` quando não há PaO2
+    #[schema(example = "pf_ratio")]
+    pub ratio_feature: String,
+    #[schema(example = 140)]
+    pub ratio: f64,
+    /// Ausente quando a PEEP não foi medida
+    #[schema(example = 10)]
+    pub peep_cmh2o: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct DerivedValueSchema {
+    #[schema(example = "shock_index")]
+    pub feature: String,
+    #[schema(example = 1.37)]
+    pub value: f64,
+    /// Parâmetros medidos usados no cálculo
+    #[schema(example = r#"["hr_bpm", "sbp_mmhg"]"#)]
+    pub from: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct SofaComponentSchema {
+    /// `respiration`, `coagulation`, `liver`, `cardiovascular`, `cns` ou `renal`
+    #[schema(example = "coagulation")]
+    pub organ: String,
+    #[schema(example = 2)]
+    pub points: u8,
+    /// Parâmetro que decidiu a pontuação (`pao2_fio2` para a respiração)
+    #[schema(example = "platelets_k_ul")]
+    pub feature: String,
+    #[schema(example = 85)]
+    pub value: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct SofaSchema {
+    #[schema(example = 6)]
+    pub total: u8,
+    pub components: Vec<SofaComponentSchema>,
+    /// Órgãos sem nenhum parâmetro medido
+    #[schema(example = r#"["liver"]"#)]
+    pub missing: Option<Vec<String>>,
+    /// Variação do total face a `measured_24h`, nos órgãos avaliados em ambos
+    #[schema(example = 3)]
+    pub delta_24h: Option<i16>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct StaleValueSchema {
+    #[schema(example = "spo2_pct")]
+    pub feature: String,
+    #[schema(example = 85)]
+    pub value: f64,
+    #[schema(example = "2025-01-01T04:00:00Z")]
+    pub observed_at: String,
+    #[schema(example = "monitor:cama-12")]
+    pub source: Option<String>,
+    #[schema(example = 21600)]
+    pub age_s: u64,
+    #[schema(example = 900)]
+    pub max_age_s: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct SensorArtifactSchema {
+    #[schema(example = "spo2_pct")]
+    pub feature: String,
+    #[schema(example = 140)]
+    pub value: f64,
+    /// `out_of_range`, `sudden_drop` ou `no_pulse`
+    #[schema(example = "out_of_range")]
+    pub rule: String,
+    pub previous: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct TextValueSchema {
+    #[schema(example = "spo2_pct")]
+    pub feature: String,
+    #[schema(example = 85)]
+    pub value: f64,
+    #[schema(example = "saturação 85%")]
+    pub mention: String,
+    /// Valor medido do mesmo parâmetro, que tem precedência
+    #[schema(example = 96)]
+    pub measured: Option<f64>,
+    /// Texto e valor medido discordam além da tolerância
+    pub conflict: Option<bool>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct NegatedFindingSchema {
+    #[schema(example = "hypoxemia")]
+    pub signal: String,
+    /// `negated`, `hypothetical` ou `historical`
+    #[schema(example = "negated")]
+    pub scope: String,
+    #[schema(example = "hipoxemia")]
+    pub mention: String,
+    #[schema(example = "sem")]
+    pub cue: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ActionConflictSchema {
+    #[schema(example = "vasopressor_review")]
+    pub kept: String,
+    #[schema(example = "hemodynamic_shock")]
+    pub kept_protocol: String,
+    #[schema(example = "fluid_challenge")]
+    pub suppressed: String,
+    #[schema(example = "sepsis_bundle")]
+    pub suppressed_protocol: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
@@ -57,6 +294,10 @@ pub struct DecisionSchema {
     #[schema(example = r#"["HYPOXEMIA_CRITICAL"]"#)]
     pub hazards: Vec<String>,
     pub watchdog_armed: bool,
+    #[schema(example = r#"["spo2_pct < 90"]"#)]
+    pub triggers_fired: Vec<String>,
+    /// Ações descartadas pela regra de conflitos do PolicySet
+    pub conflicts: Option<Vec<ActionConflictSchema>>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
@@ -65,7 +306,26 @@ pub struct DecideReq {
     pub input: Input,
     /// Política em YAML (texto)
     #[schema(example = "---\nprotocol_id: hypoxemia_acute\nversion: 1.0.0\n")]
-    pub policy_yaml: String,
+    #[serde(default)]
+    pub policy_yaml: Option<String>,
+    /// Assinatura destacada da política (`icu-sl4 policy sign`)
+    #[schema(value_type = Option<Object>)]
+    #[serde(default)]
+    pub policy_sig: Option<PolicySignature>,
+    /// Conjunto de políticas (uma YAML por item), avaliadas juntas como PolicySet
+    #[serde(default)]
+    pub policy_set_yaml: Option<Vec<String>>,
+    /// Assinaturas do PolicySet, na mesma ordem de `policy_set_yaml`
+    #[schema(value_type = Option<Vec<Object>>)]
+    #[serde(default)]
+    pub policy_set_sig: Option<Vec<PolicySignature>>,
+    /// Overlays (jurisdição → hospital → unidade) aplicados às políticas que estendem
+    #[serde(default)]
+    pub policy_overlays_yaml: Option<Vec<String>>,
+    /// Catálogo de sinais em YAML (opcional); por omissão usa o catálogo embutido.
+    /// Recusado (403) quando há trust store: alteraria limiares de políticas assinadas
+    #[serde(default)]
+    pub signal_catalog_yaml: Option<String>,
     /// Chave secreta Ed25519 (hex de 32 bytes) — DEV ONLY; em produção use HSM/KMS
     #[schema(example = "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f")]
     pub keypair_secret_hex: String,
@@ -149,12 +409,100 @@ pub struct FhirAnnotation {
 pub struct FhirDecideReq {
     pub observation: FhirObservation,
     pub policy_yaml: String,
+    #[schema(value_type = Option<Object>)]
+    #[serde(default)]
+    pub policy_sig: Option<PolicySignature>,
     pub keypair_secret_hex: String,
     pub binary_hash: Option<String>,
     pub config_hash: Option<String>,
     pub ledger_path: Option<String>,
 }
 
+// -----------------------------
+// Policy trust
+// -----------------------------
+
+/// Server configuration, read once from the environment at startup.
+struct AppConfig {
+    /// `ICU_SL4_TRUST_STORE`: keys allowed to sign policies.
+    trust: Option<TrustStore>,
+    /// `ICU_SL4_ALLOW_UNSIGNED_POLICIES=1`: DEV ONLY, accept unsigned policies
+    /// when no trust store is configured.
+    allow_unsigned: bool,
+}
+
+impl AppConfig {
+    fn from_env() -> std::io::Result<Self> {
+        let trust = match std::env::var("ICU_SL4_TRUST_STORE") {
+            Ok(path) => Some(
+                TrustStore::load(std::path::Path::new(&path))
+                    .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?,
+            ),
+            Err(_) => None,
+        };
+        let allow_unsigned = std::env::var("ICU_SL4_ALLOW_UNSIGNED_POLICIES").as_deref() == Ok("1");
+        Ok(AppConfig {
+            trust,
+            allow_unsigned,
+        })
+    }
+
+    /// Authenticate a policy before `parse_policy_yaml` compiles its triggers,
+    /// so an unauthenticated caller never reaches the trigger parser. The
+    /// signature covers the policy as deserialized, which needs no
+    /// compilation; with overlays it covers the effective policy, so only its
+    /// key is checked here and the engine verifies the rest.
+    fn authenticate(
+        &self,
+        yaml: &str,
+        sig: Option<&PolicySignature>,
+        overlays: bool,
+    ) -> Result<(), HttpResponse> {
+        let refused =
+            |e: TrustError| HttpResponse::Forbidden().body(format!("policy refused: {e}"));
+        let Some(trust) = &self.trust else {
+            return match self.allow_unsigned {
+                true => Ok(()),
+                false => Err(HttpResponse::Forbidden()
+                    .body("no policy trust store configured (set ICU_SL4_TRUST_STORE)")),
+            };
+        };
+        let raw: Policy = serde_yaml::from_str(yaml)
+            .map_err(|e| HttpResponse::BadRequest().body(format!("policy parse error: {e}")))?;
+        if !overlays {
+            return trust.verify(&raw, sig).map(|_| ()).map_err(refused);
+        }
+        match sig {
+            None => Err(refused(TrustError::Unsigned {
+                protocol_id: raw.protocol_id,
+            })),
+            Some(s) if trust.key(&s.key_id).is_none() => Err(refused(TrustError::UntrustedKey {
+                protocol_id: raw.protocol_id,
+                key_id: s.key_id.clone(),
+            })),
+            Some(_) => Ok(()),
+        }
+    }
+
+    /// Attach the trust store to `engine`, or refuse if policies cannot be authenticated.
+    fn engine(&self, engine: Engine) -> Result<Engine, HttpResponse> {
+        match (&self.trust, self.allow_unsigned) {
+            (Some(t), _) => Ok(engine.with_trust_store(t.clone())),
+            (None, true) => Ok(engine),
+            (None, false) => Err(HttpResponse::Forbidden()
+                .body("no policy trust store configured (set ICU_SL4_TRUST_STORE)")),
+        }
+    }
+}
+
+/// Untrusted policies are refused with 403; other decision errors are 400.
+fn decision_error(e: anyhow::Error) -> HttpResponse {
+    match e.downcast_ref::<TrustError>() {
+        Some(t) => HttpResponse::Forbidden().body(format!("policy refused: {t}")),
+        None => HttpResponse::BadRequest().body(format!("decision error: {e}")),
+    }
+}
+
 // -----------------------------
 // FHIR to Input conversion
 // -----------------------------
@@ -247,7 +595,29 @@ fn fhir_observation_to_input(obs: &FhirObservation) -> Input {
         text_parts.join(", ")
     };
 
-    Input { text, measured }
+    // Every value shares the Observation's time and identity
+    let observation = Observation {
+        at: obs.effective_date_time.clone(),
+        source: Some(format!(
+            "fhir:Observation/{}",
+            obs.id.as_deref().unwrap_or("unknown")
+        )),
+    };
+    let observations = measured
+        .keys()
+        .map(|k| (k.clone(), observation.clone()))
+        .collect();
+
+    Input {
+        text,
+        measured,
+        previous: Default::default(),
+        observations,
+        series: Default::default(),
+        patient: None,
+        measured_24h: Default::default(),
+        urine_output: Default::default(),
+    }
 }
 
 // -----------------------------
@@ -260,15 +630,74 @@ fn fhir_observation_to_input(obs: &FhirObservation) -> Input {
     request_body = DecideReq,
     responses(
         (status = 200, description = "Decision made successfully", body = serde_json::Value),
-        (status = 400, description = "Bad request")
+        (status = 400, description = "Bad request"),
+        (status = 403, description = "Policy unsigned or not trusted, or a signal catalog sent while a trust store is configured")
     ),
     tag = "Decision"
 )]
 #[post("/decide")]
-async fn decide_ep(body: web::Json<DecideReq>) -> impl Responder {
-    let policy: Policy = match serde_yaml::from_str(&body.policy_yaml) {
-        Ok(p) => p,
-        Err(e) => return HttpResponse::BadRequest().body(format!("policy parse error: {e}")),
+async fn decide_ep(cfg: web::Data<AppConfig>, body: web::Json<DecideReq>) -> impl Responder {
+    let set_sigs = body.policy_set_sig.clone().unwrap_or_default();
+    let signed = body
+        .policy_yaml
+        .iter()
+        .map(|y| (y, body.policy_sig.clone()))
+        .chain(
+            body.policy_set_yaml
+                .iter()
+                .flatten()
+                .enumerate()
+                .map(|(i, y)| (y, set_sigs.get(i).cloned())),
+        );
+    let overlaid = body
+        .policy_overlays_yaml
+        .as_ref()
+        .is_some_and(|o| !o.is_empty());
+    let mut entries = Vec::new();
+    for (y, signature) in signed {
+        if let Err(resp) = cfg.authenticate(y, signature.as_ref(), overlaid) {
+            return resp;
+        }
+        match parse_policy_yaml(y) {
+            Ok(policy) => entries.push(PolicyEntry {
+                policy,
+                path: None,
+                signature,
+            }),
+            Err(e) => return HttpResponse::BadRequest().body(format!("policy parse error: {e}")),
+        }
+    }
+    if entries.is_empty() {
+        return HttpResponse::BadRequest().body("policy_yaml or policy_set_yaml is required");
+    }
+    let single = body.policy_set_yaml.is_none();
+    if cfg.trust.is_some() && body.signal_catalog_yaml.is_some() {
+        // Thresholds and severity rules live in the catalog; a caller-supplied
+        // one would change what a signed policy means.
+        return HttpResponse::Forbidden()
+            .body("signal_catalog_yaml is not accepted when a policy trust store is configured");
+    }
+    let engine = match &body.signal_catalog_yaml {
+        Some(y) => match SignalCatalog::from_yaml(y) {
+            Ok(c) => Engine::new(c),
+            Err(e) => return HttpResponse::BadRequest().body(format!("signal catalog error: {e}")),
+        },
+        None => Engine::default(),
+    };
+    let engine = match cfg.engine(engine) {
+        Ok(e) => e,
+        Err(resp) => return resp,
+    };
+    let mut overlays = Vec::new();
+    for y in body.policy_overlays_yaml.iter().flatten() {
+        match parse_overlay_yaml(y) {
+            Ok(o) => overlays.push(o),
+            Err(e) => return HttpResponse::BadRequest().body(format!("overlay parse error: {e}")),
+        }
+    }
+    let set = match PolicySet::from_entries(entries).and_then(|s| s.with_overlays(&overlays)) {
+        Ok(s) => s,
+        Err(e) => return HttpResponse::BadRequest().body(format!("policy set error: {e}")),
     };
 
     let sk_bytes = match hex::decode(&body.keypair_secret_hex) {
@@ -284,22 +713,33 @@ async fn decide_ep(body: web::Json<DecideReq>) -> impl Responder {
         .binary_hash
         .clone()
         .unwrap_or_else(|| "blake3:http-demo-binary".into());
-    let cfg = body
+    let config = body
         .config_hash
         .clone()
         .unwrap_or_else(|| "blake3:http-demo-config".into());
 
-    let d = match decide(&body.input, &policy, &bin, &cfg, &sk, &now) {
+    let res = if single {
+        engine.decide_entry(&body.input, &set.entries[0], &bin, &config, &sk, &now)
+    } else {
+        engine.decide_set(&body.input, &set, &bin, &config, &sk, &now)
+    };
+    let d = match res {
         Ok(v) => v,
-        Err(e) => return HttpResponse::BadRequest().body(format!("decision error: {e}")),
+        Err(e) => return decision_error(e),
     };
 
-    let decision_v = serde_json::json!({
+    let mut decision_v = serde_json::json!({
         "ast": d.ast,
         "decision": d.decision,
         "proof_pack": d.proof_pack,
         "frontier": d.frontier,
     });
+    if let Some(news2) = &d.news2 {
+        decision_v["news2"] = serde_json::json!(news2);
+    }
+    if let Some(sepsis) = &d.sepsis {
+        decision_v["sepsis"] = serde_json::json!(sepsis);
+    }
 
     if let Some(path) = &body.ledger_path {
         if let Err(e) = icu_sl4_engine::ledger_append(path, &decision_v) {
@@ -316,18 +756,33 @@ async fn decide_ep(body: web::Json<DecideReq>) -> impl Responder {
     request_body = FhirDecideReq,
     responses(
         (status = 200, description = "Decision made from FHIR Observation", body = serde_json::Value),
-        (status = 400, description = "Bad request")
+        (status = 400, description = "Bad request"),
+        (status = 403, description = "Policy unsigned or not trusted")
     ),
     tag = "FHIR"
 )]
 #[post("/fhir/observation")]
-async fn fhir_observation_ep(body: web::Json<FhirDecideReq>) -> impl Responder {
+async fn fhir_observation_ep(
+    cfg: web::Data<AppConfig>,
+    body: web::Json<FhirDecideReq>,
+) -> impl Responder {
     let input = fhir_observation_to_input(&body.observation);
 
-    let policy: Policy = match serde_yaml::from_str(&body.policy_yaml) {
-        Ok(p) => p,
+    if let Err(resp) = cfg.authenticate(&body.policy_yaml, body.policy_sig.as_ref(), false) {
+        return resp;
+    }
+    let entry = match parse_policy_yaml(&body.policy_yaml) {
+        Ok(policy) => PolicyEntry {
+            policy,
+            path: None,
+            signature: body.policy_sig.clone(),
+        },
         Err(e) => return HttpResponse::BadRequest().body(format!("policy parse error: {e}")),
     };
+    let engine = match cfg.engine(Engine::default()) {
+        Ok(e) => e,
+        Err(resp) => return resp,
+    };
 
     let sk_bytes = match hex::decode(&body.keypair_secret_hex) {
         Ok(b) if b.len() == 32 => b,
@@ -342,23 +797,29 @@ async fn fhir_observation_ep(body: web::Json<FhirDecideReq>) -> impl Responder {
         .binary_hash
         .clone()
         .unwrap_or_else(|| "blake3:http-demo-binary".into());
-    let cfg = body
+    let config = body
         .config_hash
         .clone()
         .unwrap_or_else(|| "blake3:http-demo-config".into());
 
-    let d = match decide(&input, &policy, &bin, &cfg, &sk, &now) {
+    let d = match engine.decide_entry(&input, &entry, &bin, &config, &sk, &now) {
         Ok(v) => v,
-        Err(e) => return HttpResponse::BadRequest().body(format!("decision error: {e}")),
+        Err(e) => return decision_error(e),
     };
 
-    let decision_v = serde_json::json!({
+    let mut decision_v = serde_json::json!({
         "ast": d.ast,
         "decision": d.decision,
         "proof_pack": d.proof_pack,
         "frontier": d.frontier,
         "fhir_observation_id": body.observation.id.clone(),
     });
+    if let Some(news2) = &d.news2 {
+        decision_v["news2"] = serde_json::json!(news2);
+    }
+    if let Some(sepsis) = &d.sepsis {
+        decision_v["sepsis"] = serde_json::json!(sepsis);
+    }
 
     if let Some(path) = &body.ledger_path {
         if let Err(e) = icu_sl4_engine::ledger_append(path, &decision_v) {
@@ -381,51 +842,7 @@ async fn fhir_observation_ep(body: web::Json<FhirDecideReq>) -> impl Responder {
 )]
 #[post("/verify")]
 async fn verify_ep(body: web::Json<VerifyReq>) -> impl Responder {
-    let v = &body.decision;
-    let pp = &v["proof_pack"];
-
-    let unsigned = serde_json::json!({
-        "input_hash": pp["input_hash"],
-        "ast_hash": pp["ast_hash"],
-        "policy_hash": pp["policy_hash"],
-        "binary_hash": pp["binary_hash"],
-        "config_hash": pp["config_hash"],
-        "decision_time": pp["decision_time"],
-        "tsa_token": pp["tsa_token"],
-        "sign": serde_json::Value::Null,
-        "link_prev": pp["link_prev"],
-    });
-    let canonical = match json_canonical(&unsigned) {
-        Ok(c) => c,
-        Err(e) => return HttpResponse::BadRequest().body(format!("canonical error: {e}")),
-    };
-
-    let sig_hex = match pp["sign"]["sig"].as_str() {
-        Some(s) => s,
-        None => return HttpResponse::BadRequest().body("missing sign.sig"),
-    };
-    let pub_hex = match pp["sign"]["pubkey"].as_str() {
-        Some(s) => s,
-        None => return HttpResponse::BadRequest().body("missing sign.pubkey"),
-    };
-
-    let sig_bytes = match hex::decode(sig_hex) {
-        Ok(b) => b,
-        Err(_) => return HttpResponse::BadRequest().body("bad sig hex"),
-    };
-    let pk_bytes = match hex::decode(pub_hex) {
-        Ok(b) => b,
-        Err(_) => return HttpResponse::BadRequest().body("bad pub hex"),
-    };
-
-    let sig = ed25519_dalek::Signature::from_bytes(&sig_bytes.try_into().unwrap_or([0u8; 64]));
-    let vk =
-        match ed25519_dalek::VerifyingKey::from_bytes(&pk_bytes.try_into().unwrap_or([0u8; 32])) {
-            Ok(v) => v,
-            Err(_) => return HttpResponse::BadRequest().body("bad pubkey length"),
-        };
-
-    if let Err(e) = verify_bytes(&vk, canonical.as_bytes(), &sig) {
+    if let Err(e) = verify_proof_pack(&body.decision["proof_pack"]) {
         return HttpResponse::BadRequest().body(format!("verify failed: {e}"));
     }
     HttpResponse::Ok().json(VerifyResp { ok: true })
@@ -490,9 +907,23 @@ async fn healthz() -> impl Responder {
         FhirAnnotation,
         FhirDecideReq,
         InputSchema,
+        PatientSchema,
         ActionSchema,
+        CitationSchema,
+        NormativeLinksSchema,
+        NegatedFindingSchema,
+        TextValueSchema,
+        SensorArtifactSchema,
+        StaleValueSchema,
+        DerivedValueSchema,
+        BerlinSchema,
+        AkiCriterionSchema,
+        AkiStagingSchema,
+        SofaComponentSchema,
+        SofaSchema,
         SeveritySchema,
         AstSchema,
+        ActionConflictSchema,
         DecisionSchema
     )),
     tags(
@@ -516,11 +947,18 @@ async fn main() -> std::io::Result<()> {
         .ok()
         .and_then(|s| s.parse().ok())
         .unwrap_or(8787);
+    let cfg = web::Data::new(AppConfig::from_env()?);
+    match (&cfg.trust, cfg.allow_unsigned) {
+        (Some(t), _) => println!("policy trust store: {} key(s)", t.keys.len()),
+        (None, true) => println!("WARNING: accepting unsigned policies (DEV ONLY)"),
+        (None, false) => println!("no policy trust store: /decide will refuse policies"),
+    }
     println!("icu_sl4_http listening on :{port}");
     println!("OpenAPI docs available at: http://localhost:{port}/swagger-ui/");
 
-    HttpServer::new(|| {
+    HttpServer::new(move || {
         App::new()
+            .app_data(cfg.clone())
             .service(healthz)
             .service(decide_ep)
             .service(fhir_observation_ep)
@@ -54,6 +54,74 @@ fn main() -> Result<()> {
     )));
     doc.push(elements::Break::new(1));
     doc.push(elements::Paragraph::new("Verification: Verify by recomputing the canonical JSON (sorted keys, minified), re-hashing with BLAKE3, and checking the Ed25519 signature above."));
+    if let Some(profile) = v["ast"]["profile"].as_str() {
+        doc.push(elements::Paragraph::new(format!(
+            "Patient threshold profile: {profile}"
+        )));
+    }
+    if let Some(band) = v["ast"]["age_band"].as_str() {
+        doc.push(elements::Paragraph::new(format!(
+            "Pediatric age band: {band}"
+        )));
+    }
+    if let Some(category) = v["ast"]["berlin"]["category"].as_str() {
+        let berlin = &v["ast"]["berlin"];
+        doc.push(elements::Paragraph::new(format!(
+            "Berlin oxygenation category: {category} ({} {:.0})",
+            berlin["ratio_feature"].as_str().unwrap_or("?"),
+            berlin["ratio"].as_f64().unwrap_or(f64::NAN)
+        )));
+    }
+    if let Some(stage) = v["ast"]["aki"]["stage"].as_u64().filter(|s| *s > 0) {
+        doc.push(elements::Paragraph::new(format!("KDIGO AKI stage: {stage}")));
+        for c in v["ast"]["aki"]["criteria"].as_array().into_iter().flatten() {
+            doc.push(elements::Paragraph::new(format!(
+                "  {}: {} = {:.2} {} {}",
+                c["signal"].as_str().unwrap_or("?"),
+                c["feature"].as_str().unwrap_or("?"),
+                c["value"].as_f64().unwrap_or(f64::NAN),
+                c["relation"].as_str().unwrap_or("?"),
+                c["threshold"]
+            )));
+        }
+    }
+    if let Some(total) = v["ast"]["sofa"]["total"].as_u64() {
+        let delta = v["ast"]["sofa"]["delta_24h"]
+            .as_i64()
+            .map_or(String::new(), |d| format!(" (24 h change {d:+})"));
+        doc.push(elements::Paragraph::new(format!("SOFA: {total}{delta}")));
+    }
+
+    // Recommendations and the guideline sections that back them
+    let normative = &v["ast"]["normative"];
+    if let Some(actions) = v["decision"]["actions"].as_array().filter(|a| !a.is_empty()) {
+        doc.push(elements::Break::new(1));
+        doc.push(elements::Paragraph::new("Recommendations").styled(heading_style));
+        for a in actions {
+            let name = a["name"].as_str().unwrap_or("?");
+            doc.push(elements::Paragraph::new(format!(
+                "{name} (within {}s)",
+                a["deadline_s"].as_u64().unwrap_or(0)
+            )));
+            let ids = normative["actions"][name].as_array().cloned().unwrap_or_default();
+            for id in ids.iter().filter_map(Value::as_str) {
+                let Some(c) = normative["references"]
+                    .as_array()
+                    .and_then(|refs| refs.iter().find(|c| c["id"] == id))
+                else {
+                    continue;
+                };
+                let mut label = format!("  [{id}] {}", c["title"].as_str().unwrap_or(""));
+                if let Some(y) = c["year"].as_u64() {
+                    label.push_str(&format!(" ({y})"));
+                }
+                if let Some(sec) = c["section"].as_str() {
+                    label.push_str(&format!(" §{sec}"));
+                }
+                doc.push(elements::Paragraph::new(label));
+            }
+        }
+    }
 
     let snippet: String = canonical.chars().take(1024).collect();
     doc.push(elements::Break::new(1));